@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use dan_core_n::danmaku::data::DanmakuSpawnData;
+use dan_core_n::danmaku::standard::behaviors::{
+    ACCELERATION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID, GRAVITY3_BEHAVIOR_ID,
+    MANDATORY_END_BEHAVIOR_ID, MOTION3_BEHAVIOR_ID,
+};
+use dan_core_n::danmaku::standard::sim::SimRunner;
+use dan_core_n::danmaku::standard::{StandardDataColumns, StandardSpawnData};
+use dan_core_n::form::Form;
+use enumset::EnumSet;
+use nalgebra::{UnitVector3, Vector3};
+
+const TICK_BEHAVIORS: &[&str] = &[
+    MOTION3_BEHAVIOR_ID,
+    GRAVITY3_BEHAVIOR_ID,
+    ACCELERATION3_BEHAVIOR_ID,
+    MANDATORY_END_BEHAVIOR_ID,
+];
+
+const RENDER_BEHAVIORS: &[&str] = &[
+    MOTION3_BEHAVIOR_ID,
+    APPEARANCE_BEHAVIOR_ID,
+    MANDATORY_END_BEHAVIOR_ID,
+];
+
+// Benchmarks built with the force_scalar feature show up under their own name
+const VARIANT: &str = if cfg!(feature = "force_scalar") {
+    "scalar"
+} else {
+    "simd"
+};
+
+fn spawn(
+    i: usize,
+    behaviors: &[&'static str],
+) -> DanmakuSpawnData<StandardSpawnData, StandardDataColumns> {
+    let angle = i as f32 * 0.01;
+    let forward = UnitVector3::new_normalize(Vector3::new(angle.cos(), 0.0, angle.sin()));
+
+    DanmakuSpawnData {
+        // Long enough that nothing dies while measuring
+        end_time: i16::MAX,
+        behavior_data: vec![
+            StandardSpawnData::PosX(0.0),
+            StandardSpawnData::PosY(0.0),
+            StandardSpawnData::PosZ(0.0),
+            StandardSpawnData::MotionX(forward.x * 0.1),
+            StandardSpawnData::MotionY(0.0),
+            StandardSpawnData::MotionZ(forward.z * 0.1),
+            StandardSpawnData::GravityY(-0.0001),
+            StandardSpawnData::SpeedAccel(0.001),
+            StandardSpawnData::Forward(forward),
+            StandardSpawnData::Appearance {
+                form: &Form::SPHERE,
+            },
+            StandardSpawnData::SizeX(1.0),
+            StandardSpawnData::SizeY(1.0),
+            StandardSpawnData::SizeZ(1.0),
+        ],
+        render_properties: HashMap::new(),
+        behaviors: behaviors.to_vec(),
+        next_stage_add_data: EnumSet::empty(),
+        next_stage_inherit: HashMap::new(),
+        anchor: None,
+        next_stage: vec![],
+        parent: None,
+        children: vec![],
+        family_depth: -1,
+    }
+}
+
+fn setup(count: usize, behaviors: &[&'static str]) -> SimRunner {
+    let mut sim = SimRunner::new();
+    sim.handler().reserve(behaviors, count).unwrap();
+    sim.spawn((0..count).map(|i| spawn(i, behaviors)).collect());
+    sim
+}
+
+fn tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("tick_{VARIANT}"));
+    group.sample_size(10);
+
+    for count in [10_000, 100_000, 1_000_000] {
+        // Throughput in elements shows the time spent per bullet
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            b.iter_batched_ref(
+                || setup(count, TICK_BEHAVIORS),
+                |sim| sim.handler().tick(),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+
+    group.finish();
+}
+
+fn render_data(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("render_data_{VARIANT}"));
+    group.sample_size(10);
+
+    for count in [10_000, 100_000] {
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, &count| {
+            let mut sim = setup(count, RENDER_BEHAVIORS);
+            sim.handler().tick();
+            b.iter(|| sim.handler().render_data(0.5).len())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, tick, render_data);
+criterion_main!(benches);
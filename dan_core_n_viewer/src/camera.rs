@@ -0,0 +1,250 @@
+use nalgebra::{Matrix4, Perspective3, Point2, Point3, Vector3};
+use web_time::Duration;
+use winit::event::{ElementState, KeyEvent, MouseButton, WindowEvent};
+use winit::keyboard::{KeyCode, PhysicalKey};
+
+// nalgebra produces OpenGL clip space, where z goes from -1 to 1. wgpu wants it to go from 0 to 1
+#[rustfmt::skip]
+const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.5,
+    0.0, 0.0, 0.0, 1.0,
+);
+
+// Keeps mouse-look from flipping over when looking straight up or down
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+pub struct Camera {
+    pub position: Point3<f32>,
+    pub target: Point3<f32>,
+    pub up: Vector3<f32>,
+    pub aspect: f32,
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+}
+
+impl Camera {
+    // Looks down at the origin from above and slightly behind, where the danmaku are spawned
+    pub fn overhead(aspect: f32) -> Camera {
+        Camera {
+            position: Point3::new(0.0, 2.5, 1.5),
+            target: Point3::origin(),
+            up: Vector3::y(),
+            aspect,
+            fovy: 60.0_f32.to_radians(),
+            znear: 0.1,
+            zfar: 100.0,
+        }
+    }
+
+    pub fn view(&self) -> Matrix4<f32> {
+        Matrix4::look_at_rh(&self.position, &self.target, &self.up)
+    }
+
+    pub fn projection(&self) -> Matrix4<f32> {
+        OPENGL_TO_WGPU_MATRIX
+            * Perspective3::new(self.aspect, self.fovy, self.znear, self.zfar).to_homogeneous()
+    }
+
+    pub fn view_projection(&self) -> Matrix4<f32> {
+        self.projection() * self.view()
+    }
+
+    // The ray going from the camera through a point on the screen, given in pixels
+    pub fn screen_ray(
+        &self,
+        screen_pos: Point2<f32>,
+        screen_width: f32,
+        screen_height: f32,
+    ) -> Option<(Point3<f32>, Vector3<f32>)> {
+        let inverse = self.view_projection().try_inverse()?;
+
+        let ndc_x = 2.0 * screen_pos.x / screen_width - 1.0;
+        // Screen coordinates go down, while clip space goes up
+        let ndc_y = 1.0 - 2.0 * screen_pos.y / screen_height;
+
+        let near = inverse.transform_point(&Point3::new(ndc_x, ndc_y, 0.0));
+        let far = inverse.transform_point(&Point3::new(ndc_x, ndc_y, 1.0));
+
+        Some((near, (far - near).normalize()))
+    }
+
+    // Where the ray through a point on the screen hits the horizontal plane at the given height
+    pub fn screen_to_plane(
+        &self,
+        screen_pos: Point2<f32>,
+        screen_width: f32,
+        screen_height: f32,
+        plane_y: f32,
+    ) -> Option<Point3<f32>> {
+        let (origin, dir) = self.screen_ray(screen_pos, screen_width, screen_height)?;
+        if dir.y.abs() <= f32::EPSILON {
+            return None;
+        }
+
+        let t = (plane_y - origin.y) / dir.y;
+        (t >= 0.0).then(|| origin + dir * t)
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CameraUniform {
+    view: [[f32; 4]; 4],
+    projection: [[f32; 4]; 4],
+}
+
+impl CameraUniform {
+    pub fn new(camera: &Camera) -> CameraUniform {
+        CameraUniform {
+            view: camera.view().into(),
+            projection: camera.projection().into(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CameraController {
+    forward: bool,
+    backward: bool,
+    left: bool,
+    right: bool,
+    up: bool,
+    down: bool,
+    looking: bool,
+    mouse_delta: (f64, f64),
+}
+
+impl CameraController {
+    const SPEED: f32 = 2.0;
+    const SENSITIVITY: f32 = 0.003;
+
+    pub fn new() -> CameraController {
+        CameraController::default()
+    }
+
+    // Returns true if the event was used by the controller
+    pub fn process_window_event(&mut self, event: &WindowEvent) -> bool {
+        match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state,
+                        physical_key: PhysicalKey::Code(key),
+                        ..
+                    },
+                ..
+            } => {
+                let pressed = *state == ElementState::Pressed;
+                match key {
+                    KeyCode::KeyW => self.forward = pressed,
+                    KeyCode::KeyS => self.backward = pressed,
+                    KeyCode::KeyA => self.left = pressed,
+                    KeyCode::KeyD => self.right = pressed,
+                    KeyCode::Space => self.up = pressed,
+                    KeyCode::ShiftLeft => self.down = pressed,
+                    _ => return false,
+                }
+                true
+            }
+            // Mouse-look only happens while the right button is held, so the cursor stays usable
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Right,
+                ..
+            } => {
+                self.looking = *state == ElementState::Pressed;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn process_mouse_motion(&mut self, delta: (f64, f64)) {
+        if self.looking {
+            self.mouse_delta.0 += delta.0;
+            self.mouse_delta.1 += delta.1;
+        }
+    }
+
+    pub fn update_camera(&mut self, camera: &mut Camera, frame_delta: Duration) {
+        let dir = camera.target - camera.position;
+        let distance = dir.norm();
+
+        let (dx, dy) = std::mem::take(&mut self.mouse_delta);
+        let yaw = dir.z.atan2(dir.x) + dx as f32 * Self::SENSITIVITY;
+        let pitch = ((dir.y / distance).asin() - dy as f32 * Self::SENSITIVITY)
+            .clamp(-MAX_PITCH, MAX_PITCH);
+        let dir = Vector3::new(
+            pitch.cos() * yaw.cos(),
+            pitch.sin(),
+            pitch.cos() * yaw.sin(),
+        );
+
+        // Moving happens in the horizontal plane, so looking down doesn't also move you down
+        let forward = Vector3::new(yaw.cos(), 0.0, yaw.sin());
+        let right = forward.cross(&camera.up).normalize();
+
+        let axis = |positive: bool, negative: bool| positive as i32 as f32 - negative as i32 as f32;
+        let movement = forward * axis(self.forward, self.backward)
+            + right * axis(self.right, self.left)
+            + camera.up * axis(self.up, self.down);
+
+        camera.position += movement * Self::SPEED * frame_delta.as_secs_f32();
+        camera.target = camera.position + dir * distance;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn center_of_the_screen_hits_the_target() {
+        let camera = Camera::overhead(16.0 / 9.0);
+
+        let (origin, dir) = camera
+            .screen_ray(Point2::new(800.0, 450.0), 1600.0, 900.0)
+            .unwrap();
+        let towards_target = (camera.target - camera.position).normalize();
+        assert!((dir - towards_target).norm() < 1e-4);
+        // The ray starts on the near plane
+        assert!(((origin - camera.position).norm() - camera.znear).abs() < 1e-3);
+
+        let hit = camera
+            .screen_to_plane(Point2::new(800.0, 450.0), 1600.0, 900.0, 0.0)
+            .unwrap();
+        assert!((hit - camera.target).norm() < 1e-3);
+    }
+
+    #[test]
+    fn screen_edges_are_half_the_fov_away() {
+        let camera = Camera {
+            position: Point3::new(0.0, 0.0, 5.0),
+            target: Point3::origin(),
+            up: Vector3::y(),
+            aspect: 1.0,
+            fovy: 90.0_f32.to_radians(),
+            znear: 0.1,
+            zfar: 100.0,
+        };
+
+        let (_, right) = camera
+            .screen_ray(Point2::new(100.0, 50.0), 100.0, 100.0)
+            .unwrap();
+        assert!((right - Vector3::new(1.0, 0.0, -1.0).normalize()).norm() < 1e-4);
+
+        // Screen y goes down, so the top of the screen looks up
+        let (_, top) = camera
+            .screen_ray(Point2::new(50.0, 0.0), 100.0, 100.0)
+            .unwrap();
+        assert!((top - Vector3::new(0.0, 1.0, -1.0).normalize()).norm() < 1e-4);
+
+        // Looking along the plane never hits it
+        assert!(camera
+            .screen_to_plane(Point2::new(50.0, 50.0), 100.0, 100.0, -1.0)
+            .is_none());
+    }
+}
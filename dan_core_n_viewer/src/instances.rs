@@ -0,0 +1,118 @@
+use dan_core_n::danmaku::data::InstanceBuffers;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct InstanceRaw {
+    model: [f32; 16],
+    color: [f32; 4],
+}
+
+impl InstanceRaw {
+    const ATTRIBS: [wgpu::VertexAttribute; 5] = wgpu::vertex_attr_array![
+        0 => Float32x4,
+        1 => Float32x4,
+        2 => Float32x4,
+        3 => Float32x4,
+        4 => Float32x4,
+    ];
+
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<InstanceRaw>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+pub fn instance_data(buffers: &InstanceBuffers) -> Vec<InstanceRaw> {
+    buffers
+        .model_mats
+        .iter()
+        .zip(buffers.colors.iter())
+        .map(|(model, color)| InstanceRaw {
+            model: *model,
+            color: *color,
+        })
+        .collect()
+}
+
+// Grows in powers of two so the buffer isn't recreated every time a few more danmaku spawn
+fn grown_capacity(capacity: usize, len: usize) -> usize {
+    if len > capacity {
+        len.next_power_of_two()
+    } else {
+        capacity
+    }
+}
+
+pub struct InstanceBuffer {
+    buffer: wgpu::Buffer,
+    capacity: usize,
+}
+
+impl InstanceBuffer {
+    pub fn new(device: &wgpu::Device, capacity: usize) -> InstanceBuffer {
+        InstanceBuffer {
+            buffer: Self::create_buffer(device, capacity),
+            capacity,
+        }
+    }
+
+    fn create_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Instance Buffer"),
+            size: (capacity * std::mem::size_of::<InstanceRaw>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    pub fn upload(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, data: &[InstanceRaw]) {
+        let capacity = grown_capacity(self.capacity, data.len());
+        if capacity != self.capacity {
+            self.capacity = capacity;
+            self.buffer = Self::create_buffer(device, self.capacity);
+        }
+
+        queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(data));
+    }
+
+    pub fn slice(&self) -> wgpu::BufferSlice<'_> {
+        self.buffer.slice(..)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instance_data_pairs_matrices_with_colors() {
+        let buffers = InstanceBuffers {
+            model_mats: vec![[1.0; 16], [2.0; 16]],
+            colors: vec![[1.0, 0.0, 0.0, 1.0], [0.0, 1.0, 0.0, 1.0]],
+            secondary_colors: vec![[0.0; 4]; 2],
+            forms: vec!["sphere", "sphere"],
+        };
+
+        let data = instance_data(&buffers);
+
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[1].model, [2.0; 16]);
+        assert_eq!(data[1].color, [0.0, 1.0, 0.0, 1.0]);
+        // What gets uploaded has to line up with the five vec4 attributes of the layout
+        let bytes: &[u8] = bytemuck::cast_slice(&data);
+        assert_eq!(bytes.len(), 2 * 5 * 16);
+        assert_eq!(InstanceRaw::desc().array_stride, 5 * 16);
+    }
+
+    #[test]
+    fn buffer_grows_in_powers_of_two() {
+        assert_eq!(grown_capacity(16, 10), 16);
+        assert_eq!(grown_capacity(16, 17), 32);
+        assert_eq!(grown_capacity(16, 100), 128);
+        // Never shrinks, so the next burst doesn't need a new buffer again
+        assert_eq!(grown_capacity(128, 0), 128);
+    }
+}
@@ -1,10 +1,29 @@
+mod camera;
+mod instances;
+mod overlay;
+mod timestep;
+
+use camera::{Camera, CameraController, CameraUniform};
+use dan_core_n::color::ColorHex;
 use dan_core_n::danmaku::{
+    data::{DanmakuSpawnData, InstanceBuffers},
     handlers::TopDanmakuBehaviorsHandler,
-    standard::StandardColumns,
+    standard::{
+        patterns::ring_spawn, Pos, StandardColumns, StandardDataColumns, StandardSpawnData,
+    },
 };
+use dan_core_n::form::Form;
+use enumset::EnumSet;
+use instances::{instance_data, InstanceBuffer, InstanceRaw};
+use nalgebra::{Point2, Point3, Vector3};
+use overlay::StatsOverlay;
+use std::collections::HashMap;
 use std::sync::Arc;
+use timestep::FixedTimestep;
+use web_time::{Duration, Instant};
 
 use pollster::FutureExt;
+use wgpu::util::DeviceExt;
 use winit::event_loop::ActiveEventLoop;
 use winit::window::{WindowAttributes, WindowId};
 use winit::{
@@ -14,16 +33,122 @@ use winit::{
     keyboard::{KeyCode, PhysicalKey},
     window::Window,
 };
-use dan_core_n::danmaku::standard::behaviors::StandardTopHandlerExt;
+use dan_core_n::danmaku::standard::behaviors::{
+    StandardTopHandlerExt, APPEARANCE_BEHAVIOR_ID, COLOR_GRADIENT_BEHAVIOR_ID,
+    MANDATORY_END_BEHAVIOR_ID, MOTION3_BEHAVIOR_ID,
+};
 
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
 // Based on https://sotrh.github.io/learn-wgpu
 
+const TICKS_PER_SECOND: u32 = 20;
+const RING_SPAWN_INTERVAL: u32 = 10;
+
 struct TopState<'a> {
     top_handler: TopDanmakuBehaviorsHandler<StandardColumns>,
     display_state: Option<DisplayState<'a>>,
+    timestep: FixedTimestep,
+    last_frame: Instant,
+    tick_count: u32,
+}
+
+impl TopState<'_> {
+    fn new(top_handler: TopDanmakuBehaviorsHandler<StandardColumns>) -> Self {
+        TopState {
+            top_handler,
+            display_state: None,
+            timestep: FixedTimestep::new(TICKS_PER_SECOND),
+            last_frame: Instant::now(),
+            tick_count: 0,
+        }
+    }
+
+    fn update(&mut self) {
+        let now = Instant::now();
+        let frame_delta = now - self.last_frame;
+        let ticks = self.timestep.advance(frame_delta);
+        self.last_frame = now;
+
+        if let Some(display_state) = &mut self.display_state {
+            display_state.update(frame_delta);
+        }
+
+        for _ in 0..ticks {
+            self.tick();
+        }
+    }
+
+    fn tick(&mut self) {
+        if self.tick_count.is_multiple_of(RING_SPAWN_INTERVAL) {
+            self.spawn_ring(Point3::origin());
+        }
+
+        self.top_handler.tick();
+        self.tick_count += 1;
+    }
+
+    fn spawn_ring(&mut self, origin: Point3<f32>) {
+        let hue_shift = (self.tick_count / RING_SPAWN_INTERVAL) as f32 * 15.0;
+
+        let mut template = ring_template(hue_shift);
+        template.behavior_data.extend([
+            StandardSpawnData::PosX(Pos::from(origin.x)),
+            StandardSpawnData::PosY(Pos::from(origin.y)),
+            StandardSpawnData::PosZ(Pos::from(origin.z)),
+        ]);
+
+        self.top_handler
+            .add_danmaku(ring_spawn(32, template, 0.02, Vector3::y_axis()));
+    }
+
+    fn render(&mut self) {
+        if let Some(display_state) = &mut self.display_state {
+            let instances = self
+                .top_handler
+                .render_instances(self.timestep.partial_ticks());
+            let live_count = self.top_handler.live_count();
+            match display_state.render(&instances, live_count) {
+                Ok(()) => {}
+                // The surface needs to be reconfigured after being lost or outdated
+                Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                    display_state.resize(display_state.size)
+                }
+                Err(e) => log::error!("Could not render frame: {e}"),
+            }
+        }
+    }
+}
+
+fn ring_template(hue_shift: f32) -> DanmakuSpawnData<StandardSpawnData, StandardDataColumns> {
+    DanmakuSpawnData {
+        end_time: 60,
+        behavior_data: vec![
+            StandardSpawnData::Appearance {
+                form: &Form::SPHERE,
+            },
+            StandardSpawnData::MainColor(ColorHex(0xFF4040).rotate_hue(hue_shift).0),
+            StandardSpawnData::EndColor(ColorHex(0x4040FF).rotate_hue(hue_shift).0),
+            StandardSpawnData::SizeX(0.03),
+            StandardSpawnData::SizeY(0.03),
+            StandardSpawnData::SizeZ(0.03),
+        ],
+        render_properties: HashMap::new(),
+        behaviors: vec![
+            MOTION3_BEHAVIOR_ID,
+            COLOR_GRADIENT_BEHAVIOR_ID,
+            APPEARANCE_BEHAVIOR_ID,
+            MANDATORY_END_BEHAVIOR_ID,
+        ],
+        next_stage_add_data: EnumSet::empty(),
+        next_stage_inherit: HashMap::new(),
+        anchor: None,
+        next_stage: vec![],
+        parent: None,
+        children: vec![],
+        family_depth: -1,
+    }
 }
 
 struct DisplayState<'a> {
@@ -33,6 +158,14 @@ struct DisplayState<'a> {
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
     window: Arc<Window>,
+    render_pipeline: wgpu::RenderPipeline,
+    instance_buffer: InstanceBuffer,
+    camera: Camera,
+    camera_controller: CameraController,
+    camera_buffer: wgpu::Buffer,
+    camera_bind_group: wgpu::BindGroup,
+    stats_overlay: StatsOverlay,
+    cursor_position: Point2<f32>,
 }
 
 impl<'a> DisplayState<'a> {
@@ -95,6 +228,84 @@ impl<'a> DisplayState<'a> {
             desired_maximum_frame_latency: 2,
         };
 
+        surface.configure(&device, &config);
+
+        let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
+
+        let camera = Camera::overhead(size.width as f32 / size.height.max(1) as f32);
+
+        let camera_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Camera Buffer"),
+            contents: bytemuck::cast_slice(&[CameraUniform::new(&camera)]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+
+        let camera_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Camera Bind Group Layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+            });
+
+        let camera_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Camera Bind Group"),
+            layout: &camera_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_buffer.as_entire_binding(),
+            }],
+        });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[&camera_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                buffers: &[InstanceRaw::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+        });
+
+        let instance_buffer = InstanceBuffer::new(&device, 1024);
+
         Self {
             surface,
             device,
@@ -102,6 +313,14 @@ impl<'a> DisplayState<'a> {
             config,
             size,
             window: window_arc,
+            render_pipeline,
+            instance_buffer,
+            camera,
+            camera_controller: CameraController::new(),
+            camera_buffer,
+            camera_bind_group,
+            stats_overlay: StatsOverlay::new(),
+            cursor_position: Point2::origin(),
         }
     }
 
@@ -111,16 +330,42 @@ impl<'a> DisplayState<'a> {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
+            self.camera.aspect = new_size.width as f32 / new_size.height as f32;
         }
     }
 
-    fn input(&mut self, _event: &WindowEvent) -> bool {
-        false
+    fn input(&mut self, event: &WindowEvent) -> bool {
+        self.camera_controller.process_window_event(event)
     }
 
-    fn update(&mut self) {}
+    // Where the cursor points at on the plane the danmaku are spawned in
+    fn cursor_world_position(&self) -> Option<Point3<f32>> {
+        self.camera.screen_to_plane(
+            self.cursor_position,
+            self.size.width as f32,
+            self.size.height as f32,
+            0.0,
+        )
+    }
+
+    fn update(&mut self, frame_delta: Duration) {
+        self.camera_controller
+            .update_camera(&mut self.camera, frame_delta);
+        self.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[CameraUniform::new(&self.camera)]),
+        );
+    }
+
+    fn render(
+        &mut self,
+        instances: &InstanceBuffers,
+        live_count: usize,
+    ) -> Result<(), wgpu::SurfaceError> {
+        self.instance_buffer
+            .upload(&self.device, &self.queue, &instance_data(instances));
 
-    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture()?;
         let view = output
             .texture
@@ -132,7 +377,7 @@ impl<'a> DisplayState<'a> {
             });
 
         {
-            let _render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
                 label: Some("Render Pass"),
                 color_attachments: &[Some(wgpu::RenderPassColorAttachment {
                     view: &view,
@@ -151,11 +396,22 @@ impl<'a> DisplayState<'a> {
                 occlusion_query_set: None,
                 timestamp_writes: None,
             });
+
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, self.instance_buffer.slice());
+
+            // Every form is drawn as a sphere for now, but each still gets its own draw call
+            for (_, range) in instances.groups() {
+                render_pass.draw(0..6, range.start as u32..range.end as u32);
+            }
         }
 
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
 
+        self.stats_overlay.frame(&self.window, live_count);
+
         Ok(())
     }
 }
@@ -200,6 +456,8 @@ impl ApplicationHandler<()> for TopState<'_> {
         window_id: WindowId,
         event: WindowEvent,
     ) {
+        let mut clicked_position = None;
+
         if let Some(display_state) = &mut self.display_state {
             if display_state.window.id() == window_id && !display_state.input(&event) {
                 match event {
@@ -218,26 +476,42 @@ impl ApplicationHandler<()> for TopState<'_> {
                         //inner_size_writer.request_inner_size()
                         //display_state.resize(new_inner_size)
                     }
-                    WindowEvent::RedrawRequested => display_state.render().unwrap(),
+                    WindowEvent::RedrawRequested => self.render(),
+                    WindowEvent::CursorMoved { position, .. } => {
+                        display_state.cursor_position =
+                            Point2::new(position.x as f32, position.y as f32)
+                    }
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    } => clicked_position = display_state.cursor_world_position(),
                     _ => {}
                 }
             }
         }
+
+        if let Some(position) = clicked_position {
+            self.spawn_ring(position);
+        }
     }
 
     fn about_to_wait(&mut self, _event_loop: &ActiveEventLoop) {
-        if let Some(display_state) = &mut self.display_state {
-            display_state.render().unwrap();
-        }
+        self.update();
+        self.render();
     }
 
     fn device_event(
         &mut self,
-        event_loop: &ActiveEventLoop,
-        device_id: DeviceId,
+        _event_loop: &ActiveEventLoop,
+        _device_id: DeviceId,
         event: DeviceEvent,
     ) {
-        let _ = (event_loop, device_id, event);
+        if let (Some(display_state), DeviceEvent::MouseMotion { delta }) =
+            (&mut self.display_state, event)
+        {
+            display_state.camera_controller.process_mouse_motion(delta);
+        }
     }
 
     fn memory_warning(&mut self, _event_loop: &ActiveEventLoop) {
@@ -264,17 +538,9 @@ pub fn run() {
         if #[cfg(target_arch = "wasm32")] {
             #[cfg(target_arch = "wasm32")]
             use winit::platform::web::EventLoopExtWebSys;
-            event_loop.spawn_app(
-                TopState {
-                    display_state: None,
-                    top_handler,
-                }
-            );
+            event_loop.spawn_app(TopState::new(top_handler));
         } else {
-            let mut state = TopState {
-                display_state: None,
-                top_handler,
-            };
+            let mut state = TopState::new(top_handler);
             let _ = event_loop.run_app(&mut state);
         }
     }
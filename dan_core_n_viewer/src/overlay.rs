@@ -0,0 +1,35 @@
+use web_time::{Duration, Instant};
+use winit::window::Window;
+
+const UPDATE_INTERVAL: Duration = Duration::from_millis(500);
+
+// Shows stats in the window title, which needs no extra text rendering
+pub struct StatsOverlay {
+    frames: u32,
+    last_update: Instant,
+}
+
+impl StatsOverlay {
+    pub fn new() -> StatsOverlay {
+        StatsOverlay {
+            frames: 0,
+            last_update: Instant::now(),
+        }
+    }
+
+    pub fn frame(&mut self, window: &Window, live_count: usize) {
+        self.frames += 1;
+
+        // Averaged over the interval, as the title flickers unreadably if set every frame
+        let elapsed = self.last_update.elapsed();
+        if elapsed >= UPDATE_INTERVAL {
+            let fps = self.frames as f32 / elapsed.as_secs_f32();
+            window.set_title(&format!(
+                "DanCoreN viewer - {fps:.0} FPS - {live_count} danmaku"
+            ));
+
+            self.frames = 0;
+            self.last_update = Instant::now();
+        }
+    }
+}
@@ -0,0 +1,57 @@
+struct CameraUniform {
+    view: mat4x4<f32>,
+    projection: mat4x4<f32>,
+};
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+struct InstanceInput {
+    @location(0) model_0: vec4<f32>,
+    @location(1) model_1: vec4<f32>,
+    @location(2) model_2: vec4<f32>,
+    @location(3) model_3: vec4<f32>,
+    @location(4) color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec4<f32>,
+};
+
+// Two triangles making up a quad, which the fragment shader cuts into a circle
+var<private> CORNERS: array<vec2<f32>, 6> = array<vec2<f32>, 6>(
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, -1.0),
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(-1.0, 1.0),
+);
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, instance: InstanceInput) -> VertexOutput {
+    let model = mat4x4<f32>(instance.model_0, instance.model_1, instance.model_2, instance.model_3);
+    let corner = CORNERS[vertex_index];
+
+    var out: VertexOutput;
+    // The quad always faces the camera, sized by the largest scale of the danmaku
+    let radius = max(length(model[0].xyz), max(length(model[1].xyz), length(model[2].xyz)));
+    let view_center = camera.view * model * vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    out.clip_position = camera.projection * (view_center + vec4<f32>(corner * radius, 0.0, 0.0));
+    out.corner = corner;
+    out.color = instance.color;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let dist_sq = dot(in.corner, in.corner);
+    if dist_sq > 1.0 {
+        discard;
+    }
+
+    // Fake some depth so the circle reads as a sphere
+    let shade = 0.4 + 0.6 * sqrt(1.0 - dist_sq);
+    return vec4<f32>(in.color.rgb * shade, in.color.a);
+}
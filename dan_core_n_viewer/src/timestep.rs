@@ -0,0 +1,84 @@
+use web_time::Duration;
+
+// Caps how far the simulation tries to catch up after a long stall, like the window being dragged
+const MAX_TICKS_PER_FRAME: u32 = 10;
+
+pub struct FixedTimestep {
+    tick_duration: Duration,
+    accumulator: Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(ticks_per_second: u32) -> FixedTimestep {
+        FixedTimestep {
+            tick_duration: Duration::from_secs(1) / ticks_per_second,
+            accumulator: Duration::ZERO,
+        }
+    }
+
+    // Returns how many ticks should be run for the time that has passed since the last frame
+    pub fn advance(&mut self, frame_delta: Duration) -> u32 {
+        self.accumulator += frame_delta;
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick_duration {
+            self.accumulator -= self.tick_duration;
+            ticks += 1;
+
+            if ticks == MAX_TICKS_PER_FRAME {
+                self.accumulator = Duration::ZERO;
+                break;
+            }
+        }
+
+        ticks
+    }
+
+    // How far we are into the next tick, used to interpolate rendering between ticks
+    pub fn partial_ticks(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.tick_duration.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_follow_real_time_regardless_of_frame_rate() {
+        let mut fast = FixedTimestep::new(20);
+        let fast_ticks: u32 = (0..144)
+            .map(|_| fast.advance(Duration::from_secs(1) / 144))
+            .sum();
+
+        let mut slow = FixedTimestep::new(20);
+        let slow_ticks: u32 = (0..30)
+            .map(|_| slow.advance(Duration::from_secs(1) / 30))
+            .sum();
+
+        // Rounding in the frame deltas can leave the last tick just short
+        assert!((19..=20).contains(&fast_ticks));
+        assert!((19..=20).contains(&slow_ticks));
+    }
+
+    #[test]
+    fn keeps_the_remainder_as_partial_ticks() {
+        let mut timestep = FixedTimestep::new(20);
+
+        assert_eq!(timestep.advance(Duration::from_millis(125)), 2);
+        assert!((timestep.partial_ticks() - 0.5).abs() < 1e-4);
+        assert_eq!(timestep.advance(Duration::from_millis(25)), 1);
+        assert!(timestep.partial_ticks() < 1e-4);
+    }
+
+    #[test]
+    fn long_stalls_are_capped() {
+        let mut timestep = FixedTimestep::new(20);
+
+        assert_eq!(
+            timestep.advance(Duration::from_secs(5)),
+            MAX_TICKS_PER_FRAME
+        );
+        assert_eq!(timestep.partial_ticks(), 0.0);
+    }
+}
@@ -1,4 +1,19 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use crate::error::DanCoreError;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Hash, Default)]
+pub enum ColorLerpMode {
+    #[default]
+    Hsv,
+    Hsl,
+    // Straight through rgb, which is cheaper, and can be done for many colors at once with SIMD
+    Rgb,
+}
+
 #[derive(Eq, PartialEq, Copy, Clone, Debug, Hash)]
+#[repr(transparent)]
 pub struct ColorHex(pub i32);
 
 impl ColorHex {
@@ -13,6 +28,122 @@ impl ColorHex {
     pub fn lerp_through_hsv(self, other: ColorHex, t: f32) -> ColorHex {
         self.to_rgb().to_hsv().lerp(&other.to_rgb().to_hsv(), t).to_rgb().to_hex()
     }
+
+    pub fn lerp_through_hsl(self, other: ColorHex, t: f32) -> ColorHex {
+        self.to_rgb().to_hsl().lerp(&other.to_rgb().to_hsl(), t).to_rgb().to_hex()
+    }
+
+    pub fn lerp_through_rgb(self, other: ColorHex, t: f32) -> ColorHex {
+        let a = self.to_rgb();
+        let b = other.to_rgb();
+        ColorRgb {
+            r: lerp_channel(a.r, b.r, t),
+            g: lerp_channel(a.g, b.g, t),
+            b: lerp_channel(a.b, b.b, t),
+        }
+        .to_hex()
+    }
+
+    pub fn to_rgba(self) -> [f32; 4] {
+        let ColorRgb { r, g, b } = self.to_rgb();
+        [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0]
+    }
+
+    pub fn rotate_hue(self, degrees: f32) -> ColorHex {
+        let mut hsv = self.to_rgb().to_hsv();
+        hsv.h = (hsv.h + degrees).rem_euclid(360.0);
+        hsv.to_rgb().to_hex()
+    }
+
+    pub fn lerp(self, other: ColorHex, t: f32, mode: ColorLerpMode) -> ColorHex {
+        match mode {
+            ColorLerpMode::Hsv => self.lerp_through_hsv(other, t),
+            ColorLerpMode::Hsl => self.lerp_through_hsl(other, t),
+            ColorLerpMode::Rgb => self.lerp_through_rgb(other, t),
+        }
+    }
+}
+
+impl Display for ColorHex {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{:06x}", self.0 & 0xFFFFFF)
+    }
+}
+
+// The same format as Display, with the # being optional
+impl FromStr for ColorHex {
+    type Err = DanCoreError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(DanCoreError::ParseColor);
+        }
+
+        i32::from_str_radix(hex, 16)
+            .map(ColorHex)
+            .map_err(|_| DanCoreError::ParseColor)
+    }
+}
+
+// simd_lerp_rgb does the same for whole chunks, and has to be kept in sync with this
+fn lerp_channel(a: u8, b: u8, t: f32) -> u8 {
+    (a as f32 + (b as f32 - a as f32) * t).round() as u8
+}
+
+// Returns the hue, in degrees, of the given rgb color components in the range 0-1
+fn hue_of(rd: f32, gd: f32, bd: f32, max: f32, delta: f32) -> f32 {
+    let mut hue = if rd >= max {
+        (gd - bd) / delta
+    } else if gd >= max {
+        2.0 + (bd - rd) / delta
+    } else {
+        4.0 + (rd - gd) / delta
+    };
+
+    hue *= 60.0;
+
+    if hue < 0.0 {
+        hue += 360.0;
+    }
+
+    hue
+}
+
+// https://www.alanzucconi.com/2016/01/06/colour-interpolation/
+// Interpolates two hues in degrees along the shortest arc. ColorHsv::lerp used to take the long
+// way whenever the hues were more than 0.5 apart, as if they were fractions of a turn, when they
+// are in degrees, so it now goes the long way only past 180 degrees
+fn lerp_hue(a: f32, b: f32, mut t: f32) -> f32 {
+    let mut d = b - a;
+
+    let mut ah = a;
+    let mut bh = b;
+
+    if a > b {
+        // Swap (a.h, b.h)
+        std::mem::swap(&mut bh, &mut ah);
+        d = -d;
+        t = 1.0 - t;
+    }
+
+    if d > 180.0 {
+        ah += 360.0;
+        (ah + t * (bh - ah)) % 360.0
+    } else {
+        ah + t * d
+    }
+}
+
+// Interpolates two hues in degrees along the longest arc, like for a sweep through the rainbow.
+// The same hue goes all the way around
+fn lerp_hue_long(a: f32, b: f32, t: f32) -> f32 {
+    let mut d = b - a;
+    if d.abs() <= 180.0 {
+        d -= 360.0f32.copysign(d);
+    }
+
+    (a + t * d).rem_euclid(360.0)
 }
 
 #[derive(Eq, PartialEq, Clone, Debug, Hash)]
@@ -41,26 +172,36 @@ impl ColorRgb {
                 v: max,
             }
         } else {
-            let sat = delta / max;
-
-            let mut hue = if rd >= max {
-                (gd - bd) / delta
-            } else if gd >= max {
-                2.0 + (bd - rd) / delta
-            } else {
-                4.0 + (rd - gd) / delta
-            };
+            ColorHsv {
+                h: hue_of(rd, gd, bd, max, delta),
+                s: delta / max,
+                v: max,
+            }
+        }
+    }
 
-            hue *= 60.0;
+    pub fn to_hsl(&self) -> ColorHsl {
+        let ColorRgb { r, g, b } = *self;
+        let rd = r as f32 / 255.0;
+        let gd = g as f32 / 255.0;
+        let bd = b as f32 / 255.0;
 
-            if hue < 0.0 {
-                hue += 360.0;
-            }
+        let min = rd.min(gd.min(bd));
+        let max = rd.max(gd.max(bd));
 
-            ColorHsv {
-                h: hue,
-                s: sat,
-                v: max,
+        let delta = max - min;
+        let lightness = (max + min) / 2.0;
+        if delta < 0.00001 {
+            ColorHsl {
+                h: 0.0,
+                s: 0.0,
+                l: lightness,
+            }
+        } else {
+            ColorHsl {
+                h: hue_of(rd, gd, bd, max, delta),
+                s: delta / (1.0 - (2.0 * lightness - 1.0).abs()),
+                l: lightness,
             }
         }
     }
@@ -72,9 +213,9 @@ impl ColorRgb {
 
 #[derive(PartialEq, Clone, Debug)]
 pub struct ColorHsv {
-    h: f32,
-    s: f32,
-    v: f32,
+    pub h: f32,
+    pub s: f32,
+    pub v: f32,
 }
 
 impl ColorHsv {
@@ -82,7 +223,13 @@ impl ColorHsv {
         let ColorHsv { mut h, s, v } = *self;
 
         if s <= 0.0 {
-            return ColorRgb { r: 0, g: 0, b: 0 };
+            // No saturation means a gray with the brightness of the value
+            let vv = (v * 255.0) as u8;
+            return ColorRgb {
+                r: vv,
+                g: vv,
+                b: vv,
+            };
         }
 
         if h >= 360.0 {
@@ -94,11 +241,11 @@ impl ColorHsv {
         let i = h as u8;
         let ff = h - i as f32;
 
-        let p = ((v * (1.0 - s)) * 255.0) as u8;
-        let q = ((v * (1.0 - (s * ff))) * 255.0) as u8;
-        let t = ((v * (1.0 - (s * (1.0 - ff)))) * 255.0) as u8;
+        let p = ((v * (1.0 - s)) * 255.0).round() as u8;
+        let q = ((v * (1.0 - (s * ff))) * 255.0).round() as u8;
+        let t = ((v * (1.0 - (s * (1.0 - ff)))) * 255.0).round() as u8;
 
-        let vv = (v * 255.0) as u8;
+        let vv = (v * 255.0).round() as u8;
 
         match i {
             0 => ColorRgb { r: vv, g: t, b: p },
@@ -110,30 +257,17 @@ impl ColorHsv {
         }
     }
 
-    // https://www.alanzucconi.com/2016/01/06/colour-interpolation/
-    pub fn lerp(&self, that: &ColorHsv, mut t: f32) -> ColorHsv {
-        // Hue interpolation
-        let mut d = that.h - self.h;
-
-        let mut ah = self.h;
-        let mut bh = that.h;
-
-        if self.h > that.h {
-            // Swap (a.h, b.h)
-            std::mem::swap(&mut bh, &mut ah);
-            d = -d;
-            t = 1.0 - t;
-        }
+    pub fn lerp(&self, that: &ColorHsv, t: f32) -> ColorHsv {
+        self.lerp_with(that, t, false)
+    }
 
-        let h = if d > 0.5 {
-            // 180deg
-            ah += 1.0; // 360deg
-            (ah + t * (bh - ah)) % 1.0 // 360deg
+    // Goes the long way around the hue circle if long_way is set, instead of the shortest way
+    pub fn lerp_with(&self, that: &ColorHsv, t: f32, long_way: bool) -> ColorHsv {
+        let h = if long_way {
+            lerp_hue_long(self.h, that.h, t)
         } else {
-            // 180deg
-            ah + t * d
+            lerp_hue(self.h, that.h, t)
         };
-        // Interpolates the rest
 
         ColorHsv {
             h,
@@ -142,3 +276,141 @@ impl ColorHsv {
         }
     }
 }
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct ColorHsl {
+    pub h: f32,
+    pub s: f32,
+    pub l: f32,
+}
+
+impl ColorHsl {
+    pub fn to_rgb(&self) -> ColorRgb {
+        let ColorHsl { mut h, s, l } = *self;
+
+        if h >= 360.0 {
+            h = 0.0;
+        }
+
+        let c = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let x = c * (1.0 - ((h / 60.0) % 2.0 - 1.0).abs());
+        let m = l - c / 2.0;
+
+        let (r, g, b) = match (h / 60.0) as u8 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+
+        ColorRgb {
+            r: ((r + m) * 255.0).round() as u8,
+            g: ((g + m) * 255.0).round() as u8,
+            b: ((b + m) * 255.0).round() as u8,
+        }
+    }
+
+    pub fn lerp(&self, that: &ColorHsl, t: f32) -> ColorHsl {
+        ColorHsl {
+            h: lerp_hue(self.h, that.h, t),
+            s: nalgebra_glm::lerp_scalar(self.s, that.s, t),
+            l: nalgebra_glm::lerp_scalar(self.l, that.l, t),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_displayed_format() {
+        assert_eq!("#12ab34".parse(), Ok(ColorHex(0x12AB34)));
+        assert_eq!("FF8000".parse(), Ok(ColorHex(0xFF8000)));
+        assert_eq!(ColorHex(0x0000FF).to_string().parse(), Ok(ColorHex(0x0000FF)));
+    }
+
+    #[test]
+    fn rejects_malformed_colors() {
+        for s in ["", "#", "#12ab3", "#12ab345", "#12ag34", "+12ab3"] {
+            assert_eq!(s.parse::<ColorHex>(), Err(DanCoreError::ParseColor), "{}", s);
+        }
+    }
+
+    #[test]
+    fn white_round_trips_through_hsv() {
+        let white = ColorHex(0xFFFFFF).to_rgb();
+        let hsv = white.to_hsv();
+
+        assert_eq!(hsv.s, 0.0);
+        assert_eq!(hsv.to_rgb(), white);
+    }
+
+    #[test]
+    fn gray_round_trips_through_hsv() {
+        let gray = ColorHex(0x808080).to_rgb();
+
+        assert_eq!(gray.to_hsv().to_rgb(), gray);
+    }
+
+    #[test]
+    fn converts_known_colors_to_hsl() {
+        let red = ColorHex(0xFF0000).to_rgb().to_hsl();
+        assert_eq!(
+            red,
+            ColorHsl {
+                h: 0.0,
+                s: 1.0,
+                l: 0.5
+            }
+        );
+
+        let dark_green = ColorHsl {
+            h: 120.0,
+            s: 1.0,
+            l: 0.25,
+        };
+        assert_eq!(dark_green.to_rgb().to_hex(), ColorHex(0x008000));
+    }
+
+    #[test]
+    fn hsl_fade_ramps_lightness() {
+        let mid = ColorHex(0xFF0000).lerp(ColorHex(0xFFFFFF), 0.5, ColorLerpMode::Hsl);
+
+        assert_eq!(mid, ColorHex(0xDF9F9F));
+        assert!((mid.to_rgb().to_hsl().l - 0.75).abs() < 0.01);
+    }
+
+    #[test]
+    fn hues_lerp_along_the_shortest_arc_in_degrees() {
+        // Red to blue is shorter through magenta than through green
+        let mid = ColorHex(0xFF0000).lerp(ColorHex(0x0000FF), 0.5, ColorLerpMode::Hsv);
+        assert_eq!(mid, ColorHex(0xFF00FF));
+
+        assert_eq!(lerp_hue(350.0, 10.0, 0.5), 0.0);
+        assert_eq!(lerp_hue(10.0, 100.0, 0.5), 55.0);
+    }
+
+    #[test]
+    fn long_way_lerps_go_around_the_other_side_of_the_hue_circle() {
+        let red = ColorHex(0xFF0000).to_rgb().to_hsv();
+        let green = ColorHex(0x00FF00).to_rgb().to_hsv();
+        let lerped = |t: f32, long_way: bool| red.lerp_with(&green, t, long_way).to_rgb().to_hex();
+
+        // The short way goes through yellow
+        assert_eq!(lerped(0.5, false), ColorHex(0xFFFF00));
+        assert_eq!(red.lerp(&green, 0.5).to_rgb().to_hex(), ColorHex(0xFFFF00));
+
+        // The long way goes through magenta, then blue
+        assert_eq!(lerped(0.25, true), ColorHex(0xFF00FF));
+        assert_eq!(lerped(0.5, true), ColorHex(0x0000FF));
+
+        // Both end up at the same colors
+        for long_way in [false, true] {
+            assert_eq!(lerped(0.0, long_way), ColorHex(0xFF0000));
+            assert_eq!(lerped(1.0, long_way), ColorHex(0x00FF00));
+        }
+    }
+}
@@ -1,10 +1,39 @@
 use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::ops::Range;
+use std::simd::Mask;
 
 use enumset::{EnumSet, EnumSetType};
-use nalgebra::Matrix4;
+use nalgebra::{Matrix4, UnitQuaternion, Vector3};
 
+use crate::color::{ColorHex, ColorLerpMode};
+use crate::danmaku::N;
 use crate::form::Form;
 
+// The bytes allocated by each column, summed over all handlers
+#[derive(Clone, Debug, Default)]
+pub struct MemoryReport {
+    pub columns: HashMap<&'static str, usize>,
+}
+
+impl MemoryReport {
+    pub fn total(&self) -> usize {
+        self.columns.values().sum()
+    }
+}
+
+// How the next stage of a danmaku combines its spawn data with the data the danmaku had
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InheritOp {
+    // Makes the spawn data relative to the danmaku, like adding positions or composing rotations
+    Add,
+    Overwrite,
+    Ignore,
+}
+
+// Danmaku with a negative end_time never expire, and only die if something kills them
+pub const IMMORTAL_END_TIME: i16 = -1;
+
 #[derive(Clone)]
 pub struct DanmakuSpawnData<SpawnData, DataColumns: EnumSetType> {
     pub end_time: i16,
@@ -12,6 +41,10 @@ pub struct DanmakuSpawnData<SpawnData, DataColumns: EnumSetType> {
     pub render_properties: HashMap<&'static str, f32>,
     pub behaviors: Vec<&'static str>,
     pub next_stage_add_data: EnumSet<DataColumns>,
+    // Overrides how the next stages inherit these columns, where the default depends on the column
+    pub next_stage_inherit: HashMap<DataColumns, InheritOp>,
+    // As a next stage, spawns relative to this point in the world instead of where the parent died
+    pub anchor: Option<Vector3<f32>>,
     pub next_stage: Vec<DanmakuSpawnData<SpawnData, DataColumns>>,
     pub parent: Option<i128>,
     pub children: Vec<DanmakuSpawnData<SpawnData, DataColumns>>,
@@ -19,10 +52,15 @@ pub struct DanmakuSpawnData<SpawnData, DataColumns: EnumSetType> {
 }
 impl<SD, DC: EnumSetType> DanmakuSpawnData<SD, DC> {
     fn update_children_depth(&mut self) {
-        self.children.iter_mut().for_each(|child| {
-            child.family_depth = self.family_depth + 1;
-            child.update_children_depth()
-        })
+        // Done with a worklist instead of recursion, as family trees can get deep
+        let mut pending = vec![self];
+        while let Some(d) = pending.pop() {
+            let child_depth = d.family_depth + 1;
+            for child in d.children.iter_mut() {
+                child.family_depth = child_depth;
+                pending.push(child);
+            }
+        }
     }
 
     pub(crate) fn set_family_depth(
@@ -40,8 +78,8 @@ impl<SD, DC: EnumSetType> DanmakuSpawnData<SD, DC> {
                 }
                 Some(parent_id) => global_family_depth_map
                     .get(&parent_id)
-                    .map_or(false, |depth| {
-                        self.family_depth = *depth;
+                    .is_some_and(|depth| {
+                        self.family_depth = *depth + 1;
                         self.update_children_depth();
                         true
                     }),
@@ -50,12 +88,241 @@ impl<SD, DC: EnumSetType> DanmakuSpawnData<SD, DC> {
     }
 }
 
+pub type PendingSpawn<SpawnData, DataColumns> =
+    (DanmakuSpawnData<SpawnData, DataColumns>, Option<usize>);
+
+// Settings shared by every handler, set once on the top handler instead of being passed to each
+// behavior that needs them
+#[derive(Clone, Debug)]
+pub struct HandlerContext {
+    // The min and max corner of the play field
+    pub bounds: Option<(Vector3<f32>, Vector3<f32>)>,
+    // Named points behaviors can refer to, like where the player is
+    pub origins: HashMap<&'static str, Vector3<f32>>,
+    pub tick_scale: f32,
+    pub tick_count: u64,
+    // The scale of danmaku on axes they have no scale for
+    pub default_scale: Vector3<f32>,
+    // How colors are interpolated between ticks when rendering
+    pub color_lerp_mode: ColorLerpMode,
+    // Set by conditional behaviors while the behavior they wrap runs, flagging the slots it may
+    // change. None lets behaviors change every danmaku
+    pub lane_mask: Option<Vec<bool>>,
+}
+
+impl Default for HandlerContext {
+    fn default() -> Self {
+        HandlerContext {
+            bounds: None,
+            origins: HashMap::new(),
+            tick_scale: 1.0,
+            tick_count: 0,
+            default_scale: Vector3::new(1.0, 1.0, 1.0),
+            color_lerp_mode: ColorLerpMode::default(),
+            lane_mask: None,
+        }
+    }
+}
+
+impl HandlerContext {
+    pub fn in_bounds(&self, pos: Vector3<f32>) -> bool {
+        self.bounds.is_none_or(|(min, max)| {
+            pos.iter()
+                .zip(min.iter().zip(max.iter()))
+                .all(|(p, (min, max))| p >= min && p <= max)
+        })
+    }
+
+    pub fn lane_flagged(&self, idx: usize) -> bool {
+        self.lane_mask
+            .as_ref()
+            .is_none_or(|mask| mask.get(idx).copied().unwrap_or(false))
+    }
+
+    // The lanes of a SIMD chunk behaviors may change
+    pub fn chunk_lane_mask(&self, chunk: usize) -> Mask<i32, N> {
+        match &self.lane_mask {
+            Some(_) => Mask::from_array(std::array::from_fn(|j| self.lane_flagged(chunk * N + j))),
+            None => Mask::splat(true),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DanmakuView {
+    pub id: i128,
+    pub user_data: u64,
+    pub form: Option<&'static Form>,
+    pub position: Vector3<f32>,
+    pub scale: Vector3<f32>,
+    pub orientation: UnitQuaternion<f32>,
+    pub motion: Vector3<f32>,
+    pub main_color: i32,
+    pub secondary_color: i32,
+    pub damage: f32,
+    pub ticks_existed: i16,
+    pub end_time: i16,
+}
+
+impl Debug for DanmakuView {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DanmakuView")
+            .field("id", &self.id)
+            .field("user_data", &self.user_data)
+            .field("form", &self.form)
+            .field("position", &tuple(&self.position))
+            .field("scale", &tuple(&self.scale))
+            .field("motion", &tuple(&self.motion))
+            .field("main_color", &format_args!("{}", ColorHex(self.main_color)))
+            .field(
+                "secondary_color",
+                &format_args!("{}", ColorHex(self.secondary_color)),
+            )
+            .field("damage", &self.damage)
+            .field("ticks_existed", &self.ticks_existed)
+            .field("end_time", &self.end_time)
+            .finish()
+    }
+}
+
+// nalgebra prints vectors as nested matrices, which is hard to read among other fields
+fn tuple(v: &Vector3<f32>) -> (f32, f32, f32) {
+    (v.x, v.y, v.z)
+}
+
 pub struct RenderData<'a> {
     pub form: &'static Form,
     pub render_properties: &'a HashMap<&'static str, f32>,
+    pub trail: &'a [Vector3<f32>],
     pub model_mat: Matrix4<f32>,
-    pub main_color: i32,
-    pub secondary_color: i32,
+    pub main_color: ColorHex,
+    pub secondary_color: ColorHex,
     pub ticks_existed: i16,
     pub end_time: i16,
+    pub user_data: u64,
+}
+
+impl Debug for RenderData<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Model matrices are built without shear, so the columns are the scaled axes
+        let position = self.model_mat.fixed_view::<3, 1>(0, 3).into_owned();
+        let scale = Vector3::new(
+            self.model_mat.fixed_view::<3, 1>(0, 0).norm(),
+            self.model_mat.fixed_view::<3, 1>(0, 1).norm(),
+            self.model_mat.fixed_view::<3, 1>(0, 2).norm(),
+        );
+
+        f.debug_struct("RenderData")
+            .field("form", &self.form)
+            .field("position", &tuple(&position))
+            .field("scale", &tuple(&scale))
+            .field("main_color", &format_args!("{}", self.main_color))
+            .field("secondary_color", &format_args!("{}", self.secondary_color))
+            .field("ticks_existed", &self.ticks_existed)
+            .field("end_time", &self.end_time)
+            .field("user_data", &self.user_data)
+            .field("render_properties", self.render_properties)
+            .finish()
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct InstanceBuffers {
+    pub model_mats: Vec<[f32; 16]>,
+    // The main colors
+    pub colors: Vec<[f32; 4]>,
+    // Interpolated separately from the main colors, for shaders drawing two-tone danmaku
+    pub secondary_colors: Vec<[f32; 4]>,
+    pub forms: Vec<&'static str>,
+}
+
+impl InstanceBuffers {
+    // Instances are sorted by form, so each form can be drawn with a single instanced draw call
+    pub fn from_render_data(mut render_data: Vec<RenderData>) -> InstanceBuffers {
+        render_data.sort_by_key(|r| r.form.id());
+
+        let mut buffers = InstanceBuffers {
+            model_mats: Vec::with_capacity(render_data.len()),
+            colors: Vec::with_capacity(render_data.len()),
+            secondary_colors: Vec::with_capacity(render_data.len()),
+            forms: Vec::with_capacity(render_data.len()),
+        };
+
+        for r in render_data {
+            let mut model_mat = [0.0; 16];
+            model_mat.copy_from_slice(r.model_mat.as_slice());
+
+            buffers.model_mats.push(model_mat);
+            buffers.colors.push(r.main_color.to_rgba());
+            buffers.secondary_colors.push(r.secondary_color.to_rgba());
+            buffers.forms.push(r.form.id());
+        }
+
+        buffers
+    }
+
+    pub fn groups(&self) -> Vec<(&'static str, Range<usize>)> {
+        let mut groups: Vec<(&'static str, Range<usize>)> = Vec::new();
+        for (i, form) in self.forms.iter().enumerate() {
+            match groups.last_mut() {
+                Some((last_form, range)) if last_form == form => range.end = i + 1,
+                _ => groups.push((form, i..i + 1)),
+            }
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn danmaku_view_debug_shows_the_id_and_hex_colors() {
+        let view = DanmakuView {
+            id: 12345,
+            user_data: 0,
+            form: Some(&Form::SPHERE),
+            position: Vector3::new(1.0, 2.0, 3.0),
+            scale: Vector3::repeat(1.0),
+            orientation: UnitQuaternion::identity(),
+            motion: Vector3::zeros(),
+            main_color: 0xFF8000,
+            secondary_color: 0x0000FF,
+            damage: 0.0,
+            ticks_existed: 4,
+            end_time: 20,
+        };
+
+        let printed = format!("{:?}", view);
+        assert!(printed.contains("id: 12345"));
+        assert!(printed.contains("main_color: #ff8000"));
+        assert!(printed.contains("secondary_color: #0000ff"));
+        assert!(printed.contains("position: (1.0, 2.0, 3.0)"));
+        assert!(printed.contains("ticks_existed: 4"));
+        assert!(printed.contains("end_time: 20"));
+    }
+
+    #[test]
+    fn render_data_debug_decomposes_the_model_matrix() {
+        let render_properties = HashMap::new();
+        let render_data = RenderData {
+            form: &Form::SPHERE,
+            render_properties: &render_properties,
+            trail: &[],
+            model_mat: Matrix4::new_translation(&Vector3::new(1.0, 2.0, 3.0))
+                * Matrix4::from_scaled_axis(Vector3::z() * 0.5)
+                * Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 3.0, 4.0)),
+            main_color: ColorHex(0x12AB34),
+            secondary_color: ColorHex(0),
+            ticks_existed: 4,
+            end_time: 20,
+            user_data: 0,
+        };
+
+        let printed = format!("{:?}", render_data);
+        assert!(printed.contains("main_color: #12ab34"));
+        assert!(printed.contains("position: (1.0, 2.0, 3.0)"));
+        assert!(printed.contains("scale: (2.0, 3.0, 4.0)"));
+    }
 }
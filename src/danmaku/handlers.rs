@@ -1,33 +1,250 @@
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::rc::Rc;
 
 use enumset::EnumSet;
-use priority_queue::PriorityQueue;
+use nalgebra::{Matrix4, Vector3};
 
 use crate::danmaku::{
-    data::{DanmakuSpawnData, RenderData},
+    data::{
+        DanmakuSpawnData, DanmakuView, HandlerContext, InstanceBuffers, MemoryReport, PendingSpawn,
+        RenderData,
+    },
     Behavior, DanmakuData,
 };
+use crate::error::DanCoreError;
+
+const DEFAULT_MAX_FAMILY_DEPTH: i16 = 256;
+const DEFAULT_SPAWN_STORM_THRESHOLD: usize = 1024;
+
+pub type DeathCallback = Box<dyn FnMut(i128, &DanmakuView)>;
+
+// When handlers grow and shrink. Growing early avoids resizing in the middle of adding danmaku,
+// while shrinking late avoids resizing back and forth around a size
+#[derive(Clone, Copy, Debug)]
+pub struct ResizePolicy {
+    // How much of the size is kept free, as a fraction of it, before growing
+    pub grow_margin: f64,
+    // How much of the smaller size has to be free, as a fraction of it, before shrinking
+    pub shrink_surplus: f64,
+    // Handlers don't shrink below 2^min_size_exp
+    pub min_size_exp: u8,
+}
+
+impl Default for ResizePolicy {
+    fn default() -> Self {
+        ResizePolicy {
+            grow_margin: 0.1,
+            shrink_surplus: 0.1,
+            min_size_exp: DEFAULT_SIZE_EXP,
+        }
+    }
+}
+
+// The order render data is returned in. Unordered follows the hash maps danmaku are kept in, so it
+// changes from run to run
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum RenderOrder {
+    #[default]
+    Unordered,
+    ById,
+    // Farthest from the given eye position first, for blending transparent danmaku
+    BackToFront(Vector3<f32>),
+}
+
+fn sort_render_data(order: RenderOrder, render_data: &mut [(i128, RenderData)]) {
+    match order {
+        RenderOrder::Unordered => {}
+        RenderOrder::ById => render_data.sort_unstable_by_key(|(id, _)| *id),
+        RenderOrder::BackToFront(eye) => {
+            let distance = |r: &RenderData| (r.model_mat.column(3).xyz() - eye).norm_squared();
+            render_data.sort_unstable_by(|(a_id, a), (b_id, b)| {
+                distance(b).total_cmp(&distance(a)).then(a_id.cmp(b_id))
+            })
+        }
+    }
+}
+
+// Handlers are looked up for every spawn, so they are keyed by a hash of their behaviors computed
+// with FNV-1a, which is much cheaper than hashing the behaviors with the hasher of the map. The
+// rare handlers sharing a hash are told apart by comparing their behaviors
+type Handlers<C> = HashMap<u64, Vec<DanmakuBehaviorHandler<C>>>;
+
+// The id of an added danmaku, and its children which still have to be added
+type AddedDanmaku<C> = (
+    i128,
+    Vec<DanmakuSpawnData<<C as DanmakuData>::SpawnData, <C as DanmakuData>::DataColumns>>,
+);
+
+fn behavior_set_hash(behaviors: &[&'static str]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in behaviors {
+        // 0xff never shows up in UTF-8, so it separates the ids
+        for byte in b.bytes().chain(std::iter::once(0xff)) {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+fn canonicalize_behaviors(behaviors: &mut Vec<&'static str>, implicit: &[&'static str]) {
+    behaviors.extend_from_slice(implicit);
+    behaviors.sort_unstable();
+    behaviors.dedup();
+}
+
+fn find_handler<'a, C: DanmakuData>(
+    handlers: &'a mut Handlers<C>,
+    behaviors: &[&'static str],
+) -> Option<&'a mut DanmakuBehaviorHandler<C>> {
+    handlers
+        .get_mut(&behavior_set_hash(behaviors))?
+        .iter_mut()
+        .find(|h| h.behavior_ids == behaviors)
+}
 
 pub struct TopDanmakuBehaviorsHandler<C: DanmakuData> {
-    handlers: HashMap<Vec<&'static str>, DanmakuBehaviorHandler<C>>,
+    handlers: Handlers<C>,
     behaviors: HashMap<&'static str, Rc<Behavior<C>>>,
+    disabled_behaviors: HashSet<&'static str>,
+    // Run by every danmaku, even ones spawned without any behaviors
+    implicit_behaviors: Vec<&'static str>,
 
     global_family_depth_map: HashMap<i128, i16>,
     global_parent_map: HashMap<i128, i128>,
 
+    max_family_depth: i16,
+    // If removing a danmaku also removes its children, and their children
+    cascade_death: bool,
+    resize_policy: ResizePolicy,
+    // New handlers start out with room for 2^initial_size_exp danmaku, or more if reserved
+    initial_size_exp: u8,
+    // Adding more danmaku than this at once is logged with the diagnostics feature
+    spawn_storm_threshold: usize,
+    render_order: RenderOrder,
+    next_identifier: i64,
+    // Ticks run while not paused
+    tick_count: u64,
+    // Ticking does nothing while paused, but danmaku can still be rendered, spawned and removed
+    paused: bool,
+    // Reused between frames to order parent relationships when resolving transforms
+    parent_order: Vec<(i16, i128, i128)>,
+    context: HandlerContext,
+
+    on_death: Option<DeathCallback>,
+}
+pub struct DanmakuSnapshot<C: DanmakuData> {
+    handlers: Handlers<C>,
+    global_family_depth_map: HashMap<i128, i16>,
+    global_parent_map: HashMap<i128, i128>,
     next_identifier: i64,
+    tick_count: u64,
+}
+impl<C: DanmakuData + Clone> Clone for DanmakuSnapshot<C> {
+    fn clone(&self) -> Self {
+        DanmakuSnapshot {
+            handlers: self.handlers.clone(),
+            global_family_depth_map: self.global_family_depth_map.clone(),
+            global_parent_map: self.global_parent_map.clone(),
+            next_identifier: self.next_identifier,
+            tick_count: self.tick_count,
+        }
+    }
+}
+
+// The last few snapshots of a handler, to step it back in time. Recorded by calling record after
+// ticking, and only goes back as far as what has been recorded
+pub struct DanmakuHistory<C: DanmakuData> {
+    // Oldest first
+    snapshots: VecDeque<DanmakuSnapshot<C>>,
+    capacity: usize,
+}
+
+impl<C: DanmakuData + Clone> DanmakuHistory<C> {
+    pub fn new(capacity: usize) -> DanmakuHistory<C> {
+        DanmakuHistory {
+            snapshots: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    pub fn record(&mut self, handler: &TopDanmakuBehaviorsHandler<C>) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        // Recording the same tick again replaces it
+        if self
+            .snapshots
+            .back()
+            .is_some_and(|s| s.tick_count >= handler.tick_count)
+        {
+            self.snapshots.pop_back();
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+
+        self.snapshots.push_back(handler.snapshot());
+    }
+
+    // Restores the handler to how it was ticks ticks ago, or to the oldest recorded tick if the
+    // history doesn't go back that far. Returns how many ticks it actually went back
+    pub fn rewind(&mut self, handler: &mut TopDanmakuBehaviorsHandler<C>, ticks: u64) -> u64 {
+        let target = handler.tick_count.saturating_sub(ticks);
+
+        // Anything recorded after the restored tick is now in the future, and gets dropped
+        while self.snapshots.len() > 1 && self.snapshots.back().unwrap().tick_count > target {
+            self.snapshots.pop_back();
+        }
+
+        match self.snapshots.back() {
+            Some(snapshot) if snapshot.tick_count <= handler.tick_count => {
+                let rewound = handler.tick_count - snapshot.tick_count;
+                handler.restore(snapshot);
+                rewound
+            }
+            _ => 0,
+        }
+    }
 }
+
 impl<C: DanmakuData> Default for TopDanmakuBehaviorsHandler<C> {
     fn default() -> Self {
         TopDanmakuBehaviorsHandler {
             handlers: HashMap::new(),
             behaviors: HashMap::new(),
+            disabled_behaviors: HashSet::new(),
+            implicit_behaviors: Vec::new(),
             global_family_depth_map: HashMap::new(),
             global_parent_map: HashMap::new(),
 
+            max_family_depth: DEFAULT_MAX_FAMILY_DEPTH,
+            cascade_death: false,
+            resize_policy: ResizePolicy::default(),
+            initial_size_exp: DEFAULT_SIZE_EXP,
+            spawn_storm_threshold: DEFAULT_SPAWN_STORM_THRESHOLD,
+            render_order: RenderOrder::default(),
             next_identifier: 0,
+            tick_count: 0,
+            paused: false,
+            parent_order: Vec::new(),
+            context: HandlerContext::default(),
+
+            on_death: None,
         }
     }
 }
@@ -42,252 +259,3293 @@ impl<C: DanmakuData> TopDanmakuBehaviorsHandler<C> {
             .insert(behavior.identifier, Rc::new(behavior));
     }
 
-    fn add_single_danmaku(
-        &mut self,
-        d: DanmakuSpawnData<C::SpawnData, C::DataColumns>,
-        preferred_idx: Option<(usize, i64)>,
-    ) -> Vec<DanmakuSpawnData<C::SpawnData, C::DataColumns>> {
-        let handler = match self.handlers.get_mut(&d.behaviors) {
-            Some(t) => t,
-            None => {
-                let behaviors = d
-                    .behaviors
-                    .iter()
-                    .map(|b| Rc::clone(self.behaviors.get(b).unwrap()))
-                    .collect();
+    // Adds a behavior to those of every danmaku spawned from now on, like one aging them so that
+    // none live forever by accident
+    pub fn add_implicit_behavior(&mut self, id: &'static str) {
+        if !self.implicit_behaviors.contains(&id) {
+            self.implicit_behaviors.push(id);
+        }
+    }
 
-                self.next_identifier += 1;
-                self.handlers.insert(
-                    d.behaviors.clone(),
-                    DanmakuBehaviorHandler::new(self.next_identifier, behaviors, false),
-                );
+    fn create_handler(
+        &mut self,
+        behavior_ids: &[&'static str],
+        capacity: usize,
+    ) -> Result<(), DanCoreError> {
+        let behaviors = resolve_behaviors(&self.behaviors, behavior_ids)?;
+        let size_exp = size_exp_for(capacity).max(self.initial_size_exp);
 
-                self.handlers.get_mut(&d.behaviors).unwrap()
-            }
-        };
+        self.next_identifier += 1;
+        let mut handler = DanmakuBehaviorHandler::new(
+            self.next_identifier,
+            behavior_ids.to_vec(),
+            behaviors,
+            false,
+            size_exp,
+        );
+        handler.validate()?;
+        handler.apply_disabled(&self.disabled_behaviors);
+        handler.resize_policy = self.resize_policy;
 
-        handler.add_danmaku_with_preffered_index(
-            d,
-            preferred_idx
-                .filter(|(_, original_handler_identifier)| {
-                    *original_handler_identifier == handler.identifier
-                })
-                .map(|(idx, _)| idx),
-            &mut self.global_family_depth_map,
-            &mut self.global_parent_map,
-        )
+        self.handlers
+            .entry(behavior_set_hash(behavior_ids))
+            .or_default()
+            .push(handler);
+        Ok(())
     }
 
-    pub fn add_danmaku(&mut self, danmaku: Vec<DanmakuSpawnData<C::SpawnData, C::DataColumns>>) {
-        let mut pending = danmaku;
+    pub fn reserve(
+        &mut self,
+        behaviors: &[&'static str],
+        capacity: usize,
+    ) -> Result<(), DanCoreError> {
+        let mut behaviors = behaviors.to_vec();
+        canonicalize_behaviors(&mut behaviors, &self.implicit_behaviors);
 
-        while let Some(d) = pending
-            .pop()
-            .into_iter()
-            .filter_map(|mut d| {
-                if d.set_family_depth(&self.global_family_depth_map) {
-                    Some(d)
-                } else {
-                    None
-                }
-            })
-            .next()
-        {
-            pending.append(&mut self.add_single_danmaku(d, None));
+        match find_handler(&mut self.handlers, &behaviors) {
+            Some(handler) => {
+                handler.reserve(capacity);
+                Ok(())
+            }
+            None => self.create_handler(&behaviors, capacity),
         }
     }
 
-    pub fn tick(&mut self) {
-        let mut with_idx: Vec<(_, usize, i64)> = vec![];
-        let mut simple = vec![];
-
-        for h in self.handlers.values_mut() {
-            for (d, idx) in h.tick() {
-                match idx {
-                    None => simple.push(d),
-                    Some(i) => with_idx.push((d, i, h.identifier)),
-                }
+    // Handlers keep the behaviors they were created with, so this has to be called for behaviors
+    // registered again to take effect. Handlers whose new behaviors use columns they don't have
+    // keep their old behaviors, and the first such error is returned
+    pub fn rebuild_handlers(&mut self) -> Result<(), DanCoreError> {
+        let mut result = Ok(());
+        for h in self.handlers.values_mut().flatten() {
+            let rebuilt = resolve_behaviors(&self.behaviors, &h.behavior_ids)
+                .and_then(|behaviors| h.replace_behaviors(behaviors));
+            if rebuilt.is_err() && result.is_ok() {
+                result = rebuilt;
             }
+            h.apply_disabled(&self.disabled_behaviors);
         }
 
-        while let Some((d, idx, handler_id)) = with_idx.pop() {
-            simple.append(&mut self.add_single_danmaku(d, Some((idx, handler_id))));
+        result
+    }
+
+    // Creates handlers ahead of time, like during a loading screen, so the first danmaku of a
+    // pattern don't have to wait for them. cleanup keeps these handlers around, at their size
+    pub fn prewarm(&mut self, sets: &[(Vec<&'static str>, usize)]) -> Result<(), DanCoreError> {
+        for (behaviors, capacity) in sets {
+            self.reserve(behaviors, *capacity)?;
+
+            let mut behaviors = behaviors.clone();
+            canonicalize_behaviors(&mut behaviors, &self.implicit_behaviors);
+            if let Some(handler) = find_handler(&mut self.handlers, &behaviors) {
+                handler.always_keep = true;
+            }
         }
 
-        self.add_danmaku(simple)
+        Ok(())
     }
 
-    pub fn render_data(&mut self, partial_ticks: f32) -> Vec<RenderData> {
-        let mut local_render_data: HashMap<i128, RenderData> = self
-            .handlers
-            .values_mut()
-            .flat_map(|h| h.compute_and_get_render_data(partial_ticks))
+    // Every registered behavior with the columns it needs, sorted by id
+    pub fn registered_behaviors(&self) -> Vec<(&'static str, EnumSet<C::DataColumns>)> {
+        let mut behaviors: Vec<_> = self
+            .behaviors
+            .iter()
+            .map(|(id, behavior)| (*id, behavior.required_columns))
             .collect();
+        behaviors.sort_by_key(|(id, _)| *id);
+        behaviors
+    }
 
-        let mut remaining_relationships: PriorityQueue<_, i16> = self
-            .global_parent_map
+    pub fn registered_behavior(&self, id: &str) -> Option<Rc<Behavior<C>>> {
+        self.behaviors.get(id).map(Rc::clone)
+    }
+
+    // The columns a handler for these behaviors would allocate
+    pub fn required_columns_for(
+        &self,
+        behaviors: &[&'static str],
+    ) -> Result<EnumSet<C::DataColumns>, DanCoreError> {
+        behaviors
             .iter()
-            .map(|(child, parent)| {
-                let depth = *self.global_family_depth_map.get(child).unwrap_or(&0);
-                ((child, parent), depth)
+            .map(|b| {
+                self.behaviors
+                    .get(b)
+                    .map(|behavior| behavior.required_columns)
+                    .ok_or_else(|| DanCoreError::UnknownBehavior(b.to_string()))
             })
-            .collect();
+            .collect()
+    }
 
-        while let Some(((child_id, parent_id), _)) = remaining_relationships.pop() {
-            let parent_opt = local_render_data.get(parent_id).map(|p| p.model_mat);
+    pub fn validate(&self) -> Result<(), DanCoreError> {
+        self.handlers
+            .values()
+            .flatten()
+            .try_for_each(|h| h.validate())
+    }
 
-            if let Entry::Occupied(mut o) = local_render_data.entry(*child_id) {
-                match parent_opt {
-                    Some(parent) => {
-                        o.get_mut().model_mat = parent * o.get().model_mat;
-                    }
-                    None => {
-                        o.remove();
-                    }
-                };
+    pub fn live_count(&self) -> usize {
+        self.handlers.values().flatten().map(|h| h.count()).sum()
+    }
+
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut report = MemoryReport::default();
+        for h in self.handlers.values().flatten() {
+            for (column, bytes) in h.columns.memory_usage() {
+                *report.columns.entry(column).or_default() += bytes;
             }
         }
 
-        local_render_data.into_values().collect()
+        report
     }
 
-    pub fn cleanup(&mut self) {
-        self.handlers.retain(|_, h| h.always_keep || h.count() > 0);
-        // TODO: Scale down
+    pub fn resize_count(&self) -> usize {
+        self.handlers
+            .values()
+            .flatten()
+            .map(|h| h.resize_count)
+            .sum()
     }
-}
 
-struct DanmakuBehaviorHandler<C: DanmakuData> {
-    always_keep: bool,
-    identifier: i64,
-    next_dan_identifier: i64,
-
-    size_exp: u8,
-    current_size: usize,
+    fn add_single_danmaku(
+        &mut self,
+        d: DanmakuSpawnData<C::SpawnData, C::DataColumns>,
+        // The index of the slot to reuse, and the id of the danmaku that died there
+        preferred_idx: Option<(usize, i128)>,
+    ) -> Option<AddedDanmaku<C>> {
+        if find_handler(&mut self.handlers, &d.behaviors).is_none() {
+            if let Err(err) = self.create_handler(&d.behaviors, 0) {
+                // Easy to hit with serialized patterns, so drop the danmaku instead of panicking
+                log::warn!("Dropping danmaku with behaviors {:?}: {}", d.behaviors, err);
+                return None;
+            }
+        }
+        let handler = find_handler(&mut self.handlers, &d.behaviors).unwrap();
 
-    behaviors: Vec<Rc<Behavior<C>>>,
-    columns: C,
-}
+        if handler.ids_exhausted() {
+            // Pending next stages of the old identifier lose their preferred index, which is fine
+            self.next_identifier += 1;
+            handler.roll_identifier(self.next_identifier);
+        }
 
-impl<C: DanmakuData> DanmakuBehaviorHandler<C> {
-    fn new(
-        identifier: i64,
-        behaviors: Vec<Rc<Behavior<C>>>,
-        always_keep: bool,
-    ) -> DanmakuBehaviorHandler<C> {
-        let required_main_columns: EnumSet<C::DataColumns> =
-            behaviors.iter().map(|b| b.required_columns).collect();
+        Some(handler.add_danmaku_with_preffered_index(
+            d,
+            preferred_idx.filter(|(_, dead_id)| (dead_id >> 64) as i64 == handler.identifier),
+            &mut self.global_family_depth_map,
+            &mut self.global_parent_map,
+        ))
+    }
 
-        let size_exp = 7;
-        let max_size = 1 << size_exp;
+    // Adds a single danmaku, and its children, returning its id. None if it was dropped
+    pub fn spawn_one(
+        &mut self,
+        mut danmaku: DanmakuSpawnData<C::SpawnData, C::DataColumns>,
+    ) -> Option<i128> {
+        if !self.accept_spawn(&mut danmaku) {
+            return None;
+        }
 
-        DanmakuBehaviorHandler {
-            always_keep,
-            identifier,
-            next_dan_identifier: 0,
+        let (id, children) = self.add_single_danmaku(danmaku, None)?;
+        self.add_danmaku(children);
+        Some(id)
+    }
 
-            size_exp,
-            current_size: 0,
+    pub fn add_danmaku(&mut self, danmaku: Vec<DanmakuSpawnData<C::SpawnData, C::DataColumns>>) {
+        self.log_spawn_storm(danmaku.len());
+        let mut pending = danmaku;
 
-            behaviors,
-            columns: C::new(max_size, required_main_columns),
+        while let Some(mut d) = pending.pop() {
+            if self.accept_spawn(&mut d) {
+                if let Some((_, mut children)) = self.add_single_danmaku(d, None) {
+                    pending.append(&mut children);
+                }
+            }
         }
     }
 
-    fn current_max_size(&self) -> usize {
-        1 << self.size_exp
+    // Like add_danmaku, but looks up the handler once for each set of behaviors, and makes room for
+    // all of its danmaku at once. Worth it for large bursts of danmaku with the same behaviors
+    pub fn add_danmaku_grouped(
+        &mut self,
+        danmaku: Vec<DanmakuSpawnData<C::SpawnData, C::DataColumns>>,
+    ) {
+        self.log_spawn_storm(danmaku.len());
+        let mut pending = danmaku;
+
+        while !pending.is_empty() {
+            let mut groups: HashMap<Vec<&'static str>, Vec<_>> = HashMap::new();
+            for mut d in pending {
+                if self.accept_spawn(&mut d) {
+                    groups.entry(d.behaviors.clone()).or_default().push(d);
+                }
+            }
+
+            pending = vec![];
+            for (behaviors, group) in groups {
+                pending.append(&mut self.add_danmaku_group(&behaviors, group));
+            }
+        }
     }
 
-    fn dead(&self) -> usize {
-        self.columns.current_dead_len()
+    pub fn set_spawn_storm_threshold(&mut self, threshold: usize) {
+        self.spawn_storm_threshold = threshold;
     }
 
-    fn count(&self) -> usize {
-        self.current_size - self.dead()
+    fn log_spawn_storm(&self, count: usize) {
+        #[cfg(feature = "diagnostics")]
+        if count > self.spawn_storm_threshold {
+            log::debug!(
+                "Adding {} danmaku at once, over the threshold of {}",
+                count,
+                self.spawn_storm_threshold
+            );
+        }
+        #[cfg(not(feature = "diagnostics"))]
+        let _ = count;
     }
 
-    fn should_resize_up_soon(&self) -> bool {
-        if self.size_exp > 30 {
-            return false;
+    fn add_danmaku_group(
+        &mut self,
+        behaviors: &[&'static str],
+        group: Vec<DanmakuSpawnData<C::SpawnData, C::DataColumns>>,
+    ) -> Vec<DanmakuSpawnData<C::SpawnData, C::DataColumns>> {
+        if let Err(err) = self.reserve_additional(behaviors, group.len()) {
+            log::warn!(
+                "Dropping {} danmaku with behaviors {:?}: {}",
+                group.len(),
+                behaviors,
+                err
+            );
+            return vec![];
         }
+        let handler = find_handler(&mut self.handlers, behaviors).unwrap();
 
-        let max = self.current_max_size();
-        self.current_size as f64 + (max as f64 * 0.1) > max as f64
+        let mut children = vec![];
+        for d in group {
+            if handler.ids_exhausted() {
+                self.next_identifier += 1;
+                handler.roll_identifier(self.next_identifier);
+            }
+
+            let (_, mut added_children) = handler.add_danmaku_with_preffered_index(
+                d,
+                None,
+                &mut self.global_family_depth_map,
+                &mut self.global_parent_map,
+            );
+            children.append(&mut added_children);
+        }
+
+        children
     }
 
-    fn should_resize_down_soon(&self) -> bool {
-        if self.size_exp < 8 {
-            return false;
+    fn reserve_additional(
+        &mut self,
+        behaviors: &[&'static str],
+        additional: usize,
+    ) -> Result<(), DanCoreError> {
+        match find_handler(&mut self.handlers, behaviors) {
+            Some(handler) => {
+                handler.reserve(handler.current_size + additional);
+                Ok(())
+            }
+            None => self.create_handler(behaviors, additional),
         }
-        let step_down_max_size = 1 << (self.size_exp - 1);
-        let surplus_if_step_down = step_down_max_size - self.current_size;
-        surplus_if_step_down as f64 > (step_down_max_size as f64 * 0.1)
     }
 
-    fn must_resize_before_add(&self, length: usize) -> bool {
-        self.current_size + length >= self.current_max_size()
+    pub fn set_on_death(&mut self, on_death: impl FnMut(i128, &DanmakuView) + 'static) {
+        self.on_death = Some(Box::new(on_death));
     }
 
-    fn add_danmaku_with_preffered_index(
-        &mut self,
-        mut danmaku: DanmakuSpawnData<C::SpawnData, C::DataColumns>,
-        idx: Option<usize>,
-        global_family_depth_map: &mut HashMap<i128, i16>,
-        global_parent_map: &mut HashMap<i128, i128>,
-    ) -> Vec<DanmakuSpawnData<C::SpawnData, C::DataColumns>> {
-        let idx_with_filter = idx.filter(|i| *self.columns.dead().get(*i).unwrap_or(&false));
-        let i = idx_with_filter.unwrap_or(self.current_size);
+    pub fn clear_on_death(&mut self) {
+        self.on_death = None;
+    }
 
-        if self.must_resize_before_add(if idx_with_filter.is_some() { 0 } else { 1 }) {
-            self.resize(true)
-        }
+    pub fn set_max_family_depth(&mut self, max_family_depth: i16) {
+        self.max_family_depth = max_family_depth;
+    }
 
-        self.current_size += 1;
+    // Applies to existing handlers from their next resize
+    pub fn set_resize_policy(&mut self, resize_policy: ResizePolicy) {
+        self.resize_policy = resize_policy;
+        self.handlers
+            .values_mut()
+            .flatten()
+            .for_each(|h| h.resize_policy = resize_policy);
+    }
 
-        let this_id = ((self.identifier as i128) << 64) + (self.next_dan_identifier as i128);
-        self.next_dan_identifier += 1;
-        for c in &mut danmaku.children.iter_mut() {
-            c.parent = Some(this_id);
-        }
+    // Only affects handlers created after this. Small for patterns known to stay small, like a
+    // single boss, or large to skip the resizes of patterns known to be huge. Clamped to
+    // MAX_INITIAL_SIZE_EXP, as reserve is the way to make room for more than that
+    pub fn set_initial_size_exp(&mut self, size_exp: u8) {
+        self.initial_size_exp = size_exp.min(MAX_INITIAL_SIZE_EXP);
+    }
 
-        danmaku.parent.iter().for_each(|parent_id| {
-            global_parent_map.insert(this_id, *parent_id);
-        });
-        global_family_depth_map.insert(this_id, danmaku.family_depth);
+    pub fn set_render_order(&mut self, render_order: RenderOrder) {
+        self.render_order = render_order;
+    }
 
-        self.columns.add_danmaku_at_idx(i, danmaku, this_id)
+    pub fn set_cascade_death(&mut self, cascade_death: bool) {
+        self.cascade_death = cascade_death;
     }
 
-    fn tick(
-        &mut self,
-    ) -> Vec<(
-        DanmakuSpawnData<C::SpawnData, C::DataColumns>,
-        Option<usize>,
-    )> {
-        for behavior in self.behaviors.iter() {
-            (behavior.act)(&mut self.columns, self.current_size);
+    fn accept_spawn(&self, d: &mut DanmakuSpawnData<C::SpawnData, C::DataColumns>) -> bool {
+        if !d.set_family_depth(&self.global_family_depth_map) {
+            return false;
         }
 
-        self.columns.grab_new_spawns()
+        if let Err(err) = self.check_family_depth(d.family_depth) {
+            log::warn!(
+                "Dropping danmaku with family depth {}: {}",
+                d.family_depth,
+                err
+            );
+            return false;
+        }
+
+        // The order behaviors run in comes from Behavior::order, so the order they are listed in
+        // doesn't matter, and the same behaviors in another order can share a handler
+        canonicalize_behaviors(&mut d.behaviors, &self.implicit_behaviors);
+
+        true
     }
 
-    fn compute_and_get_render_data(&mut self, partial_ticks: f32) -> Vec<(i128, RenderData)> {
-        self.columns
-            .compute_and_get_render_data(self.current_size, partial_ticks)
+    fn check_family_depth(&self, family_depth: i16) -> Result<(), DanCoreError> {
+        if family_depth > self.max_family_depth {
+            return Err(DanCoreError::DepthLimitExceeded);
+        }
+
+        Ok(())
     }
 
-    fn resize(&mut self, force_up: bool) {
-        if force_up || self.should_resize_up_soon() {
-            self.size_exp += 1;
-            self.columns.resize(self.current_max_size());
-        } else if self.should_resize_down_soon() {
-            let dead = self.dead();
-            self.size_exp -= 1;
-            self.columns.compact(self.current_max_size());
-            self.current_size -= dead;
-        } else {
-            // Something weird is going on. Cancel the resizing
+    pub fn tick(&mut self) {
+        if self.paused {
             return;
         }
+
+        let mut with_idx: Vec<(_, usize, i128)> = vec![];
+        let mut simple = vec![];
+
+        self.context.tick_count = self.tick_count;
+        for h in self.handlers.values_mut().flatten() {
+            for (d, idx) in h.tick(&mut self.on_death, &self.context) {
+                match idx {
+                    None => simple.push(d),
+                    Some(i) => with_idx.push((d, i, h.columns.ids()[i])),
+                }
+            }
+        }
+
+        while let Some((mut d, idx, dead_id)) = with_idx.pop() {
+            if self.accept_spawn(&mut d) {
+                if let Some((_, mut children)) = self.add_single_danmaku(d, Some((idx, dead_id))) {
+                    simple.append(&mut children);
+                }
+            }
+        }
+
+        self.add_danmaku(simple);
+        self.tick_count += 1;
+    }
+
+    // Finds the identifier of the handler a danmaku is in, and its index in that handler
+    pub fn locate(&self, id: i128) -> Option<(i64, usize)> {
+        let handler_identifier = (id >> 64) as i64;
+        let handler = self
+            .handlers
+            .values()
+            .flatten()
+            .find(|h| h.has_identifier(handler_identifier))?;
+        handler.index_of(id).map(|idx| (handler.identifier, idx))
+    }
+
+    // Kills a danmaku without spawning its next stages. Returns false if it wasn't alive
+    pub fn remove_danmaku(&mut self, id: i128) -> bool {
+        if !self.kill(id) {
+            return false;
+        }
+
+        if self.cascade_death && !self.global_parent_map.is_empty() {
+            // Children whose parent is gone are no longer rendered, so they shouldn't keep existing.
+            // Looking up the children of each parent in the map would be quadratic for large
+            // families, so they are indexed once for the whole cascade
+            let mut children: HashMap<i128, Vec<i128>> = HashMap::new();
+            for (child, parent) in self.global_parent_map.iter() {
+                children.entry(*parent).or_default().push(*child);
+            }
+
+            let mut removed = HashSet::from([id]);
+            let mut pending = vec![id];
+            while let Some(parent_id) = pending.pop() {
+                for child in children.remove(&parent_id).unwrap_or_default() {
+                    if removed.insert(child) {
+                        self.kill(child);
+                        pending.push(child);
+                    }
+                }
+            }
+        }
+
+        true
+    }
+
+    pub fn get_danmaku(&self, id: i128) -> Option<DanmakuView> {
+        let (handler_identifier, idx) = self.locate(id)?;
+        self.handlers
+            .values()
+            .flatten()
+            .find(|h| h.identifier == handler_identifier)
+            .map(|h| h.columns.view(idx))
+    }
+
+    fn kill(&mut self, id: i128) -> bool {
+        let Some((handler_identifier, idx)) = self.locate(id) else {
+            return false;
+        };
+
+        if let Some(h) = self
+            .handlers
+            .values_mut()
+            .flatten()
+            .find(|h| h.identifier == handler_identifier)
+        {
+            h.columns.kill(idx);
+        }
+        true
+    }
+
+    pub fn tick_count(&self) -> u64 {
+        self.tick_count
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn context(&self) -> &HandlerContext {
+        &self.context
+    }
+
+    // Changes apply from the next tick
+    pub fn context_mut(&mut self) -> &mut HandlerContext {
+        &mut self.context
+    }
+
+    // Disabled behaviors are skipped when ticking, but their columns stay allocated so they can be
+    // enabled again at any time. Also applies to handlers created later
+    pub fn set_behavior_enabled(&mut self, id: &'static str, enabled: bool) {
+        if enabled {
+            self.disabled_behaviors.remove(id);
+        } else {
+            self.disabled_behaviors.insert(id);
+        }
+
+        self.handlers
+            .values_mut()
+            .flatten()
+            .for_each(|h| h.apply_disabled(&self.disabled_behaviors));
+    }
+
+    pub fn is_behavior_enabled(&self, id: &str) -> bool {
+        !self.disabled_behaviors.contains(id)
+    }
+
+    pub fn render_data(&mut self, partial_ticks: f32) -> Vec<RenderData<'_>> {
+        self.render_data_with_ids(partial_ticks)
+            .into_iter()
+            .map(|(_, r)| r)
+            .collect()
+    }
+
+    pub fn render_data_with_ids(&mut self, partial_ticks: f32) -> Vec<(i128, RenderData<'_>)> {
+        let render_data = self
+            .handlers
+            .values_mut()
+            .flatten()
+            .flat_map(|h| h.compute_and_get_render_data(partial_ticks));
+
+        let mut render_data: Vec<(i128, RenderData)> = if self.global_parent_map.is_empty() {
+            render_data.collect()
+        } else {
+            let mut local_render_data: HashMap<i128, RenderData> = render_data.collect();
+            resolve_parent_transforms(
+                &self.global_parent_map,
+                &self.global_family_depth_map,
+                &mut self.parent_order,
+                &mut local_render_data,
+                |r| &mut r.model_mat,
+            );
+            local_render_data.into_iter().collect()
+        };
+
+        sort_render_data(self.render_order, &mut render_data);
+        render_data
+    }
+
+    // Only renders the danmaku of handlers whose behaviors match the predicate, like to draw some
+    // of them to another layer. Parents are still taken into account when not rendered themselves
+    pub fn render_data_filtered(
+        &mut self,
+        partial_ticks: f32,
+        predicate: impl Fn(&[&'static str]) -> bool,
+    ) -> Vec<RenderData<'_>> {
+        let rendered_handlers: HashSet<i64> = self
+            .handlers
+            .values()
+            .flatten()
+            .filter(|h| predicate(&h.behavior_ids))
+            .flat_map(|h| h.retired_identifiers.iter().copied().chain([h.identifier]))
+            .collect();
+
+        // Parents can be in any handler, so the transforms of the handlers holding the parents of
+        // rendered danmaku, and their parents in turn, are needed too
+        let mut ancestor_handlers: HashSet<i64> = HashSet::new();
+        let mut visited: HashSet<i128> = HashSet::new();
+        for child in self
+            .global_parent_map
+            .keys()
+            .filter(|child| rendered_handlers.contains(&((*child >> 64) as i64)))
+        {
+            let mut current = self.global_parent_map.get(child);
+            while let Some(parent) = current.filter(|parent| visited.insert(**parent)) {
+                ancestor_handlers.insert((parent >> 64) as i64);
+                current = self.global_parent_map.get(parent);
+            }
+        }
+        let world_transforms = if ancestor_handlers.is_empty() {
+            HashMap::new()
+        } else {
+            self.world_transforms_of(partial_ticks, |h| {
+                h.retired_identifiers
+                    .iter()
+                    .chain([&h.identifier])
+                    .any(|identifier| ancestor_handlers.contains(identifier))
+            })
+        };
+
+        let mut render_data: Vec<(i128, RenderData)> = self
+            .handlers
+            .values_mut()
+            .flatten()
+            .filter(|h| rendered_handlers.contains(&h.identifier))
+            .flat_map(|h| h.compute_and_get_render_data(partial_ticks))
+            .filter_map(|(id, mut r)| {
+                if let Some(parent_id) = self.global_parent_map.get(&id) {
+                    // Like when rendering everything, danmaku whose parent is gone aren't rendered
+                    r.model_mat = world_transforms.get(parent_id)? * r.model_mat;
+                }
+                Some((id, r))
+            })
+            .collect();
+
+        sort_render_data(self.render_order, &mut render_data);
+        render_data.into_iter().map(|(_, r)| r).collect()
+    }
+
+    pub fn render_instances(&mut self, partial_ticks: f32) -> InstanceBuffers {
+        InstanceBuffers::from_render_data(self.render_data(partial_ticks))
+    }
+
+    fn world_transforms(&mut self, partial_ticks: f32) -> HashMap<i128, Matrix4<f32>> {
+        self.world_transforms_of(partial_ticks, |_| true)
+    }
+
+    // Only computes the transforms of the handlers matching include. Parents in other handlers are
+    // missing then, so their children are left out
+    fn world_transforms_of(
+        &mut self,
+        partial_ticks: f32,
+        include: impl Fn(&DanmakuBehaviorHandler<C>) -> bool,
+    ) -> HashMap<i128, Matrix4<f32>> {
+        let mut transforms: HashMap<i128, Matrix4<f32>> = self
+            .handlers
+            .values_mut()
+            .flatten()
+            .filter(|h| include(h))
+            .flat_map(|h| h.transform_mats(partial_ticks))
+            .collect();
+
+        resolve_parent_transforms(
+            &self.global_parent_map,
+            &self.global_family_depth_map,
+            &mut self.parent_order,
+            &mut transforms,
+            |m| m,
+        );
+
+        transforms
+    }
+
+    // The world transform of a single danmaku, like to attach an effect to it. Parents are applied
+    // like when rendering, so danmaku whose parent is gone have none
+    pub fn model_matrix_of(&mut self, id: i128, partial_ticks: f32) -> Option<Matrix4<f32>> {
+        let mut model_mat = Matrix4::identity();
+        let mut current = Some(id);
+        while let Some(id) = current {
+            model_mat = self.local_transform(id, partial_ticks)? * model_mat;
+            current = self.global_parent_map.get(&id).copied();
+        }
+        Some(model_mat)
+    }
+
+    fn local_transform(&mut self, id: i128, partial_ticks: f32) -> Option<Matrix4<f32>> {
+        let (handler_identifier, idx) = self.locate(id)?;
+        self.handlers
+            .values_mut()
+            .flatten()
+            .find(|h| h.identifier == handler_identifier)?
+            .columns
+            .transform_mat_of(idx, partial_ticks)
+    }
+
+    // The box around the world positions of all live danmaku, like to frame them with the camera
+    pub fn scene_bounds(&mut self, partial_ticks: f32) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        self.world_transforms(partial_ticks)
+            .values()
+            .map(|transform| transform.column(3).xyz())
+            .fold(None, |bounds, pos| match bounds {
+                None => Some((pos, pos)),
+                Some((min, max)) => Some((min.inf(&pos), max.sup(&pos))),
+            })
+    }
+
+    pub fn collect_damage(&mut self, hitbox: (Vector3<f32>, f32), partial_ticks: f32) -> f32 {
+        let world_transforms = self.world_transforms(partial_ticks);
+
+        self.handlers
+            .values_mut()
+            .flatten()
+            .map(|h| {
+                h.columns
+                    .collect_damage(h.current_size, &world_transforms, hitbox)
+            })
+            .sum()
+    }
+
+    // Drops the handler for these behaviors together with its danmaku, like when a stage retires a
+    // pattern. Nothing is reported as dead. Returns false if there is no such handler
+    pub fn clear_behaviors(&mut self, behaviors: &[&'static str]) -> bool {
+        let mut behaviors = behaviors.to_vec();
+        canonicalize_behaviors(&mut behaviors, &self.implicit_behaviors);
+
+        let hash = behavior_set_hash(&behaviors);
+        let Some(bucket) = self.handlers.get_mut(&hash) else {
+            return false;
+        };
+        let Some(idx) = bucket.iter().position(|h| h.behavior_ids == behaviors) else {
+            return false;
+        };
+
+        let handler = bucket.remove(idx);
+        if bucket.is_empty() {
+            self.handlers.remove(&hash);
+        }
+
+        let removed: HashSet<i128> = handler.columns.ids()[0..handler.current_size]
+            .iter()
+            .copied()
+            .collect();
+        self.global_family_depth_map
+            .retain(|id, _| !removed.contains(id));
+        self.global_parent_map.retain(|id, _| !removed.contains(id));
+        true
+    }
+
+    // Moves all danmaku of the handler with these behaviors, like for a field following a boss.
+    // Returns false if there is no such handler
+    pub fn translate_handler(&mut self, behaviors: &[&'static str], delta: Vector3<f32>) -> bool {
+        let mut behaviors = behaviors.to_vec();
+        canonicalize_behaviors(&mut behaviors, &self.implicit_behaviors);
+
+        let Some(handler) = find_handler(&mut self.handlers, &behaviors) else {
+            return false;
+        };
+        handler.columns.translate(handler.current_size, delta);
+        true
+    }
+
+    pub fn snapshot(&self) -> DanmakuSnapshot<C>
+    where
+        C: Clone,
+    {
+        DanmakuSnapshot {
+            handlers: self.handlers.clone(),
+            global_family_depth_map: self.global_family_depth_map.clone(),
+            global_parent_map: self.global_parent_map.clone(),
+            next_identifier: self.next_identifier,
+            tick_count: self.tick_count,
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &DanmakuSnapshot<C>)
+    where
+        C: Clone,
+    {
+        let DanmakuSnapshot {
+            handlers,
+            global_family_depth_map,
+            global_parent_map,
+            next_identifier,
+            tick_count,
+        } = snapshot.clone();
+
+        self.handlers = handlers;
+        self.global_family_depth_map = global_family_depth_map;
+        self.global_parent_map = global_parent_map;
+        self.next_identifier = next_identifier;
+        self.tick_count = tick_count;
+
+        // Which behaviors are enabled isn't part of the snapshot, so keep the current settings
+        self.handlers
+            .values_mut()
+            .flatten()
+            .for_each(|h| h.apply_disabled(&self.disabled_behaviors));
+    }
+
+    pub fn cleanup(&mut self) {
+        self.handlers.retain(|_, handlers| {
+            handlers.retain(|h| h.always_keep || h.count() > 0);
+            !handlers.is_empty()
+        });
+        self.handlers
+            .values_mut()
+            .flatten()
+            .filter(|h| !h.always_keep)
+            .for_each(|h| h.shrink_to_fit());
+        self.prune_family_maps();
+    }
+
+    pub fn compact_all(&mut self) {
+        self.handlers
+            .values_mut()
+            .flatten()
+            .for_each(|h| h.compact());
+        self.prune_family_maps();
+    }
+
+    // Every spawn adds entries to the family maps, so the entries of dead danmaku are removed here
+    // to keep them from growing forever
+    fn prune_family_maps(&mut self) {
+        let live: HashSet<i128> = self
+            .handlers
+            .values()
+            .flatten()
+            .flat_map(|h| h.live_ids())
+            .collect();
+
+        self.global_family_depth_map
+            .retain(|id, _| live.contains(id));
+        self.global_parent_map.retain(|id, _| live.contains(id));
+    }
+}
+
+fn resolve_behaviors<C: DanmakuData>(
+    registered: &HashMap<&'static str, Rc<Behavior<C>>>,
+    behavior_ids: &[&'static str],
+) -> Result<Vec<Rc<Behavior<C>>>, DanCoreError> {
+    behavior_ids
+        .iter()
+        .map(|b| {
+            registered
+                .get(b)
+                .map(Rc::clone)
+                .ok_or_else(|| DanCoreError::UnknownBehavior(b.to_string()))
+        })
+        .collect()
+}
+
+// Applies the transforms of parents to their children. Children whose parent is gone are removed
+fn resolve_parent_transforms<T>(
+    global_parent_map: &HashMap<i128, i128>,
+    global_family_depth_map: &HashMap<i128, i16>,
+    order: &mut Vec<(i16, i128, i128)>,
+    local: &mut HashMap<i128, T>,
+    model_mat: fn(&mut T) -> &mut Matrix4<f32>,
+) {
+    if global_parent_map.is_empty() {
+        return;
+    }
+
+    // Shallowest first, so the transform of a parent already includes those of its own parents
+    // by the time its children use it
+    order.clear();
+    order.extend(global_parent_map.iter().map(|(child, parent)| {
+        let depth = *global_family_depth_map.get(child).unwrap_or(&0);
+        (depth, *child, *parent)
+    }));
+    order.sort_unstable_by_key(|(depth, _, _)| *depth);
+
+    for (_, child_id, parent_id) in order.iter() {
+        let parent_opt = local.get_mut(parent_id).map(|p| *model_mat(p));
+
+        if let Entry::Occupied(mut o) = local.entry(*child_id) {
+            match parent_opt {
+                Some(parent) => {
+                    let child_mat = model_mat(o.get_mut());
+                    *child_mat = parent * *child_mat;
+                }
+                None => {
+                    o.remove();
+                }
+            };
+        }
+    }
+}
+
+const DEFAULT_SIZE_EXP: u8 = 7;
+// Every new handler would allocate this much up front, so larger sizes are left to reserve
+pub const MAX_INITIAL_SIZE_EXP: u8 = 20;
+
+// The smallest size exponent that can hold the given amount of danmaku without resizing
+fn size_exp_for(capacity: usize) -> u8 {
+    (usize::BITS - capacity.leading_zeros()) as u8
+}
+
+#[derive(Clone)]
+struct DanmakuBehaviorHandler<C: DanmakuData> {
+    always_keep: bool,
+    identifier: i64,
+    // Identifiers the handler had before its ids ran out, which its older danmaku still carry
+    retired_identifiers: Vec<i64>,
+    next_dan_identifier: i64,
+    // Sorted and without duplicates, which is how handlers are looked up
+    behavior_ids: Vec<&'static str>,
+
+    size_exp: u8,
+    current_size: usize,
+    resize_count: usize,
+    resize_policy: ResizePolicy,
+
+    behaviors: Vec<Rc<Behavior<C>>>,
+    // Indexed the same as behaviors
+    enabled: Vec<bool>,
+    columns: C,
+}
+
+impl<C: DanmakuData> DanmakuBehaviorHandler<C> {
+    fn new(
+        identifier: i64,
+        behavior_ids: Vec<&'static str>,
+        mut behaviors: Vec<Rc<Behavior<C>>>,
+        always_keep: bool,
+        size_exp: u8,
+    ) -> DanmakuBehaviorHandler<C> {
+        // Stable, so behaviors with the same order run sorted by their ids
+        behaviors.sort_by_key(|b| b.order);
+
+        let required_main_columns: EnumSet<C::DataColumns> =
+            behaviors.iter().map(|b| b.required_columns).collect();
+
+        let max_size = 1 << size_exp;
+
+        DanmakuBehaviorHandler {
+            always_keep,
+            identifier,
+            retired_identifiers: vec![],
+            next_dan_identifier: 0,
+            behavior_ids,
+
+            size_exp,
+            current_size: 0,
+            resize_count: 0,
+            resize_policy: ResizePolicy::default(),
+
+            enabled: vec![true; behaviors.len()],
+            behaviors,
+            columns: C::new(max_size, required_main_columns),
+        }
+    }
+
+    fn apply_disabled(&mut self, disabled: &HashSet<&'static str>) {
+        for (behavior, enabled) in self.behaviors.iter().zip(self.enabled.iter_mut()) {
+            *enabled = !disabled.contains(behavior.identifier);
+        }
+    }
+
+    fn replace_behaviors(
+        &mut self,
+        mut behaviors: Vec<Rc<Behavior<C>>>,
+    ) -> Result<(), DanCoreError> {
+        behaviors.sort_by_key(|b| b.order);
+
+        let old = std::mem::replace(&mut self.behaviors, behaviors);
+        if let Err(err) = self.validate() {
+            self.behaviors = old;
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    // Behaviors index straight into the columns, so every column they use has to be allocated
+    fn validate(&self) -> Result<(), DanCoreError> {
+        let allocated = self.columns.required_columns();
+        for behavior in &self.behaviors {
+            let missing = behavior.required_columns - allocated;
+            if !missing.is_empty() {
+                log::error!(
+                    "Behavior {} requires the columns {:?}, which are not allocated",
+                    behavior.identifier,
+                    missing
+                );
+                return Err(DanCoreError::ColumnNotAllocated(behavior.identifier));
+            }
+
+            if let Some(required) = behavior
+                .required_behaviors
+                .iter()
+                .find(|id| !self.behaviors.iter().any(|b| b.identifier == **id))
+            {
+                log::error!(
+                    "Behavior {} requires the behavior {}, which is not used with it",
+                    behavior.identifier,
+                    required
+                );
+                return Err(DanCoreError::MissingBehavior(behavior.identifier, required));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn current_max_size(&self) -> usize {
+        1 << self.size_exp
+    }
+
+    fn dead(&self) -> usize {
+        self.columns.current_dead_len()
+    }
+
+    fn live_ids(&self) -> impl Iterator<Item = i128> + '_ {
+        self.columns.ids()[0..self.current_size]
+            .iter()
+            .enumerate()
+            .filter(|(idx, _)| !self.columns.is_dead(*idx))
+            .map(|(_, id)| *id)
+    }
+
+    fn index_of(&self, id: i128) -> Option<usize> {
+        self.columns.ids()[0..self.current_size]
+            .iter()
+            .enumerate()
+            .find(|(idx, i)| **i == id && !self.columns.is_dead(*idx))
+            .map(|(idx, _)| idx)
+    }
+
+    fn count(&self) -> usize {
+        self.current_size - self.dead()
+    }
+
+    fn should_resize_up_soon(&self) -> bool {
+        if self.size_exp > 30 {
+            return false;
+        }
+
+        let max = self.current_max_size();
+        self.current_size as f64 + (max as f64 * self.resize_policy.grow_margin) > max as f64
+    }
+
+    fn should_resize_down_soon(&self) -> bool {
+        if self.size_exp <= self.resize_policy.min_size_exp {
+            return false;
+        }
+        let step_down_max_size = 1 << (self.size_exp - 1);
+        // Dead danmaku are compacted away when stepping down, so only the live ones need to fit
+        let surplus_if_step_down = step_down_max_size as f64 - self.count() as f64;
+        surplus_if_step_down > (step_down_max_size as f64 * self.resize_policy.shrink_surplus)
+    }
+
+    fn must_resize_before_add(&self, length: usize) -> bool {
+        self.current_size + length >= self.current_max_size()
+    }
+
+    fn ids_exhausted(&self) -> bool {
+        self.next_dan_identifier == i64::MAX
+    }
+
+    // Gives the handler a fresh id space, so new ids can't collide with those of live danmaku
+    fn roll_identifier(&mut self, identifier: i64) {
+        log::warn!(
+            "Danmaku ids of handler {} ran out, continuing as handler {}",
+            self.identifier,
+            identifier
+        );
+        self.retired_identifiers.push(self.identifier);
+        self.identifier = identifier;
+        self.next_dan_identifier = 0;
+    }
+
+    fn has_identifier(&self, identifier: i64) -> bool {
+        self.identifier == identifier || self.retired_identifiers.contains(&identifier)
+    }
+
+    fn add_danmaku_with_preffered_index(
+        &mut self,
+        mut danmaku: DanmakuSpawnData<C::SpawnData, C::DataColumns>,
+        idx: Option<(usize, i128)>,
+        global_family_depth_map: &mut HashMap<i128, i16>,
+        global_parent_map: &mut HashMap<i128, i128>,
+    ) -> AddedDanmaku<C> {
+        // Compacting moves danmaku around, so the slot is only reused if the danmaku that died there
+        // is still in it. Otherwise it could now hold an unrelated live danmaku
+        let idx_with_filter = idx
+            .filter(|(i, dead_id)| {
+                *i < self.current_size
+                    && *self.columns.dead().get(*i).unwrap_or(&false)
+                    && self.columns.ids()[*i] == *dead_id
+            })
+            .map(|(i, _)| i);
+        let i = idx_with_filter.unwrap_or(self.current_size);
+
+        if idx_with_filter.is_none() {
+            if self.must_resize_before_add(1) {
+                self.resize(true)
+            }
+
+            self.current_size += 1;
+        }
+
+        // Ids are the handler identifier in the high 64 bits, and a per handler counter in the low 64
+        let this_id = ((self.identifier as i128) << 64) + (self.next_dan_identifier as i128);
+        self.next_dan_identifier += 1;
+        for c in &mut danmaku.children.iter_mut() {
+            c.parent = Some(this_id);
+        }
+
+        danmaku.parent.iter().for_each(|parent_id| {
+            global_parent_map.insert(this_id, *parent_id);
+        });
+        global_family_depth_map.insert(this_id, danmaku.family_depth);
+
+        (
+            this_id,
+            self.columns.add_danmaku_at_idx(i, danmaku, this_id),
+        )
+    }
+
+    fn tick(
+        &mut self,
+        on_death: &mut Option<DeathCallback>,
+        context: &HandlerContext,
+    ) -> Vec<PendingSpawn<C::SpawnData, C::DataColumns>> {
+        // Danmaku killed between ticks, like by collect_damage, aren't reported
+        self.columns.clear_expired();
+
+        self.columns.invalidate_transform_mats();
+        self.columns.set_context(context);
+        for (behavior, _) in self
+            .behaviors
+            .iter()
+            .zip(&self.enabled)
+            .filter(|(_, e)| **e)
+        {
+            (behavior.act)(&mut self.columns, self.current_size);
+        }
+
+        if let Some(on_death) = on_death {
+            for idx in self.columns.expired_this_tick() {
+                let view = self.columns.view(*idx);
+                on_death(view.id, &view);
+            }
+        }
+
+        self.columns.grab_new_spawns()
+    }
+
+    fn transform_mats(&mut self, partial_ticks: f32) -> Vec<(i128, Matrix4<f32>)> {
+        self.columns
+            .compute_and_get_transform_mats(self.current_size, partial_ticks)
+    }
+
+    fn compute_and_get_render_data(&mut self, partial_ticks: f32) -> Vec<(i128, RenderData<'_>)> {
+        self.columns
+            .compute_and_get_render_data(self.current_size, partial_ticks)
+    }
+
+    fn reserve(&mut self, capacity: usize) {
+        let size_exp = size_exp_for(capacity);
+        if size_exp > self.size_exp {
+            self.size_exp = size_exp;
+            self.columns.resize(self.current_max_size());
+            self.log_grown();
+        }
+    }
+
+    fn log_grown(&self) {
+        #[cfg(feature = "diagnostics")]
+        log::debug!(
+            "Handler {} with behaviors {:?} grew to {}",
+            self.identifier,
+            self.behavior_ids,
+            self.current_max_size()
+        );
+    }
+
+    fn step_down(&mut self) {
+        let dead = self.dead();
+        self.resize_count += 1;
+        self.size_exp -= 1;
+        self.columns.compact(self.current_max_size());
+        self.current_size -= dead;
+    }
+
+    fn compact(&mut self) {
+        let dead = self.dead();
+        if dead > 0 {
+            self.columns.compact(self.current_max_size());
+            self.current_size -= dead;
+        }
+
+        self.shrink_to_fit();
+    }
+
+    fn shrink_to_fit(&mut self) {
+        while self.should_resize_down_soon() {
+            self.step_down();
+        }
+    }
+
+    fn resize(&mut self, force_up: bool) {
+        if force_up || self.should_resize_up_soon() {
+            self.resize_count += 1;
+            self.size_exp += 1;
+            self.columns.resize(self.current_max_size());
+            self.log_grown();
+        } else if self.should_resize_down_soon() {
+            self.step_down();
+        }
+        // Otherwise something weird is going on, and the resizing is cancelled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{ColorHex, ColorLerpMode};
+    use crate::danmaku::standard::{
+        behaviors::*,
+        test_util::{spawn, standard_handler},
+        Pos, StandardColumns, StandardDataColumns, StandardSpawnData,
+    };
+    use crate::danmaku::{
+        data::{InheritOp, IMMORTAL_END_TIME},
+        N,
+    };
+    use crate::form::Form;
+
+    fn motion_spawns(count: usize) -> Vec<crate::danmaku::standard::test_util::Spawn> {
+        (0..count)
+            .map(|i| {
+                spawn(
+                    100,
+                    &[MOTION3_BEHAVIOR_ID],
+                    vec![StandardSpawnData::PosX(Pos::from(i as f32))],
+                )
+            })
+            .collect()
+    }
+
+    fn max_size(handler: &TopDanmakuBehaviorsHandler<StandardColumns>) -> usize {
+        handler
+            .handlers
+            .values()
+            .flatten()
+            .map(|h| h.current_max_size())
+            .sum()
+    }
+
+    // Counts the allocations of each thread, as tests run in parallel
+    struct CountingAllocator;
+
+    thread_local! {
+        static ALLOCATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+
+        unsafe fn realloc(
+            &self,
+            ptr: *mut u8,
+            layout: std::alloc::Layout,
+            new_size: usize,
+        ) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+            std::alloc::System.realloc(ptr, layout, new_size)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    fn allocations_during(f: impl FnOnce()) -> usize {
+        let before = ALLOCATIONS.with(|a| a.get());
+        f();
+        ALLOCATIONS.with(|a| a.get()) - before
+    }
+
+    // Keeps the messages of each thread, like the allocator above
+    #[cfg(feature = "diagnostics")]
+    struct CapturingLogger;
+
+    #[cfg(feature = "diagnostics")]
+    thread_local! {
+        static LOGGED: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(vec![]) };
+    }
+
+    #[cfg(feature = "diagnostics")]
+    impl log::Log for CapturingLogger {
+        fn enabled(&self, _: &log::Metadata) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record) {
+            let _ = LOGGED.try_with(|l| l.borrow_mut().push(record.args().to_string()));
+        }
+
+        fn flush(&self) {}
+    }
+
+    #[cfg(feature = "diagnostics")]
+    fn logged_during(f: impl FnOnce()) -> Vec<String> {
+        static LOGGER: CapturingLogger = CapturingLogger;
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            log::set_logger(&LOGGER).unwrap();
+            log::set_max_level(log::LevelFilter::Debug);
+        });
+
+        LOGGED.with(|l| l.borrow_mut().clear());
+        f();
+        LOGGED.with(|l| l.take())
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn growing_a_handler_logs_one_event() {
+        let mut handler = standard_handler();
+        handler.add_danmaku(motion_spawns(1));
+        let size = max_size(&handler);
+
+        let logged = logged_during(|| handler.add_danmaku(motion_spawns(size)));
+        let grown: Vec<_> = logged.iter().filter(|l| l.contains("grew")).collect();
+        assert_eq!(grown.len(), 1, "{:?}", logged);
+        assert!(grown[0].contains(MOTION3_BEHAVIOR_ID));
+        assert!(grown[0].ends_with(&max_size(&handler).to_string()));
+
+        // Ticking without any resizes logs nothing
+        assert!(logged_during(|| handler.tick()).is_empty());
+    }
+
+    #[test]
+    fn resolving_parents_allocates_nothing_once_warmed_up() {
+        let mut local: HashMap<i128, Matrix4<f32>> = (0..100)
+            .map(|id| {
+                (
+                    id,
+                    Matrix4::new_translation(&Vector3::new(id as f32, 0.0, 0.0)),
+                )
+            })
+            .collect();
+        let expected = local.clone();
+        let mut order = vec![];
+
+        let no_parents = HashMap::new();
+        let depths = HashMap::new();
+        let allocations = allocations_during(|| {
+            resolve_parent_transforms(&no_parents, &depths, &mut order, &mut local, |m| m)
+        });
+        assert_eq!(allocations, 0);
+        assert_eq!(local, expected);
+
+        let parents: HashMap<i128, i128> = (1..100).map(|id| (id, id - 1)).collect();
+        let depths: HashMap<i128, i16> = (0..100).map(|id| (id, id as i16)).collect();
+        let mut resolve = |local: &mut HashMap<i128, Matrix4<f32>>| {
+            resolve_parent_transforms(&parents, &depths, &mut order, local, |m| m)
+        };
+        let mut first = expected.clone();
+        resolve(&mut first);
+        // Every danmaku is offset by all of its ancestors
+        assert_eq!(first[&99].column(3).x, (0..100).sum::<i32>() as f32);
+
+        let mut second = expected.clone();
+        assert_eq!(allocations_during(|| resolve(&mut second)), 0);
+        assert_eq!(second, first);
+    }
+
+    #[test]
+    fn rendering_without_parents_matches_resolving_each_danmaku() {
+        let mut handler = standard_handler();
+        let ids: Vec<i128> = (0..50)
+            .map(|i| {
+                handler
+                    .spawn_one(spawn(
+                        100,
+                        &[MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID],
+                        vec![
+                            StandardSpawnData::Appearance {
+                                form: &Form::SPHERE,
+                            },
+                            StandardSpawnData::PosX(Pos::from(i as f32)),
+                            StandardSpawnData::MotionY(1.0),
+                        ],
+                    ))
+                    .unwrap()
+            })
+            .collect();
+        handler.tick();
+        assert!(handler.global_parent_map.is_empty());
+
+        let rendered: HashMap<i128, Matrix4<f32>> = handler
+            .render_data_with_ids(0.5)
+            .into_iter()
+            .map(|(id, r)| (id, r.model_mat))
+            .collect();
+        assert_eq!(rendered.len(), ids.len());
+        for id in ids {
+            assert_eq!(Some(rendered[&id]), handler.model_matrix_of(id, 0.5));
+        }
+    }
+
+    #[test]
+    fn model_matrices_of_families_match_their_render_data() {
+        let sized = |x: f32, size: f32| {
+            vec![
+                StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                },
+                StandardSpawnData::PosX(Pos::from(x)),
+                StandardSpawnData::MotionY(1.0),
+                StandardSpawnData::SizeX(size),
+                StandardSpawnData::SizeY(size),
+                StandardSpawnData::SizeZ(size),
+                StandardSpawnData::Orientation(nalgebra::UnitQuaternion::from_euler_angles(
+                    0.1, 0.2, x,
+                )),
+            ]
+        };
+        let moving = [MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID];
+        let falling = [
+            MOTION3_BEHAVIOR_ID,
+            GRAVITY3_BEHAVIOR_ID,
+            APPEARANCE_BEHAVIOR_ID,
+        ];
+        // Three generations, with the middle one in another handler
+        let mut child = spawn(100, &falling, sized(3.0, 0.5));
+        child.children.push(spawn(100, &moving, sized(1.0, 2.0)));
+        let mut parent = spawn(100, &moving, sized(10.0, 2.0));
+        parent.children.push(child);
+
+        let mut handler = standard_handler();
+        handler.spawn_one(parent).unwrap();
+        handler.tick();
+        // Stale matrices from before the tick aren't used
+        for h in handler.handlers.values_mut().flatten() {
+            h.columns
+                .transform_mats
+                .fill(Matrix4::new_translation(&Vector3::x()));
+        }
+
+        let ids: Vec<i128> = handler
+            .handlers
+            .values()
+            .flatten()
+            .flat_map(|h| h.columns.ids()[0..h.current_size].to_vec())
+            .collect();
+        assert_eq!(ids.len(), 3);
+        let model_mats: Vec<_> = ids
+            .iter()
+            .map(|id| handler.model_matrix_of(*id, 0.5).unwrap())
+            .collect();
+
+        let rendered: HashMap<i128, Matrix4<f32>> = handler
+            .render_data_with_ids(0.5)
+            .into_iter()
+            .map(|(id, r)| (id, r.model_mat))
+            .collect();
+        for (id, model_mat) in ids.iter().zip(model_mats) {
+            assert!((rendered[id] - model_mat).norm() < 1e-4);
+            // Now from the matrices computed for rendering
+            let cached = handler.model_matrix_of(*id, 0.5).unwrap();
+            assert!((cached - model_mat).norm() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn prewarmed_handlers_exist_before_any_spawn() {
+        let mut handler = standard_handler();
+        let motion = vec![MOTION3_BEHAVIOR_ID];
+        let falling = vec![GRAVITY3_BEHAVIOR_ID, MOTION3_BEHAVIOR_ID];
+        handler
+            .prewarm(&[(motion.clone(), 1000), (falling.clone(), 50)])
+            .unwrap();
+
+        let size_of = |handler: &mut TopDanmakuBehaviorsHandler<StandardColumns>,
+                       behaviors: &[&'static str]| {
+            let mut behaviors = behaviors.to_vec();
+            canonicalize_behaviors(&mut behaviors, &handler.implicit_behaviors);
+            find_handler(&mut handler.handlers, &behaviors).map(|h| h.current_max_size())
+        };
+        assert!(size_of(&mut handler, &motion).unwrap() >= 1000);
+        assert!(size_of(&mut handler, &falling).unwrap() >= 50);
+        assert_eq!(handler.live_count(), 0);
+
+        // Kept while empty, and big enough for the first wave
+        handler.cleanup();
+        assert!(size_of(&mut handler, &motion).unwrap() >= 1000);
+        handler.add_danmaku(motion_spawns(1000));
+        assert_eq!(handler.resize_count(), 0);
+
+        assert_eq!(
+            handler.prewarm(&[(vec!["does_not_exist"], 10)]),
+            Err(DanCoreError::UnknownBehavior("does_not_exist".to_string()))
+        );
+    }
+
+    #[test]
+    fn aggressive_shrink_policies_downsize_sooner() {
+        let shrunk = |policy: ResizePolicy| {
+            let mut handler = standard_handler();
+            handler.set_resize_policy(policy);
+            let ids: Vec<i128> = motion_spawns(1000)
+                .into_iter()
+                .map(|d| handler.spawn_one(d).unwrap())
+                .collect();
+            // 120 left, which fills too much of 128 for the default surplus
+            for id in &ids[120..] {
+                handler.remove_danmaku(*id);
+            }
+            handler.cleanup();
+
+            assert_eq!(handler.live_count(), 120);
+            for (i, id) in ids[..120].iter().enumerate() {
+                assert_eq!(handler.get_danmaku(*id).unwrap().position.x, i as f32);
+            }
+            max_size(&handler)
+        };
+
+        assert_eq!(shrunk(ResizePolicy::default()), 256);
+        assert_eq!(
+            shrunk(ResizePolicy {
+                shrink_surplus: 0.0,
+                min_size_exp: 2,
+                ..ResizePolicy::default()
+            }),
+            128
+        );
+    }
+
+    #[test]
+    fn user_data_moves_with_danmaku_when_shrinking() {
+        let mut handler = standard_handler();
+        let ids: Vec<i128> = (0..1000_u64)
+            .map(|i| {
+                handler
+                    .spawn_one(spawn(
+                        100,
+                        &[MOTION3_BEHAVIOR_ID],
+                        vec![StandardSpawnData::UserData(i * 7 + 1)],
+                    ))
+                    .unwrap()
+            })
+            .collect();
+        let size = max_size(&handler);
+
+        // The survivors are spread out, so compacting moves most of them
+        let kept: Vec<usize> = (0..1000).filter(|i| i % 100 == 99).collect();
+        for (i, id) in ids.iter().enumerate() {
+            if !kept.contains(&i) {
+                handler.remove_danmaku(*id);
+            }
+        }
+        handler.cleanup();
+
+        assert!(max_size(&handler) < size);
+        for i in kept {
+            let view = handler.get_danmaku(ids[i]).unwrap();
+            assert_eq!(view.user_data, i as u64 * 7 + 1);
+        }
+    }
+
+    #[test]
+    fn new_handlers_start_at_the_initial_size() {
+        let mut handler = standard_handler();
+        handler.set_initial_size_exp(2);
+        handler.add_danmaku(motion_spawns(1));
+        assert_eq!(max_size(&handler), 4);
+
+        // Reserving more than the initial size still makes room for all of it
+        handler.reserve(&[GRAVITY3_BEHAVIOR_ID], 10).unwrap();
+        let gravity = handler
+            .handlers
+            .values()
+            .flatten()
+            .find(|h| h.behavior_ids.contains(&GRAVITY3_BEHAVIOR_ID))
+            .unwrap();
+        assert_eq!(gravity.current_max_size(), 16);
+
+        // Sizes that could overflow, or allocate more than anyone needs, are clamped
+        handler.set_initial_size_exp(u8::MAX);
+        assert_eq!(handler.initial_size_exp, MAX_INITIAL_SIZE_EXP);
+    }
+
+    #[test]
+    fn reserved_handlers_take_a_burst_without_resizing() {
+        let mut handler = standard_handler();
+        handler.reserve(&[MOTION3_BEHAVIOR_ID], 10_000).unwrap();
+
+        handler.add_danmaku(motion_spawns(10_000));
+
+        assert_eq!(handler.live_count(), 10_000);
+        assert_eq!(handler.resize_count(), 0);
+
+        let mut unreserved = standard_handler();
+        unreserved.add_danmaku(motion_spawns(10_000));
+        assert!(unreserved.resize_count() > 0);
+    }
+
+    #[test]
+    fn cleanup_shrinks_handlers_keeping_live_danmaku() {
+        let mut handler = standard_handler();
+        let ids: Vec<i128> = motion_spawns(1000)
+            .into_iter()
+            .map(|d| handler.spawn_one(d).unwrap())
+            .collect();
+        let grown = max_size(&handler);
+        let grown_memory = handler.memory_report();
+
+        for id in &ids[10..] {
+            handler.remove_danmaku(*id);
+        }
+        handler.cleanup();
+
+        assert!(max_size(&handler) < grown);
+        assert_eq!(handler.live_count(), 10);
+        // Shrinking only compacts the columns the handler uses, and doesn't allocate the others
+        let memory = handler.memory_report();
+        assert!(memory.total() < grown_memory.total());
+        assert!(memory.columns["motion_x"] < grown_memory.columns["motion_x"]);
+        for column in ["gravity_x", "trail", "orientation", "main_color", "form"] {
+            assert!(!memory.columns.contains_key(column), "{}", column);
+        }
+        for (i, id) in ids[..10].iter().enumerate() {
+            assert_eq!(handler.get_danmaku(*id).unwrap().position.x, i as f32);
+        }
+    }
+
+    #[test]
+    fn compact_all_keeps_survivors_and_their_parents() {
+        let mut handler = standard_handler();
+        let ids: Vec<i128> = motion_spawns(400)
+            .into_iter()
+            .map(|d| handler.spawn_one(d).unwrap())
+            .collect();
+        let mut child = spawn(100, &[APPEARANCE_BEHAVIOR_ID], vec![]);
+        child.parent = Some(ids[0]);
+        let child = handler.spawn_one(child).unwrap();
+        let grown = max_size(&handler);
+
+        for id in ids.iter().skip(1).step_by(2) {
+            handler.remove_danmaku(*id);
+        }
+        handler.compact_all();
+
+        assert!(max_size(&handler) < grown);
+        assert_eq!(handler.live_count(), 201);
+        for (i, id) in ids.iter().enumerate().step_by(2) {
+            assert_eq!(handler.get_danmaku(*id).unwrap().position.x, i as f32);
+        }
+        assert_eq!(handler.global_parent_map.get(&child), Some(&ids[0]));
+        assert!(handler.model_matrix_of(child, 1.0).is_some());
+
+        // Neither motion3 nor appearance use these, so compacting leaves them unallocated
+        let memory = handler.memory_report();
+        for column in [
+            "gravity_x",
+            "trail",
+            "orientation",
+            "speed_accel",
+            "held_motion_x",
+        ] {
+            assert!(!memory.columns.contains_key(column), "{}", column);
+        }
+    }
+
+    #[test]
+    fn deep_family_chains_are_truncated_at_the_max_depth() {
+        let mut handler = standard_handler();
+        handler.set_max_family_depth(100);
+
+        let mut chain = spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]);
+        for _ in 0..1000 {
+            let mut parent = spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]);
+            parent.children.push(chain);
+            chain = parent;
+        }
+
+        let root = handler.spawn_one(chain).unwrap();
+
+        // Depths 0 through 100 are kept, and everything deeper is dropped
+        assert_eq!(handler.live_count(), 101);
+        assert_eq!(handler.global_family_depth_map[&root], 0);
+        assert_eq!(handler.global_family_depth_map.values().max(), Some(&100));
+    }
+
+    #[test]
+    fn family_depths_past_the_max_are_an_error() {
+        let mut handler = standard_handler();
+        handler.set_max_family_depth(3);
+
+        assert_eq!(handler.check_family_depth(3), Ok(()));
+        assert_eq!(
+            handler.check_family_depth(4),
+            Err(DanCoreError::DepthLimitExceeded)
+        );
+    }
+
+    #[test]
+    fn on_death_fires_once_for_an_expired_danmaku() {
+        let mut handler = standard_handler();
+        let deaths = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let recorded = deaths.clone();
+        handler.set_on_death(move |id, view| recorded.borrow_mut().push((id, view.id)));
+
+        let id = handler
+            .spawn_one(spawn(2, &[MOTION3_BEHAVIOR_ID], vec![]))
+            .unwrap();
+        handler
+            .spawn_one(spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]))
+            .unwrap();
+
+        for _ in 0..5 {
+            handler.tick();
+        }
+
+        assert_eq!(*deaths.borrow(), vec![(id, id)]);
+    }
+
+    #[test]
+    fn spawning_into_a_freed_slot_doesnt_report_the_new_danmaku_dead() {
+        let mut handler = standard_handler();
+        let deaths = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let recorded = deaths.clone();
+        handler.set_on_death(move |id, _| recorded.borrow_mut().push(id));
+
+        let behaviors = [MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID];
+        let appearance = || {
+            vec![StandardSpawnData::Appearance {
+                form: &Form::SPHERE,
+            }]
+        };
+        let mut first = spawn(1, &behaviors, appearance());
+        first.next_stage.push(spawn(100, &behaviors, appearance()));
+        let first_id = handler.spawn_one(first).unwrap();
+        let (_, first_idx) = handler.locate(first_id).unwrap();
+
+        // The next stage takes over the slot in the same tick it was freed
+        handler.tick();
+        handler.tick();
+        assert_eq!(*deaths.borrow(), vec![first_id]);
+        let (next_id, _) = handler.render_data_with_ids(1.0)[0];
+        assert_eq!(handler.locate(next_id).unwrap().1, first_idx);
+
+        // Its slot was expired last tick, but that was the old occupant
+        for _ in 0..5 {
+            handler.tick();
+        }
+        assert_eq!(*deaths.borrow(), vec![first_id]);
+        assert!(handler.get_danmaku(next_id).is_some());
+    }
+
+    #[test]
+    fn immortal_danmaku_outlive_expiring_ones() {
+        let mut handler = standard_handler();
+        let immortal = handler
+            .spawn_one(spawn(IMMORTAL_END_TIME, &[MOTION3_BEHAVIOR_ID], vec![]))
+            .unwrap();
+        let mortal = handler
+            .spawn_one(spawn(10, &[MOTION3_BEHAVIOR_ID], vec![]))
+            .unwrap();
+
+        for _ in 0..1000 {
+            handler.tick();
+        }
+
+        assert!(handler.get_danmaku(mortal).is_none());
+        let view = handler.get_danmaku(immortal).unwrap();
+        assert_eq!(view.ticks_existed, 1000);
+        assert_eq!(handler.live_count(), 1);
+    }
+
+    #[test]
+    fn static_markers_render_until_they_expire() {
+        let mut handler = standard_handler();
+        let marker = handler
+            .spawn_one(spawn(
+                3,
+                &[APPEARANCE_BEHAVIOR_ID],
+                vec![StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                }],
+            ))
+            .unwrap();
+        // Without appearance nothing is rendered, but it still ages
+        let hidden = handler.spawn_one(spawn(3, &[], vec![])).unwrap();
+
+        for tick in 1..=3 {
+            handler.tick();
+            assert_eq!(handler.get_danmaku(marker).unwrap().ticks_existed, tick);
+            assert_eq!(handler.get_danmaku(hidden).unwrap().ticks_existed, tick);
+
+            let rendered: Vec<i128> = handler
+                .render_data_with_ids(1.0)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect();
+            assert_eq!(rendered, vec![marker]);
+        }
+
+        handler.tick();
+        assert!(handler.get_danmaku(marker).is_none());
+        assert!(handler.get_danmaku(hidden).is_none());
+        assert!(handler.render_data(1.0).is_empty());
+        assert_eq!(handler.live_count(), 0);
+    }
+
+    #[test]
+    fn render_data_past_the_first_chunk_has_its_own_age() {
+        let mut handler = standard_handler();
+        let appearing = |end_time| {
+            spawn(
+                end_time,
+                &[MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID],
+                vec![StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                }],
+            )
+        };
+        let early: Vec<_> = (0..N + 2)
+            .map(|i| handler.spawn_one(appearing(100 + i as i16)).unwrap())
+            .collect();
+        for _ in 0..3 {
+            handler.tick();
+        }
+        let late: Vec<_> = (0..N + 2)
+            .map(|i| handler.spawn_one(appearing(200 + i as i16)).unwrap())
+            .collect();
+        handler.tick();
+        handler.tick();
+
+        let indices: HashMap<_, _> = early
+            .iter()
+            .chain(&late)
+            .map(|id| (*id, handler.locate(*id).unwrap().1))
+            .collect();
+        let rendered = handler.render_data_with_ids(1.0);
+        assert_eq!(rendered.len(), 2 * N + 4);
+        assert!(indices.values().any(|idx| *idx >= N));
+        for (id, data) in &rendered {
+            let (ticks_existed, end_time) = match early.iter().position(|e| e == id) {
+                Some(i) => (5, 100 + i as i16),
+                None => (2, 200 + late.iter().position(|l| l == id).unwrap() as i16),
+            };
+            assert_eq!(data.ticks_existed, ticks_existed, "index {}", indices[id]);
+            assert_eq!(data.end_time, end_time, "index {}", indices[id]);
+        }
+    }
+
+    #[test]
+    fn non_finite_danmaku_are_skipped_without_poisoning_their_children() {
+        let behaviors = [MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID];
+        let sized = |x: f32| {
+            vec![
+                StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                },
+                StandardSpawnData::PosX(Pos::from(x)),
+                StandardSpawnData::SizeX(1.0),
+                StandardSpawnData::SizeY(1.0),
+                StandardSpawnData::SizeZ(1.0),
+            ]
+        };
+        let with_child = |x: f32| {
+            let mut parent = spawn(100, &behaviors, sized(x));
+            parent.children.push(spawn(100, &behaviors, sized(2.0)));
+            parent
+        };
+        let mut handler = standard_handler();
+        handler.spawn_one(with_child(f32::NAN)).unwrap();
+        handler.spawn_one(with_child(10.0)).unwrap();
+        handler.tick();
+
+        // The NaN is kept out of the transform of the child, which drops out with its parent like
+        // the children of any parent that isn't rendered
+        let mut positions: Vec<f32> = handler
+            .render_data(1.0)
+            .iter()
+            .inspect(|r| assert!(r.model_mat.iter().all(|v| v.is_finite())))
+            .map(|r| r.model_mat.column(3).x)
+            .collect();
+        positions.sort_by(f32::total_cmp);
+        assert_eq!(positions, vec![10.0, 12.0]);
+        assert_eq!(handler.live_count(), 4);
+    }
+
+    #[test]
+    fn collect_damage_sums_and_kills_overlapping_danmaku() {
+        let mut handler = standard_handler();
+        let bullet = |x: f32, damage: f32| {
+            spawn(
+                100,
+                &[MOTION3_BEHAVIOR_ID, DAMAGE_BEHAVIOR_ID],
+                vec![
+                    StandardSpawnData::PosX(Pos::from(x)),
+                    StandardSpawnData::Damage(damage),
+                ],
+            )
+        };
+        let near = handler.spawn_one(bullet(0.5, 2.0)).unwrap();
+        let touching = handler.spawn_one(bullet(-1.0, 3.0)).unwrap();
+        let far = handler.spawn_one(bullet(10.0, 5.0)).unwrap();
+        // Overlaps, but has no damage column to sum
+        let harmless = handler
+            .spawn_one(spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]))
+            .unwrap();
+
+        let damage = handler.collect_damage((Vector3::zeros(), 0.5), 0.0);
+
+        assert_eq!(damage, 5.0);
+        assert!(handler.get_danmaku(near).is_none());
+        assert!(handler.get_danmaku(touching).is_none());
+        assert!(handler.get_danmaku(far).is_some());
+        assert!(handler.get_danmaku(harmless).is_some());
+    }
+
+    #[test]
+    fn danmaku_keep_their_own_forms() {
+        let mut handler = standard_handler();
+        let bullet = |x: f32, form: &'static Form| {
+            spawn(
+                100,
+                &[
+                    MOTION3_BEHAVIOR_ID,
+                    APPEARANCE_BEHAVIOR_ID,
+                    DAMAGE_BEHAVIOR_ID,
+                ],
+                vec![
+                    StandardSpawnData::PosX(Pos::from(x)),
+                    StandardSpawnData::Appearance { form },
+                    StandardSpawnData::SizeX(1.0),
+                    StandardSpawnData::SizeY(1.0),
+                    StandardSpawnData::SizeZ(1.0),
+                    StandardSpawnData::Damage(1.0),
+                ],
+            )
+        };
+        let sphere = handler.spawn_one(bullet(1.5, &Form::SPHERE)).unwrap();
+        let cube = handler.spawn_one(bullet(-1.5, &Form::BOX)).unwrap();
+
+        let forms: HashMap<i128, &'static str> = handler
+            .render_data_with_ids(1.0)
+            .into_iter()
+            .map(|(id, data)| (id, data.form.id()))
+            .collect();
+        assert_eq!(forms[&sphere], "sphere");
+        assert_eq!(forms[&cube], "box");
+
+        // Only the corners of the box reach the hitbox
+        assert_eq!(handler.collect_damage((Vector3::zeros(), 0.1), 1.0), 1.0);
+        assert!(handler.get_danmaku(sphere).is_some());
+        assert!(handler.get_danmaku(cube).is_none());
+    }
+
+    fn rendered(
+        handler: &mut TopDanmakuBehaviorsHandler<StandardColumns>,
+    ) -> Vec<(i128, Matrix4<f32>, ColorHex)> {
+        let mut rendered: Vec<_> = handler
+            .render_data_with_ids(0.5)
+            .into_iter()
+            .map(|(id, data)| (id, data.model_mat, data.main_color))
+            .collect();
+        rendered.sort_by_key(|(id, _, _)| *id);
+        rendered
+    }
+
+    #[test]
+    fn restoring_a_snapshot_replays_the_same_ticks() {
+        let mut handler = standard_handler();
+        handler.add_danmaku(
+            (0..20)
+                .map(|i| {
+                    spawn(
+                        10 + i * 3,
+                        &[
+                            MOTION3_BEHAVIOR_ID,
+                            GRAVITY3_BEHAVIOR_ID,
+                            APPEARANCE_BEHAVIOR_ID,
+                        ],
+                        vec![
+                            StandardSpawnData::Appearance {
+                                form: &Form::SPHERE,
+                            },
+                            StandardSpawnData::MotionX(i as f32 * 0.1),
+                            StandardSpawnData::GravityY(-0.05),
+                            StandardSpawnData::MainColor(0x10 * i as i32),
+                        ],
+                    )
+                })
+                .collect(),
+        );
+        for _ in 0..5 {
+            handler.tick();
+        }
+
+        let snapshot = handler.snapshot();
+        for _ in 0..50 {
+            handler.tick();
+        }
+        let first_run = rendered(&mut handler);
+
+        handler.restore(&snapshot);
+        for _ in 0..50 {
+            handler.tick();
+        }
+
+        assert!(!first_run.is_empty());
+        assert_eq!(rendered(&mut handler), first_run);
+        assert_eq!(handler.tick_count(), 55);
+    }
+
+    #[test]
+    fn rewinding_restores_an_earlier_frame() {
+        let mut handler = standard_handler();
+        handler.add_danmaku(
+            (0..10)
+                .map(|i| {
+                    spawn(
+                        5 + i,
+                        &[MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID],
+                        vec![
+                            StandardSpawnData::Appearance {
+                                form: &Form::SPHERE,
+                            },
+                            StandardSpawnData::MotionX(i as f32),
+                        ],
+                    )
+                })
+                .collect(),
+        );
+        let mut history = DanmakuHistory::new(5);
+        let mut frames = HashMap::new();
+        for _ in 0..10 {
+            handler.tick();
+            history.record(&handler);
+            frames.insert(handler.tick_count(), rendered(&mut handler));
+        }
+
+        assert_eq!(history.rewind(&mut handler, 3), 3);
+        assert_eq!(handler.tick_count(), 7);
+        // Some of these died since, and come back with the rewind
+        assert!(frames[&7].len() > frames[&10].len());
+        assert_eq!(rendered(&mut handler), frames[&7]);
+
+        // Only ticks 6 to 10 were kept, so going further back stops at the oldest one
+        assert_eq!(history.rewind(&mut handler, 100), 1);
+        assert_eq!(handler.tick_count(), 6);
+        assert_eq!(rendered(&mut handler), frames[&6]);
+    }
+
+    #[test]
+    fn partial_ticks_outside_zero_to_one_are_clamped() {
+        let mut handler = standard_handler();
+        handler
+            .spawn_one(spawn(
+                100,
+                &[MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID],
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::MotionX(2.0),
+                    StandardSpawnData::SizeX(1.0),
+                    StandardSpawnData::SizeY(1.0),
+                    StandardSpawnData::SizeZ(1.0),
+                ],
+            ))
+            .unwrap();
+        handler.tick();
+
+        let model_mat = |handler: &mut TopDanmakuBehaviorsHandler<StandardColumns>, partial| {
+            handler.render_data(partial)[0].model_mat
+        };
+        let previous = model_mat(&mut handler, 0.0);
+        let current = model_mat(&mut handler, 1.0);
+        assert_eq!(current.column(3).x, 2.0);
+
+        assert_eq!(model_mat(&mut handler, 1.5), current);
+        assert_eq!(model_mat(&mut handler, -1.0), previous);
+        // A NaN is taken as the current tick, instead of ending up in the matrix
+        assert_eq!(model_mat(&mut handler, f32::NAN), current);
+        let transforms = handler.world_transforms(f32::NAN);
+        assert_eq!(transforms.values().collect::<Vec<_>>(), vec![&current]);
+    }
+
+    #[test]
+    fn ordered_render_data_is_the_same_every_run() {
+        let run = |order: RenderOrder| {
+            let mut handler = standard_handler();
+            handler.set_render_order(order);
+            for i in 0..40 {
+                let behaviors: &[&'static str] = if i % 3 == 0 {
+                    &[MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID]
+                } else {
+                    &[
+                        MOTION3_BEHAVIOR_ID,
+                        GRAVITY3_BEHAVIOR_ID,
+                        APPEARANCE_BEHAVIOR_ID,
+                    ]
+                };
+                let mut danmaku = spawn(
+                    100,
+                    behaviors,
+                    vec![
+                        StandardSpawnData::Appearance {
+                            form: &Form::SPHERE,
+                        },
+                        StandardSpawnData::PosX(Pos::from((i * 7 % 40) as f32)),
+                        StandardSpawnData::SizeX(1.0),
+                        StandardSpawnData::SizeY(1.0),
+                        StandardSpawnData::SizeZ(1.0),
+                    ],
+                );
+                // Children go through the parent map when rendering
+                if i % 5 == 0 {
+                    danmaku.children.push(spawn(
+                        100,
+                        &[MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID],
+                        vec![StandardSpawnData::Appearance {
+                            form: &Form::SPHERE,
+                        }],
+                    ));
+                }
+                handler.spawn_one(danmaku).unwrap();
+            }
+            handler.tick();
+
+            handler
+                .render_data_with_ids(0.5)
+                .into_iter()
+                .map(|(id, r)| (id, r.model_mat))
+                .collect::<Vec<_>>()
+        };
+
+        let by_id = run(RenderOrder::ById);
+        assert_eq!(by_id.len(), 48);
+        assert!(by_id.windows(2).all(|w| w[0].0 < w[1].0));
+        assert_eq!(run(RenderOrder::ById), by_id);
+
+        let eye = Vector3::new(15.0, 0.0, 0.0);
+        let back_to_front = run(RenderOrder::BackToFront(eye));
+        let distances: Vec<f32> = back_to_front
+            .iter()
+            .map(|(_, m)| (m.column(3).xyz() - eye).norm())
+            .collect();
+        assert!(distances.windows(2).all(|w| w[0] >= w[1]));
+        assert_eq!(run(RenderOrder::BackToFront(eye)), back_to_front);
+    }
+
+    #[test]
+    fn tick_count_advances_once_per_tick_unless_paused() {
+        let mut handler = standard_handler();
+        let id = handler
+            .spawn_one(spawn(
+                100,
+                &[MOTION3_BEHAVIOR_ID],
+                vec![StandardSpawnData::MotionX(1.0)],
+            ))
+            .unwrap();
+
+        for tick in 1..=3 {
+            handler.tick();
+            assert_eq!(handler.tick_count(), tick);
+        }
+
+        handler.set_paused(true);
+        for _ in 0..5 {
+            handler.tick();
+        }
+        assert_eq!(handler.tick_count(), 3);
+        let danmaku = handler.get_danmaku(id).unwrap();
+        assert_eq!(danmaku.ticks_existed, 3);
+        assert_eq!(danmaku.position.x, 3.0);
+
+        handler.set_paused(false);
+        handler.tick();
+        assert_eq!(handler.tick_count(), 4);
+        assert_eq!(handler.context().tick_count, 3);
+        assert_eq!(handler.get_danmaku(id).unwrap().position.x, 4.0);
+    }
+
+    #[test]
+    fn disabled_gravity_stops_pulling_while_motion_continues() {
+        let mut handler = standard_handler();
+        let id = handler
+            .spawn_one(spawn(
+                100,
+                &[MOTION3_BEHAVIOR_ID, GRAVITY3_BEHAVIOR_ID],
+                vec![
+                    StandardSpawnData::MotionX(1.0),
+                    StandardSpawnData::GravityY(-0.1),
+                ],
+            ))
+            .unwrap();
+        for _ in 0..3 {
+            handler.tick();
+        }
+        let before = handler.get_danmaku(id).unwrap();
+        assert!(before.motion.y < 0.0);
+
+        handler.set_behavior_enabled(GRAVITY3_BEHAVIOR_ID, false);
+        for _ in 0..3 {
+            handler.tick();
+        }
+        let disabled = handler.get_danmaku(id).unwrap();
+        assert_eq!(disabled.motion, before.motion);
+        assert!((disabled.position - (before.position + before.motion * 3.0)).norm() < 1e-5);
+
+        handler.set_behavior_enabled(GRAVITY3_BEHAVIOR_ID, true);
+        handler.tick();
+        assert!(handler.get_danmaku(id).unwrap().motion.y < disabled.motion.y);
+    }
+
+    #[test]
+    fn filtered_rendering_leaves_out_other_behavior_sets() {
+        let mut handler = standard_handler();
+        let visible = |behaviors: &[&'static str], x: f32| {
+            let mut all_behaviors = behaviors.to_vec();
+            all_behaviors.push(APPEARANCE_BEHAVIOR_ID);
+            spawn(
+                100,
+                &all_behaviors,
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::SizeX(1.0),
+                    StandardSpawnData::SizeY(1.0),
+                    StandardSpawnData::SizeZ(1.0),
+                    StandardSpawnData::PosX(Pos::from(x)),
+                ],
+            )
+        };
+        handler.spawn_one(visible(&[MOTION3_BEHAVIOR_ID], 1.0));
+        // The child only has motion3, but is placed relative to a parent which isn't rendered
+        let mut falling = visible(&[MOTION3_BEHAVIOR_ID, GRAVITY3_BEHAVIOR_ID], 10.0);
+        falling.children.push(visible(&[MOTION3_BEHAVIOR_ID], 2.0));
+        handler.spawn_one(falling);
+
+        let motion_only = |behaviors: &[&'static str]| !behaviors.contains(&GRAVITY3_BEHAVIOR_ID);
+        let mut positions: Vec<f32> = handler
+            .render_data_filtered(1.0, motion_only)
+            .iter()
+            .map(|r| r.model_mat.column(3).x)
+            .collect();
+        positions.sort_by(f32::total_cmp);
+        assert_eq!(positions, vec![1.0, 12.0]);
+
+        assert_eq!(handler.render_data(1.0).len(), 3);
+        assert!(handler.render_data_filtered(1.0, |_| false).is_empty());
+    }
+
+    #[test]
+    fn filtered_rendering_only_computes_the_transforms_of_parents() {
+        let mut handler = standard_handler();
+        let moving = |behaviors: &[&'static str]| {
+            spawn(
+                100,
+                behaviors,
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::MotionX(1.0),
+                ],
+            )
+        };
+        let child_behaviors = [MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID];
+        let parent_behaviors = [MOTION3_BEHAVIOR_ID, GRAVITY3_BEHAVIOR_ID];
+        let other_behaviors = [MOTION3_BEHAVIOR_ID, APPEARANCE2_BEHAVIOR_ID];
+
+        let mut parent = moving(&parent_behaviors);
+        parent.children.push(moving(&child_behaviors));
+        handler.spawn_one(parent);
+        handler.spawn_one(moving(&other_behaviors));
+        handler.tick();
+
+        // Transforms are only written to when they are computed
+        let poisoned = Matrix4::zeros();
+        fn first_transform<'a>(
+            handler: &'a mut TopDanmakuBehaviorsHandler<StandardColumns>,
+            behaviors: &[&'static str],
+        ) -> &'a mut Matrix4<f32> {
+            let h = handler
+                .handlers
+                .values_mut()
+                .flatten()
+                .find(|h| behaviors.iter().all(|b| h.behavior_ids.contains(b)))
+                .unwrap();
+            &mut h.columns.transform_mats[0]
+        }
+        *first_transform(&mut handler, &parent_behaviors) = poisoned;
+        *first_transform(&mut handler, &other_behaviors) = poisoned;
+
+        let rendered = handler
+            .render_data_filtered(1.0, |behaviors| behaviors.contains(&APPEARANCE_BEHAVIOR_ID));
+        assert_eq!(rendered.len(), 1);
+        assert_eq!(rendered[0].model_mat.column(3).x, 2.0);
+
+        assert_ne!(*first_transform(&mut handler, &parent_behaviors), poisoned);
+        assert_eq!(*first_transform(&mut handler, &other_behaviors), poisoned);
+    }
+
+    #[test]
+    fn family_maps_stay_bounded_over_a_long_session() {
+        let mut handler = standard_handler();
+        let wave = || {
+            let mut parent = spawn(5, &[MOTION3_BEHAVIOR_ID], vec![]);
+            parent
+                .children
+                .extend((0..2).map(|_| spawn(5, &[MOTION3_BEHAVIOR_ID], vec![])));
+            parent
+        };
+
+        let mut largest = 0;
+        for tick in 0..1000 {
+            handler.spawn_one(wave());
+            handler.tick();
+            if tick % 10 == 0 {
+                handler.cleanup();
+            }
+            largest = largest.max(handler.global_family_depth_map.len());
+        }
+
+        // About 6 ticks of live waves, and up to 10 ticks of dead ones between cleanups
+        assert!(largest <= 3 * 17, "{}", largest);
+        handler.cleanup();
+        assert_eq!(handler.global_family_depth_map.len(), handler.live_count());
+        assert_eq!(
+            handler.global_parent_map.len(),
+            handler.live_count() * 2 / 3
+        );
+
+        for _ in 0..10 {
+            handler.tick();
+        }
+        handler.cleanup();
+        assert!(handler.global_family_depth_map.is_empty());
+        assert!(handler.global_parent_map.is_empty());
+    }
+
+    #[test]
+    fn removing_a_parent_can_take_its_descendants_along() {
+        let family = || {
+            let mut child = spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]);
+            child
+                .children
+                .push(spawn(100, &[GRAVITY3_BEHAVIOR_ID], vec![]));
+            let mut parent = spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]);
+            parent.children.push(child);
+            parent
+                .children
+                .push(spawn(100, &[GRAVITY3_BEHAVIOR_ID], vec![]));
+            parent
+        };
+
+        let mut handler = standard_handler();
+        let parent = handler.spawn_one(family()).unwrap();
+        let bystander = handler
+            .spawn_one(spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]))
+            .unwrap();
+        assert_eq!(handler.live_count(), 5);
+
+        // By default the children outlive their parent
+        assert!(handler.remove_danmaku(parent));
+        assert_eq!(handler.live_count(), 4);
+
+        handler.set_cascade_death(true);
+        let parent = handler.spawn_one(family()).unwrap();
+        assert_eq!(handler.live_count(), 8);
+        assert!(handler.remove_danmaku(parent));
+        assert_eq!(handler.live_count(), 4);
+        assert!(handler.get_danmaku(bystander).is_some());
+    }
+
+    #[test]
+    fn cascading_removal_reaches_every_descendant_of_a_large_family() {
+        let mut handler = standard_handler();
+        handler.set_cascade_death(true);
+        let family = || {
+            let mut root = spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]);
+            for _ in 0..2000 {
+                let mut child = spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]);
+                child
+                    .children
+                    .push(spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]));
+                root.children.push(child);
+            }
+            root
+        };
+        let root = handler.spawn_one(family()).unwrap();
+        let other = handler.spawn_one(family()).unwrap();
+        assert_eq!(handler.live_count(), 2 * 4001);
+
+        assert!(handler.remove_danmaku(root));
+        assert_eq!(handler.live_count(), 4001);
+        assert!(handler.get_danmaku(other).is_some());
+    }
+
+    #[test]
+    fn render_instances_match_render_data() {
+        let mut handler = standard_handler();
+        handler.add_danmaku(
+            (0..8)
+                .map(|i| {
+                    let form = if i < 5 { &Form::SPHERE } else { &Form::BOX };
+                    spawn(
+                        100,
+                        &[MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID],
+                        vec![
+                            StandardSpawnData::Appearance { form },
+                            StandardSpawnData::PosX(Pos::from(i as f32)),
+                            StandardSpawnData::MotionY(1.0),
+                            StandardSpawnData::MainColor(0xFF0000 + i),
+                        ],
+                    )
+                })
+                .collect(),
+        );
+        handler.tick();
+
+        let instances = handler.render_instances(0.5);
+        assert_eq!(instances.model_mats.len(), 8);
+        assert_eq!(instances.colors.len(), 8);
+        assert_eq!(instances.forms.len(), 8);
+        let group_sizes: Vec<_> = instances
+            .groups()
+            .into_iter()
+            .map(|(form, range)| (form, range.len()))
+            .collect();
+        assert_eq!(
+            group_sizes,
+            vec![(Form::BOX.id(), 3), (Form::SPHERE.id(), 5)]
+        );
+
+        let render_data: Vec<_> = handler
+            .render_data(0.5)
+            .into_iter()
+            .map(|r| (r.form.id(), r.model_mat, r.main_color.to_rgba()))
+            .collect();
+        for i in 0..8 {
+            let model_mat = Matrix4::from_column_slice(&instances.model_mats[i]);
+            assert!(render_data.contains(&(instances.forms[i], model_mat, instances.colors[i])));
+        }
+    }
+
+    #[test]
+    fn both_color_streams_are_interpolated_independently() {
+        use StandardDataColumns as C;
+
+        let mut handler = standard_handler();
+        handler.register_behavior(Behavior {
+            act: Box::new(|columns: &mut StandardColumns, size| {
+                for i in 0..size.div_ceil(N) {
+                    columns.old_main_color[i] = columns.main_color[i];
+                    columns.old_secondary_color[i] = columns.secondary_color[i];
+                    columns.main_color[i] = std::simd::Simd::splat(0x00FF00);
+                    columns.secondary_color[i] = std::simd::Simd::splat(0x000000);
+                }
+            }),
+            ..probe_behavior(C::MainColor | C::SecondaryColor)
+        });
+        handler
+            .spawn_one(spawn(
+                100,
+                &["probe", APPEARANCE_BEHAVIOR_ID],
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::MainColor(0xFF0000),
+                    StandardSpawnData::SecondaryColor(0xFFFFFF),
+                ],
+            ))
+            .unwrap();
+        handler.context_mut().color_lerp_mode = ColorLerpMode::Rgb;
+        handler.tick();
+
+        let lerped = |from: i32, to: i32| {
+            ColorHex(from)
+                .lerp(ColorHex(to), 0.5, ColorLerpMode::Rgb)
+                .to_rgba()
+        };
+        let instances = handler.render_instances(0.5);
+        assert_eq!(instances.colors, vec![lerped(0xFF0000, 0x00FF00)]);
+        assert_eq!(instances.secondary_colors, vec![lerped(0xFFFFFF, 0x000000)]);
+        assert_ne!(instances.colors, instances.secondary_colors);
+    }
+
+    #[test]
+    fn translating_a_handler_shifts_its_danmaku_without_streaks() {
+        let moving = [MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID];
+        let still = [APPEARANCE_BEHAVIOR_ID];
+        let appearing = |behaviors: &[&'static str], i: usize| {
+            spawn(
+                100,
+                behaviors,
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::PosX(Pos::from(i as f32)),
+                    StandardSpawnData::MotionY(2.0),
+                ],
+            )
+        };
+        let mut handler = standard_handler();
+        handler.add_danmaku((1..N + 4).map(|i| appearing(&moving, i)).collect());
+        // Without motion3 there are no position columns, so this one stays at the origin
+        handler.spawn_one(appearing(&still, 0)).unwrap();
+        handler.tick();
+
+        let positions = |handler: &mut TopDanmakuBehaviorsHandler<StandardColumns>, partial| {
+            let mut positions: Vec<Vector3<f32>> = handler
+                .render_data(partial)
+                .iter()
+                .map(|r| r.model_mat.column(3).xyz())
+                .collect();
+            positions.sort_by(|a, b| a.x.total_cmp(&b.x));
+            positions
+        };
+        let before = [positions(&mut handler, 0.0), positions(&mut handler, 1.0)];
+
+        assert!(handler.translate_handler(&moving, Vector3::new(1.0, 0.0, 0.0)));
+        assert!(!handler.translate_handler(&[GRAVITY3_BEHAVIOR_ID], Vector3::x()));
+
+        // The previous tick is moved too, so interpolating doesn't streak back to where it was
+        let after = [positions(&mut handler, 0.0), positions(&mut handler, 1.0)];
+        for (before, after) in before.iter().zip(&after) {
+            let (still_before, moving_before) = before.split_first().unwrap();
+            let (still_after, moving_after) = after.split_first().unwrap();
+            assert_eq!(still_after, still_before);
+            for (b, a) in moving_before.iter().zip(moving_after) {
+                assert_eq!(a - b, Vector3::new(1.0, 0.0, 0.0));
+            }
+        }
+    }
+
+    #[test]
+    fn scene_bounds_span_the_outermost_danmaku() {
+        let mut handler = standard_handler();
+        assert_eq!(handler.scene_bounds(1.0), None);
+
+        let corners: [Vector3<f32>; 4] = [
+            Vector3::new(-3.0, 2.0, 1.0),
+            Vector3::new(4.0, -5.0, 0.5),
+            Vector3::new(0.0, 1.0, -6.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        ];
+        handler.add_danmaku(
+            corners
+                .iter()
+                .map(|c| {
+                    spawn(
+                        100,
+                        &[MOTION3_BEHAVIOR_ID],
+                        vec![
+                            StandardSpawnData::PosX(Pos::from(c.x)),
+                            StandardSpawnData::PosY(Pos::from(c.y)),
+                            StandardSpawnData::PosZ(Pos::from(c.z)),
+                        ],
+                    )
+                })
+                .collect(),
+        );
+
+        assert_eq!(
+            handler.scene_bounds(1.0),
+            Some((Vector3::new(-3.0, -5.0, -6.0), Vector3::new(4.0, 2.0, 1.0)))
+        );
+
+        for _ in 0..101 {
+            handler.tick();
+        }
+        assert_eq!(handler.scene_bounds(1.0), None);
+    }
+
+    #[test]
+    fn transform_mats_are_computed_once_per_frame() {
+        let mut handler = standard_handler();
+        handler.add_danmaku(vec![spawn(
+            100,
+            &[
+                MOTION3_BEHAVIOR_ID,
+                APPEARANCE_BEHAVIOR_ID,
+                DAMAGE_BEHAVIOR_ID,
+            ],
+            vec![
+                StandardSpawnData::Appearance { form: &Form::POINT },
+                StandardSpawnData::Damage(1.0),
+            ],
+        )]);
+        handler.tick();
+        assert_eq!(handler.render_data(0.5).len(), 1);
+
+        // Moving the cached matrix only shows up in queries that reuse it instead of recomputing
+        let moved = Vector3::new(100.0, 0.0, 0.0);
+        for h in handler.handlers.values_mut().flatten() {
+            h.columns.transform_mats[0] = Matrix4::new_translation(&moved);
+        }
+        assert_eq!(handler.scene_bounds(0.5), Some((moved, moved)));
+        assert_eq!(handler.collect_damage((moved, 0.5), 0.5), 1.0);
+    }
+
+    #[test]
+    fn transform_mats_are_computed_again_after_a_tick() {
+        let mut handler = standard_handler();
+        handler.add_danmaku(motion_spawns(1));
+        assert!(handler.scene_bounds(0.5).is_some());
+
+        // Another partial ticks value, or a tick, means the cached matrices are stale
+        let moved = Vector3::new(100.0, 0.0, 0.0);
+        for h in handler.handlers.values_mut().flatten() {
+            h.columns.transform_mats[0] = Matrix4::new_translation(&moved);
+        }
+        assert_eq!(
+            handler.scene_bounds(1.0),
+            Some((Vector3::zeros(), Vector3::zeros()))
+        );
+
+        for h in handler.handlers.values_mut().flatten() {
+            h.columns.transform_mats[0] = Matrix4::new_translation(&moved);
+        }
+        handler.tick();
+        assert_eq!(
+            handler.scene_bounds(1.0),
+            Some((Vector3::zeros(), Vector3::zeros()))
+        );
+    }
+
+    #[test]
+    fn each_dead_index_is_recorded_once() {
+        let mut handler = standard_handler();
+        let ids: Vec<_> = (0..20)
+            .map(|i| {
+                let end_time = if i % 3 == 0 { 2 } else { 100 };
+                handler
+                    .spawn_one(spawn(end_time, &[MOTION3_BEHAVIOR_ID], vec![]))
+                    .unwrap()
+            })
+            .collect();
+        // Killed before it would have expired, which mandatory_end must not record again
+        assert!(handler.remove_danmaku(ids[1]));
+        assert!(handler.remove_danmaku(ids[3]));
+
+        for _ in 0..10 {
+            handler.tick();
+        }
+
+        let h = handler.handlers.values().flatten().next().unwrap();
+        let mut current_dead = h.columns.current_dead.clone();
+        current_dead.sort_unstable();
+        let mut expected: Vec<_> = (0..20).filter(|i| i % 3 == 0).collect();
+        expected.push(1);
+        expected.sort_unstable();
+        assert_eq!(current_dead, expected);
+    }
+
+    #[test]
+    fn alive_chunks_match_the_dead_array() {
+        let mut handler = standard_handler();
+        let ids: Vec<_> = (0..64)
+            .map(|i| {
+                // Some expire on their own, and the rest except a few are removed
+                let end_time = if i % 7 == 0 { 3 } else { 100 };
+                handler
+                    .spawn_one(spawn(end_time, &[MOTION3_BEHAVIOR_ID], vec![]))
+                    .unwrap()
+            })
+            .collect();
+        for (i, id) in ids.iter().enumerate() {
+            if i % 7 != 0 && i != 5 && i != 40 {
+                handler.remove_danmaku(*id);
+            }
+        }
+
+        let check = |handler: &TopDanmakuBehaviorsHandler<StandardColumns>| {
+            let h = handler.handlers.values().flatten().next().unwrap();
+            let expected: Vec<_> = h
+                .columns
+                .dead
+                .chunks(N)
+                .map(|chunk| chunk.iter().any(|d| !d))
+                .collect();
+            assert_eq!(h.columns.alive_chunks, expected);
+        };
+        check(&handler);
+        for _ in 0..5 {
+            handler.tick();
+            check(&handler);
+        }
+        assert_eq!(handler.live_count(), 2);
+    }
+
+    #[test]
+    fn running_out_of_ids_never_reuses_a_live_id() {
+        let mut handler = standard_handler();
+        let first = handler
+            .spawn_one(spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]))
+            .unwrap();
+        for h in handler.handlers.values_mut().flatten() {
+            h.next_dan_identifier = i64::MAX - 2;
+        }
+
+        let mut ids: Vec<_> = (0..4)
+            .map(|_| {
+                handler
+                    .spawn_one(spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]))
+                    .unwrap()
+            })
+            .collect();
+        ids.push(first);
+
+        let unique: HashSet<_> = ids.iter().collect();
+        assert_eq!(unique.len(), 5);
+        // Danmaku spawned before the handler got a new identifier can still be found
+        for id in &ids {
+            assert_eq!(handler.get_danmaku(*id).unwrap().id, *id);
+        }
+        assert!(handler.remove_danmaku(first));
+        assert_eq!(handler.live_count(), 4);
+    }
+
+    #[test]
+    fn mandatory_end_runs_after_motion_whatever_the_listed_order() {
+        let mut handler = standard_handler();
+        // Sees the age danmaku had while moving
+        let seen = Rc::new(std::cell::RefCell::new(vec![]));
+        let recorded = seen.clone();
+        handler.register_behavior(Behavior {
+            identifier: "age_probe",
+            required_columns: EnumSet::empty(),
+            order: MOTION_ORDER,
+            required_behaviors: &[],
+            act: Box::new(move |columns: &mut StandardColumns, _| {
+                recorded.borrow_mut().push(columns.ticks_existed[0][0])
+            }),
+        });
+
+        handler
+            .spawn_one(spawn(
+                100,
+                &[MANDATORY_END_BEHAVIOR_ID, "age_probe", GRAVITY3_BEHAVIOR_ID],
+                vec![],
+            ))
+            .unwrap();
+        handler.tick();
+        handler.tick();
+
+        assert_eq!(*seen.borrow(), vec![0, 1]);
+        let h = handler.handlers.values().flatten().next().unwrap();
+        let order: Vec<_> = h.behaviors.iter().map(|b| b.identifier).collect();
+        assert_eq!(
+            order,
+            vec![GRAVITY3_BEHAVIOR_ID, "age_probe", MANDATORY_END_BEHAVIOR_ID]
+        );
+    }
+
+    fn probe_behavior(required_columns: EnumSet<StandardDataColumns>) -> Behavior<StandardColumns> {
+        Behavior {
+            identifier: "probe",
+            required_columns,
+            order: MOTION_ORDER,
+            required_behaviors: &[],
+            act: Box::new(|_, _| {}),
+        }
+    }
+
+    #[test]
+    fn behaviors_using_unallocated_columns_are_rejected() {
+        let mut handler = standard_handler();
+        handler.register_behavior(probe_behavior(EnumSet::empty()));
+        handler.spawn_one(spawn(100, &["probe"], vec![])).unwrap();
+
+        handler.register_behavior(probe_behavior(EnumSet::only(StandardDataColumns::MotionX)));
+        assert_eq!(
+            handler.rebuild_handlers(),
+            Err(DanCoreError::ColumnNotAllocated("probe"))
+        );
+        // The handler keeps the behaviors it had, so it stays valid
+        assert_eq!(handler.validate(), Ok(()));
+
+        let h = handler.handlers.values_mut().flatten().next().unwrap();
+        h.behaviors.push(Rc::new(probe_behavior(EnumSet::only(
+            StandardDataColumns::MotionX,
+        ))));
+        assert_eq!(
+            handler.validate(),
+            Err(DanCoreError::ColumnNotAllocated("probe"))
+        );
+    }
+
+    #[test]
+    fn rebuilt_handlers_run_the_re_registered_act() {
+        let runs = std::rc::Rc::new(std::cell::Cell::new((0, 0)));
+        let counting = |second: bool| {
+            let runs = runs.clone();
+            Behavior {
+                act: Box::new(move |_: &mut StandardColumns, _| {
+                    let (old, new) = runs.get();
+                    runs.set(if second {
+                        (old, new + 1)
+                    } else {
+                        (old + 1, new)
+                    });
+                }),
+                ..probe_behavior(EnumSet::empty())
+            }
+        };
+
+        let mut handler = standard_handler();
+        handler.register_behavior(counting(false));
+        let id = handler.spawn_one(spawn(100, &["probe"], vec![])).unwrap();
+        handler.tick();
+
+        // Existing handlers keep the old act until rebuilt
+        handler.register_behavior(counting(true));
+        handler.tick();
+        assert_eq!(runs.get(), (2, 0));
+
+        assert_eq!(handler.rebuild_handlers(), Ok(()));
+        handler.tick();
+        handler.tick();
+        assert_eq!(runs.get(), (2, 2));
+        assert_eq!(handler.get_danmaku(id).unwrap().ticks_existed, 4);
+    }
+
+    #[test]
+    fn memory_report_grows_with_resizes_and_skips_unallocated_columns() {
+        let mut handler = standard_handler();
+        handler.add_danmaku(motion_spawns(1));
+        let before = handler.memory_report();
+        assert!(before.columns["motion_x"] > 0);
+        assert!(!before.columns.contains_key("gravity_x"));
+        assert!(!before.columns.contains_key("trail"));
+
+        let size = max_size(&handler);
+        handler.add_danmaku(motion_spawns(size));
+        let grown = max_size(&handler);
+        assert!(grown > size);
+
+        let after = handler.memory_report();
+        assert!(after.total() > before.total());
+        assert!(after.columns["motion_x"] > before.columns["motion_x"]);
+        assert!(!after.columns.contains_key("gravity_x"));
+
+        // Compacting and shrinking don't allocate them either
+        handler.compact_all();
+        assert!(!handler.memory_report().columns.contains_key("gravity_x"));
+        let ids: Vec<i128> = handler
+            .handlers
+            .values()
+            .flatten()
+            .flat_map(|h| h.columns.ids()[..h.current_size].to_vec())
+            .collect();
+        for id in &ids[1..] {
+            handler.remove_danmaku(*id);
+        }
+        handler.cleanup();
+        assert!(max_size(&handler) < grown);
+        let shrunk = handler.memory_report();
+        assert!(!shrunk.columns.contains_key("gravity_x"));
+        assert!(!shrunk.columns.contains_key("trail"));
+    }
+
+    #[test]
+    fn spawn_one_returns_the_id_of_the_spawned_danmaku() {
+        let mut handler = standard_handler();
+        let mut parent = spawn(
+            50,
+            &[MOTION3_BEHAVIOR_ID],
+            vec![
+                StandardSpawnData::PosX(Pos::from(3.0_f32)),
+                StandardSpawnData::UserData(7),
+            ],
+        );
+        parent
+            .children
+            .push(spawn(20, &[MOTION3_BEHAVIOR_ID], vec![]));
+
+        let id = handler.spawn_one(parent).unwrap();
+
+        // The children are spawned too, but the id is the one of the parent
+        assert_eq!(handler.live_count(), 2);
+        let view = handler.get_danmaku(id).unwrap();
+        assert_eq!(view.id, id);
+        assert_eq!(view.position.x, 3.0);
+        assert_eq!(view.user_data, 7);
+        assert_eq!(view.end_time, 50);
+        assert_eq!(view.ticks_existed, 0);
+    }
+
+    #[test]
+    fn danmaku_without_scale_columns_render_at_unit_scale() {
+        use StandardDataColumns as C;
+
+        let mut handler = standard_handler();
+        handler.register_behavior(probe_behavior(C::Appearance | C::PosX | C::PosY | C::PosZ));
+        handler
+            .spawn_one(spawn(
+                100,
+                &["probe"],
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::PosX(Pos::from(4.0_f32)),
+                ],
+            ))
+            .unwrap();
+
+        let model_mat = handler.render_data(1.0)[0].model_mat;
+        assert_eq!(
+            model_mat,
+            Matrix4::new_translation(&Vector3::new(4.0, 0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn clearing_a_behavior_set_leaves_the_others() {
+        let mut handler = standard_handler();
+        let mut cleared = spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]);
+        cleared
+            .children
+            .push(spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]));
+        let cleared_id = handler.spawn_one(cleared).unwrap();
+        let kept: Vec<i128> = (0..3)
+            .map(|_| {
+                handler
+                    .spawn_one(spawn(100, &[GRAVITY3_BEHAVIOR_ID], vec![]))
+                    .unwrap()
+            })
+            .collect();
+
+        assert!(handler.clear_behaviors(&[MOTION3_BEHAVIOR_ID]));
+
+        assert_eq!(handler.live_count(), 3);
+        assert!(handler.get_danmaku(cleared_id).is_none());
+        assert!(kept.iter().all(|id| handler.get_danmaku(*id).is_some()));
+        assert!(handler.global_parent_map.is_empty());
+        assert!(!handler.global_family_depth_map.contains_key(&cleared_id));
+        assert!(!handler.clear_behaviors(&[MOTION3_BEHAVIOR_ID]));
+        handler.tick();
+        assert_eq!(handler.live_count(), 3);
+    }
+
+    #[test]
+    fn unknown_behaviors_drop_the_danmaku() {
+        let mut handler = standard_handler();
+
+        assert_eq!(
+            handler.spawn_one(spawn(100, &["does_not_exist"], vec![])),
+            None
+        );
+        handler.add_danmaku(vec![
+            spawn(100, &[MOTION3_BEHAVIOR_ID, "does_not_exist"], vec![]),
+            spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]),
+        ]);
+        handler.tick();
+
+        assert_eq!(handler.live_count(), 1);
+        assert_eq!(
+            handler.required_columns_for(&["does_not_exist"]),
+            Err(DanCoreError::UnknownBehavior("does_not_exist".to_string()))
+        );
+    }
+
+    #[test]
+    fn malformed_patterns_are_dropped_without_stopping_the_handler() {
+        let mut handler = standard_handler();
+        let moving = |x: f32| {
+            spawn(
+                100,
+                &[MOTION3_BEHAVIOR_ID],
+                vec![StandardSpawnData::MotionX(x)],
+            )
+        };
+
+        let mut unknown_child = moving(1.0);
+        unknown_child
+            .children
+            .push(spawn(100, &["does_not_exist"], vec![]));
+        let mut orphan = moving(1.0);
+        orphan.parent = Some(12345);
+        let mut unknown_next_stage = spawn(2, &[MOTION3_BEHAVIOR_ID], vec![]);
+        unknown_next_stage
+            .next_stage
+            .push(spawn(100, &["does_not_exist"], vec![]));
+
+        handler.add_danmaku(vec![
+            spawn(100, &["does_not_exist"], vec![]),
+            unknown_child,
+            orphan,
+            unknown_next_stage,
+        ]);
+        let id = handler.spawn_one(moving(2.0)).unwrap();
+
+        for _ in 0..5 {
+            handler.tick();
+        }
+
+        // Only what was well formed is left, and still moves
+        assert_eq!(handler.get_danmaku(id).unwrap().position.x, 10.0);
+        assert_eq!(handler.live_count(), 2);
+        assert!(handler.spawn_one(moving(1.0)).is_some());
+        handler.tick();
+        assert_eq!(handler.live_count(), 3);
+    }
+
+    #[test]
+    fn required_columns_for_combines_the_behaviors() {
+        use StandardDataColumns as C;
+
+        let handler = standard_handler();
+
+        assert_eq!(
+            handler.required_columns_for(&[MOTION3_BEHAVIOR_ID, GRAVITY3_BEHAVIOR_ID]),
+            Ok(C::PosX
+                | C::PosY
+                | C::PosZ
+                | C::MotionX
+                | C::MotionY
+                | C::MotionZ
+                | C::GravityX
+                | C::GravityY
+                | C::GravityZ)
+        );
+        assert_eq!(handler.required_columns_for(&[]), Ok(EnumSet::empty()));
+    }
+
+    #[test]
+    fn stale_preferred_indices_dont_overwrite_live_danmaku() {
+        let mut handler = standard_handler();
+        let ids: Vec<i128> = motion_spawns(10)
+            .into_iter()
+            .map(|d| handler.spawn_one(d).unwrap())
+            .collect();
+        let (_, dead_idx) = handler.locate(ids[2]).unwrap();
+        handler.remove_danmaku(ids[2]);
+
+        // Another danmaku is moved into the slot before the next stage gets to use it
+        handler.compact_all();
+        let moved = *ids
+            .iter()
+            .find(|id| handler.locate(**id).map(|(_, idx)| idx) == Some(dead_idx))
+            .unwrap();
+        let moved_x = handler.get_danmaku(moved).unwrap().position.x;
+
+        let next = motion_spawns(1).pop().unwrap();
+        let (next_id, _) = handler
+            .add_single_danmaku(next, Some((dead_idx, ids[2])))
+            .unwrap();
+
+        assert_ne!(handler.locate(next_id).unwrap().1, dead_idx);
+        assert_eq!(handler.locate(moved).unwrap().1, dead_idx);
+        assert_eq!(handler.get_danmaku(moved).unwrap().position.x, moved_x);
+        assert_eq!(handler.live_count(), 10);
+    }
+
+    #[test]
+    fn reused_slots_dont_interpolate_from_the_previous_danmaku() {
+        let behaviors = [MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID];
+        let mut handler = standard_handler();
+        let mut parent = spawn(
+            1,
+            &behaviors,
+            vec![
+                StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                },
+                StandardSpawnData::MotionX(10.0),
+                StandardSpawnData::MainColor(0xFF0000),
+            ],
+        );
+        let mut next = spawn(
+            100,
+            &behaviors,
+            vec![
+                StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                },
+                StandardSpawnData::PosX(Pos::from(-50.0_f32)),
+                StandardSpawnData::MainColor(0x0000FF),
+            ],
+        );
+        next.anchor = Some(Vector3::zeros());
+        parent
+            .next_stage_inherit
+            .insert(StandardDataColumns::MainColor, InheritOp::Ignore);
+        parent.next_stage.push(next);
+        let parent_id = handler.spawn_one(parent).unwrap();
+        let (_, parent_idx) = handler.locate(parent_id).unwrap();
+
+        handler.tick();
+        handler.tick();
+
+        let rendered = handler.render_data_with_ids(0.0);
+        assert_eq!(rendered.len(), 1);
+        let (next_id, next) = &rendered[0];
+        assert_eq!(next.model_mat.column(3).x, -50.0);
+        assert_eq!(next.main_color, ColorHex(0x0000FF));
+        let next_id = *next_id;
+        assert_eq!(handler.locate(next_id).unwrap().1, parent_idx);
+    }
+
+    #[test]
+    fn reused_motion1_slots_start_from_the_new_spawn_position() {
+        let behaviors = [MOTION1_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID];
+        let along_z = |end_time, pos_z: f32, motion_z| {
+            spawn(
+                end_time,
+                &behaviors,
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::PosZ(Pos::from(pos_z)),
+                    StandardSpawnData::MotionZ(motion_z),
+                ],
+            )
+        };
+        let mut handler = standard_handler();
+        let mut parent = along_z(3, 0.0, 10.0);
+        let mut next = along_z(100, -50.0, 1.0);
+        next.anchor = Some(Vector3::zeros());
+        parent.next_stage_add_data = EnumSet::empty();
+        parent.next_stage.push(next);
+        let parent_id = handler.spawn_one(parent).unwrap();
+        let (_, parent_idx) = handler.locate(parent_id).unwrap();
+
+        let z_at = |handler: &mut TopDanmakuBehaviorsHandler<StandardColumns>, partial| {
+            let rendered = handler.render_data_with_ids(partial);
+            assert_eq!(rendered.len(), 1);
+            (rendered[0].0, rendered[0].1.model_mat.column(3).z)
+        };
+        while handler.get_danmaku(parent_id).is_some() {
+            handler.tick();
+        }
+
+        let (next_id, z) = z_at(&mut handler, 0.0);
+        assert_eq!(handler.locate(next_id).unwrap().1, parent_idx);
+        // Not from where the parent was when it died
+        assert_eq!(z, -50.0);
+        assert_eq!(z_at(&mut handler, 1.0).1, -50.0);
+
+        handler.tick();
+        assert_eq!(z_at(&mut handler, 0.0).1, -50.0);
+        assert_eq!(z_at(&mut handler, 1.0).1, -49.0);
+    }
+
+    #[test]
+    fn forward_defaults_to_x_in_new_resized_and_compacted_slots() {
+        let mut handler = standard_handler();
+        let accelerating = |end_time| {
+            spawn(
+                end_time,
+                &[ACCELERATION3_BEHAVIOR_ID],
+                vec![StandardSpawnData::SpeedAccel(1.0)],
+            )
+        };
+        let first = handler.spawn_one(accelerating(1)).unwrap();
+        let ids: Vec<_> = (0..300)
+            .map(|_| handler.spawn_one(accelerating(100)).unwrap())
+            .collect();
+        assert!(handler.resize_count() > 0);
+        handler.tick();
+        handler.tick();
+        assert!(handler.get_danmaku(first).is_none());
+
+        handler.compact_all();
+        let fresh = handler.spawn_one(accelerating(100)).unwrap();
+        handler.tick();
+
+        assert_eq!(
+            handler.get_danmaku(fresh).unwrap().motion,
+            Vector3::new(1.0, 0.0, 0.0)
+        );
+        for id in [ids[0], ids[299]] {
+            assert_eq!(
+                handler.get_danmaku(id).unwrap().motion,
+                Vector3::new(3.0, 0.0, 0.0)
+            );
+        }
+    }
+
+    #[test]
+    fn render_colors_decode_to_rgb() {
+        let mut handler = standard_handler();
+        handler
+            .spawn_one(spawn(
+                100,
+                &[APPEARANCE_BEHAVIOR_ID],
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::MainColor(0x336699),
+                    StandardSpawnData::SecondaryColor(0xFF8000),
+                ],
+            ))
+            .unwrap();
+
+        let render_data = handler.render_data(1.0);
+        let main = render_data[0].main_color;
+        assert_eq!(main.0, 0x336699);
+        assert_eq!(main.to_rgba(), [0.2, 0.4, 0.6, 1.0]);
+        assert_eq!(main.to_string(), "#336699");
+        assert_eq!(
+            render_data[0].secondary_color.to_rgba(),
+            [1.0, 128.0 / 255.0, 0.0, 1.0]
+        );
+    }
+
+    fn live_positions(handler: &TopDanmakuBehaviorsHandler<StandardColumns>) -> Vec<(i16, f32)> {
+        let mut positions: Vec<_> = handler
+            .handlers
+            .values()
+            .flatten()
+            .flat_map(|h| h.live_ids())
+            .map(|id| {
+                let view = handler.get_danmaku(id).unwrap();
+                (handler.global_family_depth_map[&id], view.position.x)
+            })
+            .collect();
+        positions.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        positions
+    }
+
+    #[test]
+    fn grouped_spawning_matches_spawning_one_by_one() {
+        let burst = || {
+            let mut burst = motion_spawns(1000);
+            burst[10].children.push(spawn(
+                100,
+                &[GRAVITY3_BEHAVIOR_ID],
+                vec![StandardSpawnData::MotionX(1.0)],
+            ));
+            burst
+        };
+
+        let mut one_by_one = standard_handler();
+        one_by_one.add_danmaku(burst());
+        let mut grouped = standard_handler();
+        grouped.add_danmaku_grouped(burst());
+
+        assert_eq!(grouped.live_count(), 1001);
+        assert_eq!(live_positions(&grouped), live_positions(&one_by_one));
+        // Room for the whole group is made at once, instead of growing the handler step by step
+        assert_eq!(grouped.resize_count(), 0);
+        assert!(one_by_one.resize_count() >= 3);
+    }
+
+    #[test]
+    fn locate_finds_only_live_danmaku() {
+        let mut handler = standard_handler();
+        let motion = |handler: &mut TopDanmakuBehaviorsHandler<StandardColumns>| {
+            handler
+                .spawn_one(spawn(100, &[MOTION3_BEHAVIOR_ID], vec![]))
+                .unwrap()
+        };
+        let first = motion(&mut handler);
+        let second = motion(&mut handler);
+        let other = handler
+            .spawn_one(spawn(100, &[GRAVITY3_BEHAVIOR_ID], vec![]))
+            .unwrap();
+
+        let (motion_handler, first_idx) = handler.locate(first).unwrap();
+        assert_eq!(first_idx, 0);
+        assert_eq!(handler.locate(second), Some((motion_handler, 1)));
+        let (other_handler, other_idx) = handler.locate(other).unwrap();
+        assert_ne!(other_handler, motion_handler);
+        assert_eq!(other_idx, 0);
+
+        assert!(handler.remove_danmaku(first));
+        assert_eq!(handler.locate(first), None);
+        assert_eq!(handler.locate(second), Some((motion_handler, 1)));
+
+        // An unknown handler, and a known handler with an id it never gave out
+        assert_eq!(handler.locate(((i64::MAX as i128) << 64) | 1), None);
+        assert_eq!(handler.locate(second + 1000), None);
+        assert_eq!(handler.locate(-1), None);
+    }
+
+    fn handler_of(handler: &TopDanmakuBehaviorsHandler<StandardColumns>, id: i128) -> i64 {
+        handler.locate(id).unwrap().0
+    }
+
+    #[test]
+    fn same_behavior_sets_share_a_handler() {
+        let mut handler = standard_handler();
+        let mut spawn_with =
+            |behaviors: &[&'static str]| handler.spawn_one(spawn(100, behaviors, vec![])).unwrap();
+        let a = spawn_with(&[MOTION3_BEHAVIOR_ID, GRAVITY3_BEHAVIOR_ID]);
+        let b = spawn_with(&[MOTION3_BEHAVIOR_ID, GRAVITY3_BEHAVIOR_ID]);
+        let c = spawn_with(&[MOTION3_BEHAVIOR_ID]);
+        let d = spawn_with(&[GRAVITY3_BEHAVIOR_ID]);
+
+        assert_eq!(handler_of(&handler, a), handler_of(&handler, b));
+        assert_ne!(handler_of(&handler, a), handler_of(&handler, c));
+        assert_ne!(handler_of(&handler, c), handler_of(&handler, d));
+        assert_eq!(handler.handlers.values().flatten().count(), 3);
+
+        // Ids are separated, so moving a character between them makes another set
+        assert_ne!(
+            behavior_set_hash(&["ab", "c"]),
+            behavior_set_hash(&["a", "bc"])
+        );
+    }
+
+    #[test]
+    fn reordered_behavior_sets_share_a_handler() {
+        let mut handler = standard_handler();
+        let a = handler
+            .spawn_one(spawn(
+                100,
+                &[MOTION3_BEHAVIOR_ID, GRAVITY3_BEHAVIOR_ID],
+                vec![],
+            ))
+            .unwrap();
+        let b = handler
+            .spawn_one(spawn(
+                100,
+                &[
+                    GRAVITY3_BEHAVIOR_ID,
+                    MOTION3_BEHAVIOR_ID,
+                    GRAVITY3_BEHAVIOR_ID,
+                ],
+                vec![],
+            ))
+            .unwrap();
+
+        assert_eq!(handler_of(&handler, a), handler_of(&handler, b));
+        assert_eq!(handler.handlers.values().flatten().count(), 1);
     }
 }
@@ -0,0 +1,197 @@
+use std::ops::{Index, IndexMut};
+use std::simd::{Simd, SimdElement};
+
+use crate::danmaku::N;
+
+// Element indexed access to SIMD columns. How danmaku are split into chunks depends on the SIMD
+// width of the host, so anything reading across danmaku, like neighbor queries, goes through these
+// to get the same results on every machine. The lane count only differs from N in tests
+#[derive(Clone, Copy)]
+pub struct LogicalColumn<'a, A: SimdElement, const L: usize = N> {
+    chunks: &'a [Simd<A, L>],
+    len: usize,
+}
+
+impl<'a, A: SimdElement, const L: usize> LogicalColumn<'a, A, L> {
+    // len is the amount of danmaku in use, as the last chunk may be padded out
+    pub fn new(chunks: &'a [Simd<A, L>], len: usize) -> LogicalColumn<'a, A, L> {
+        assert!(len <= chunks.len() * L);
+        LogicalColumn { chunks, len }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, idx: usize) -> Option<A> {
+        (idx < self.len).then(|| self.chunks[idx / L][idx % L])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = A> + 'a {
+        self.chunks
+            .iter()
+            .flat_map(|chunk| chunk.to_array())
+            .take(self.len)
+    }
+}
+
+impl<A: SimdElement, const L: usize> Index<usize> for LogicalColumn<'_, A, L> {
+    type Output = A;
+
+    fn index(&self, idx: usize) -> &A {
+        assert!(idx < self.len);
+        &self.chunks[idx / L].as_array()[idx % L]
+    }
+}
+
+pub struct LogicalColumnMut<'a, A: SimdElement, const L: usize = N> {
+    chunks: &'a mut [Simd<A, L>],
+    len: usize,
+}
+
+impl<'a, A: SimdElement, const L: usize> LogicalColumnMut<'a, A, L> {
+    pub fn new(chunks: &'a mut [Simd<A, L>], len: usize) -> LogicalColumnMut<'a, A, L> {
+        assert!(len <= chunks.len() * L);
+        LogicalColumnMut { chunks, len }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, idx: usize) -> Option<A> {
+        (idx < self.len).then(|| self.chunks[idx / L][idx % L])
+    }
+
+    pub fn set(&mut self, idx: usize, value: A) {
+        self[idx] = value;
+    }
+
+    pub fn as_logical(&self) -> LogicalColumn<'_, A, L> {
+        LogicalColumn::new(self.chunks, self.len)
+    }
+}
+
+impl<A: SimdElement, const L: usize> Index<usize> for LogicalColumnMut<'_, A, L> {
+    type Output = A;
+
+    fn index(&self, idx: usize) -> &A {
+        assert!(idx < self.len);
+        &self.chunks[idx / L].as_array()[idx % L]
+    }
+}
+
+impl<A: SimdElement, const L: usize> IndexMut<usize> for LogicalColumnMut<'_, A, L> {
+    fn index_mut(&mut self, idx: usize) -> &mut A {
+        assert!(idx < self.len);
+        &mut self.chunks[idx / L].as_mut_array()[idx % L]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A scene of 13 positions, split into chunks of the given width with the tail padded out
+    fn chunked<const L: usize>() -> Vec<Simd<f32, L>> {
+        let values = (0..13)
+            .map(|i| (i as f32 * 0.37).sin() * 10.0)
+            .collect::<Vec<_>>();
+        values.chunks(L).map(Simd::load_or_default).collect()
+    }
+
+    // The spawns of a scene, as the position, motion and gravity along one axis
+    fn spawns() -> Vec<(f32, f32, f32)> {
+        (0..13)
+            .map(|i| {
+                let i = i as f32;
+                ((i * 0.37).sin() * 10.0, (i * 0.71).cos(), -0.01 * (i % 3.0))
+            })
+            .collect()
+    }
+
+    // Runs the scene with the columns split into chunks of L lanes. Gravity and motion go through
+    // whole chunks, like the standard behaviors, while pulling danmaku towards the middle of the
+    // scene and their neighbors reads across danmaku through the logical columns
+    fn run_scene<const L: usize>(ticks: usize) -> Vec<f32> {
+        let column = |value: fn(&(f32, f32, f32)) -> f32| -> Vec<Simd<f32, L>> {
+            let values: Vec<f32> = spawns().iter().map(value).collect();
+            values.chunks(L).map(Simd::load_or_default).collect()
+        };
+        let len = spawns().len();
+        let mut pos = column(|s| s.0);
+        let mut motion = column(|s| s.1);
+        let gravity = column(|s| s.2);
+
+        for tick in 0..ticks {
+            for i in 0..pos.len() {
+                motion[i] += gravity[i] * Simd::splat(tick as f32);
+                pos[i] += motion[i];
+            }
+
+            let middle = LogicalColumn::new(&pos, len).iter().sum::<f32>() / len as f32;
+            for i in 0..pos.len() {
+                motion[i] += (Simd::splat(middle) - pos[i]) * Simd::splat(0.01);
+            }
+
+            let mut pos = LogicalColumnMut::new(&mut pos, len);
+            for idx in 1..len {
+                let pull = (pos[idx - 1] - pos[idx]) * 0.1;
+                pos[idx] += pull;
+            }
+        }
+
+        LogicalColumn::new(&pos, len).iter().collect()
+    }
+
+    #[test]
+    fn output_is_the_same_for_every_simd_width() {
+        let four = run_scene::<4>(100);
+        let eight = run_scene::<8>(100);
+
+        assert_eq!(four.len(), 13);
+        assert!(four.iter().all(|p| p.is_finite()));
+        // Bit for bit, down to the order the middle is summed in
+        assert_eq!(
+            four.iter().map(|p| p.to_bits()).collect::<Vec<_>>(),
+            eight.iter().map(|p| p.to_bits()).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            four.iter().sum::<f32>().to_bits(),
+            eight.iter().sum::<f32>().to_bits()
+        );
+        assert_eq!(run_scene::<1>(100), four);
+    }
+
+    #[test]
+    fn writes_land_in_the_same_element() {
+        let mut four = chunked::<4>();
+        let mut eight = chunked::<8>();
+
+        let mut four_mut = LogicalColumnMut::new(&mut four, 13);
+        let mut eight_mut = LogicalColumnMut::new(&mut eight, 13);
+        for idx in [0, 3, 4, 7, 8, 12] {
+            four_mut.set(idx, idx as f32);
+            eight_mut.set(idx, idx as f32);
+        }
+
+        assert_eq!(
+            four_mut.as_logical().iter().collect::<Vec<_>>(),
+            eight_mut.as_logical().iter().collect::<Vec<_>>()
+        );
+        assert_eq!(four[1][0], 4.0);
+        assert_eq!(four[3][0], 12.0);
+        assert_eq!(eight[1][4], 12.0);
+        // The padding past the last danmaku can't be read
+        assert_eq!(LogicalColumn::new(&four, 13).get(13), None);
+        assert_eq!(LogicalColumn::new(&eight, 13).get(13), None);
+    }
+}
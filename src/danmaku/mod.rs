@@ -1,13 +1,22 @@
+use std::collections::HashMap;
+use std::fmt::Debug;
+
 use enumset::{EnumSet, EnumSetType};
+use nalgebra::{Matrix4, Vector3};
 use target_features::CURRENT_TARGET;
 
-use crate::danmaku::data::{DanmakuSpawnData, RenderData};
+use crate::danmaku::data::{
+    DanmakuSpawnData, DanmakuView, HandlerContext, PendingSpawn, RenderData,
+};
 
 pub mod data;
 pub mod handlers;
+pub mod logical;
 pub mod standard;
 
-pub const N: usize = if let Some(size) = CURRENT_TARGET.suggested_simd_width::<f32>() {
+pub const N: usize = if cfg!(feature = "force_scalar") {
+    1
+} else if let Some(size) = CURRENT_TARGET.suggested_simd_width::<f32>() {
     size
 } else {
     // If SIMD isn't supported natively, we use a vector of 1 element.
@@ -16,27 +25,38 @@ pub const N: usize = if let Some(size) = CURRENT_TARGET.suggested_simd_width::<f
 };
 
 pub trait DanmakuData {
-    type DataColumns: EnumSetType;
+    type DataColumns: EnumSetType + Debug;
     type SpawnData;
 
     fn new(new_column_size: usize, required: EnumSet<Self::DataColumns>) -> Self;
 
     fn required_columns(&self) -> EnumSet<Self::DataColumns>;
 
-    fn grab_new_spawns(
-        &mut self,
-    ) -> Vec<(
-        DanmakuSpawnData<Self::SpawnData, Self::DataColumns>,
-        Option<usize>,
-    )>;
+    fn grab_new_spawns(&mut self) -> Vec<PendingSpawn<Self::SpawnData, Self::DataColumns>>;
 
     fn resize(&mut self, new_max_size: usize);
 
     fn compact(&mut self, new_max_size: usize);
 
+    // Must be called whenever the data used for the transform matrices changes
+    fn invalidate_transform_mats(&mut self);
+
+    // Called before the behaviors run each tick
+    fn set_context(&mut self, context: &HandlerContext);
+
     fn id(&mut self) -> &mut Vec<i128>;
     fn dead(&mut self) -> &mut Vec<bool>;
+    fn ids(&self) -> &[i128];
+    fn is_dead(&self, idx: usize) -> bool;
     fn current_dead_len(&self) -> usize;
+    // The bytes allocated for each column, leaving out columns that aren't allocated
+    fn memory_usage(&self) -> Vec<(&'static str, usize)>;
+
+    fn expired_this_tick(&self) -> &[usize];
+    fn clear_expired(&mut self);
+
+    fn view(&self, idx: usize) -> DanmakuView;
+    fn kill(&mut self, idx: usize);
 
     fn add_danmaku_at_idx(
         &mut self,
@@ -46,15 +66,39 @@ pub trait DanmakuData {
     ) -> Vec<DanmakuSpawnData<Self::SpawnData, Self::DataColumns>>;
 
     fn compute_transform_mats(&mut self, current_size: usize, partial_ticks: f32);
+    // The transform of a single danmaku, without computing those of the others. None for dead
+    // danmaku, and transforms that aren't finite
+    fn transform_mat_of(&mut self, idx: usize, partial_ticks: f32) -> Option<Matrix4<f32>>;
+    fn compute_and_get_transform_mats(
+        &mut self,
+        current_size: usize,
+        partial_ticks: f32,
+    ) -> Vec<(i128, Matrix4<f32>)>;
     fn compute_and_get_render_data(
         &mut self,
         current_size: usize,
         partial_ticks: f32,
-    ) -> Vec<(i128, RenderData)>;
+    ) -> Vec<(i128, RenderData<'_>)>;
+
+    fn collect_damage(
+        &mut self,
+        current_size: usize,
+        world_transforms: &HashMap<i128, Matrix4<f32>>,
+        hitbox: (Vector3<f32>, f32),
+    ) -> f32;
+
+    // Moves every danmaku, including where they were last tick, so they don't streak there
+    fn translate(&mut self, current_size: usize, delta: Vector3<f32>);
 }
 
+pub type BehaviorAct<C> = Box<dyn Fn(&mut C, usize)>;
+
 pub struct Behavior<C: DanmakuData> {
     pub identifier: &'static str,
     pub required_columns: EnumSet<C::DataColumns>,
-    pub act: fn(&mut C, usize),
+    // Behaviors of a handler run from the lowest to the highest order
+    pub order: i32,
+    // Other behaviors which have to be part of the same handler for this one to work
+    pub required_behaviors: &'static [&'static str],
+    pub act: BehaviorAct<C>,
 }
@@ -1,33 +1,60 @@
+use crate::color::{ColorHex, ColorLerpMode};
 use crate::danmaku::{
+    data::{DanmakuSpawnData, InheritOp},
     handlers::TopDanmakuBehaviorsHandler,
-    standard::{StandardColumns, StandardDataColumns, StandardSpawnData},
-    Behavior, N,
+    logical::LogicalColumn,
+    standard::{pos_to_f32, Pos, StandardColumns, StandardDataColumns, StandardSpawnData},
+    Behavior, DanmakuData, N,
 };
+use crate::error::DanCoreError;
+use crate::simd_math::{simd_len3, simd_normalize3};
 
 use enumset::EnumSet;
 use multiversion::multiversion;
-use nalgebra::{UnitVector3, Vector3};
-use std::simd::{cmp::SimdPartialOrd, num::SimdInt, Simd};
+use nalgebra::{UnitQuaternion, UnitVector3, Vector3};
+use std::simd::{
+    cmp::{SimdPartialEq, SimdPartialOrd},
+    num::{SimdFloat, SimdInt},
+    Mask, Select, Simd, SimdElement, StdFloat,
+};
+
+// Forces change the motion, which is then applied unless the danmaku is delayed, after which things
+// like bouncing can react to the new position. mandatory_end always runs last, so danmaku age after
+// everything else
+pub const PRE_FORCE_ORDER: i32 = -100;
+pub const FORCE_ORDER: i32 = 0;
+pub const CLAMP_ACCEL_ORDER: i32 = 50;
+pub const MOTION_ORDER: i32 = 100;
+pub const DELAY_ORDER: i32 = 150;
+pub const POST_MOTION_ORDER: i32 = 200;
+pub const APPEARANCE_ORDER: i32 = 300;
+pub const END_ORDER: i32 = i32::MAX;
 
 pub const MOTION1_BEHAVIOR_ID: &str = "motion1";
 pub fn motion1_behavior() -> Behavior<StandardColumns> {
     #[multiversion(targets = "simd")]
     fn act(columns: &mut StandardColumns, size: usize) {
+        let context = &columns.handler_context;
         let motion_z = &mut columns.motion_z[0..size.div_ceil(N)];
         let pos_z = &mut columns.pos_z[0..size.div_ceil(N)];
         let old_pos_z = &mut columns.old_pos_z[0..size.div_ceil(N)];
 
         old_pos_z[0..size.div_ceil(N)].copy_from_slice(&pos_z[0..size.div_ceil(N)]);
 
-        for i in 0..size {
-            pos_z[i] += motion_z[i]
+        for i in 0..size.div_ceil(N) {
+            pos_z[i] += context
+                .chunk_lane_mask(i)
+                .select(motion_z[i], Simd::splat(0.0))
+                .cast::<Pos>()
         }
     }
 
     Behavior {
         identifier: MOTION1_BEHAVIOR_ID,
         required_columns: StandardDataColumns::PosZ | StandardDataColumns::MotionZ,
-        act,
+        order: MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
     }
 }
 
@@ -35,19 +62,25 @@ pub const GRAVITY1_BEHAVIOR_ID: &str = "gravity1";
 pub fn gravity1_behavior() -> Behavior<StandardColumns> {
     #[multiversion(targets = "simd")]
     fn act(columns: &mut StandardColumns, size: usize) {
+        let context = &columns.handler_context;
         let ticks_existed = &columns.ticks_existed[0..size.div_ceil(N)];
         let mot = &mut columns.motion_y[0..size.div_ceil(N)];
         let gravity = &mut columns.gravity_y[0..size.div_ceil(N)];
 
-        for i in 0..size {
-            mot[i] += gravity[i] * ticks_existed[i].cast::<f32>();
+        for i in 0..size.div_ceil(N) {
+            mot[i] += context.chunk_lane_mask(i).select(
+                gravity[i] * ticks_existed[i].cast::<f32>(),
+                Simd::splat(0.0),
+            );
         }
     }
 
     Behavior {
         identifier: GRAVITY1_BEHAVIOR_ID,
         required_columns: StandardDataColumns::MotionY | StandardDataColumns::GravityY,
-        act,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
     }
 }
 
@@ -55,18 +88,23 @@ pub const ACCELERATION1_BEHAVIOR_ID: &str = "acceleration1";
 pub fn acceleration1_behavior() -> Behavior<StandardColumns> {
     #[multiversion(targets = "simd")]
     fn act(columns: &mut StandardColumns, size: usize) {
+        let context = &columns.handler_context;
         let speed_accel = &mut columns.speed_accel[0..size.div_ceil(N)];
         let motion = &mut columns.motion_z[0..size.div_ceil(N)];
 
         for i in 0..size.div_ceil(N) {
-            motion[i] += speed_accel[i];
+            motion[i] += context
+                .chunk_lane_mask(i)
+                .select(speed_accel[i], Simd::splat(0.0));
         }
     }
 
     Behavior {
         identifier: ACCELERATION1_BEHAVIOR_ID,
         required_columns: StandardDataColumns::MotionZ | StandardDataColumns::SpeedAccel,
-        act,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
     }
 }
 
@@ -78,8 +116,9 @@ pub fn rotate_orientation_behavior() -> Behavior<StandardColumns> {
         let old_orientation = &mut columns.old_orientation[0..size];
         let rotation = &mut columns.rotation[0..size];
 
+        old_orientation.copy_from_slice(orientation);
+
         for i in 0..size {
-            old_orientation[0] = orientation[i];
             orientation[i] *= rotation[i];
         }
     }
@@ -87,10 +126,16 @@ pub fn rotate_orientation_behavior() -> Behavior<StandardColumns> {
     Behavior {
         identifier: ROTATE_ORIENTATION_BEHAVIOR_ID,
         required_columns: StandardDataColumns::Rotation | StandardDataColumns::Orientation,
-        act,
+        order: POST_MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
     }
 }
 
+fn rotate_forward(rotation: &UnitQuaternion<f32>, forward: Vector3<f32>) -> UnitVector3<f32> {
+    rotation * UnitVector3::new_normalize(forward)
+}
+
 pub const ROTATE_FORWARD_BEHAVIOR_ID: &str = "rotate_forward";
 pub fn rotate_forward_behavior() -> Behavior<StandardColumns> {
     #[multiversion(targets = "simd")]
@@ -101,25 +146,25 @@ pub fn rotate_forward_behavior() -> Behavior<StandardColumns> {
 
         let rotation = &mut columns.rotation[0..size];
 
-        for i in 0..size.div_ceil(N) {
-            for j in 0..N {
-                let forward = UnitVector3::new_normalize(Vector3::new(
-                    forward_x[i][j],
-                    forward_y[i][j],
-                    forward_z[i][j],
-                ));
-                let new_forward = rotation[i * N + j] * forward;
-                forward_x[i][j] = new_forward.x;
-                forward_y[i][j] = new_forward.y;
-                forward_z[i][j] = new_forward.z;
-            }
+        // The rotations aren't SIMD, so they aren't padded out to a whole chunk
+        for (idx, rotation) in rotation.iter().enumerate() {
+            let (i, j) = (idx / N, idx % N);
+            let new_forward = rotate_forward(
+                rotation,
+                Vector3::new(forward_x[i][j], forward_y[i][j], forward_z[i][j]),
+            );
+            forward_x[i][j] = new_forward.x;
+            forward_y[i][j] = new_forward.y;
+            forward_z[i][j] = new_forward.z;
         }
     }
 
     Behavior {
         identifier: ROTATE_FORWARD_BEHAVIOR_ID,
         required_columns: StandardDataColumns::Rotation | StandardDataColumns::Forward,
-        act,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
     }
 }
 
@@ -127,6 +172,7 @@ pub const MOTION3_BEHAVIOR_ID: &str = "motion3";
 pub fn motion3_behavior() -> Behavior<StandardColumns> {
     #[multiversion(targets = "simd")]
     fn act(columns: &mut StandardColumns, size: usize) {
+        let context = &columns.handler_context;
         let motion_x = &mut columns.motion_x[0..size.div_ceil(N)];
         let motion_y = &mut columns.motion_y[0..size.div_ceil(N)];
         let motion_z = &mut columns.motion_z[0..size.div_ceil(N)];
@@ -137,20 +183,29 @@ pub fn motion3_behavior() -> Behavior<StandardColumns> {
         let old_pos_y = &mut columns.old_pos_y[0..size.div_ceil(N)];
         let old_pos_z = &mut columns.old_pos_z[0..size.div_ceil(N)];
 
-        old_pos_x[0..size].copy_from_slice(&pos_x[0..size.div_ceil(N)]);
-        old_pos_y[0..size].copy_from_slice(&pos_y[0..size.div_ceil(N)]);
-        old_pos_z[0..size].copy_from_slice(&pos_z[0..size.div_ceil(N)]);
+        old_pos_x.copy_from_slice(pos_x);
+        old_pos_y.copy_from_slice(pos_y);
+        old_pos_z.copy_from_slice(pos_z);
 
         for i in 0..size.div_ceil(N) {
-            pos_x[i] += motion_x[i]
+            pos_x[i] += context
+                .chunk_lane_mask(i)
+                .select(motion_x[i], Simd::splat(0.0))
+                .cast::<Pos>()
         }
 
         for i in 0..size.div_ceil(N) {
-            pos_y[i] += motion_y[i]
+            pos_y[i] += context
+                .chunk_lane_mask(i)
+                .select(motion_y[i], Simd::splat(0.0))
+                .cast::<Pos>()
         }
 
         for i in 0..size.div_ceil(N) {
-            pos_z[i] += motion_z[i]
+            pos_z[i] += context
+                .chunk_lane_mask(i)
+                .select(motion_z[i], Simd::splat(0.0))
+                .cast::<Pos>()
         }
     }
 
@@ -162,7 +217,92 @@ pub fn motion3_behavior() -> Behavior<StandardColumns> {
             | StandardDataColumns::MotionX
             | StandardDataColumns::MotionY
             | StandardDataColumns::MotionZ,
-        act,
+        order: MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+// The 2 variants of behaviors only use x and y, for planar games. Danmaku using only them never
+// allocate their z columns
+pub const MOTION2_BEHAVIOR_ID: &str = "motion2";
+pub fn motion2_behavior() -> Behavior<StandardColumns> {
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize) {
+        let context = &columns.handler_context;
+        let motion_x = &mut columns.motion_x[0..size.div_ceil(N)];
+        let motion_y = &mut columns.motion_y[0..size.div_ceil(N)];
+        let pos_x = &mut columns.pos_x[0..size.div_ceil(N)];
+        let pos_y = &mut columns.pos_y[0..size.div_ceil(N)];
+        let old_pos_x = &mut columns.old_pos_x[0..size.div_ceil(N)];
+        let old_pos_y = &mut columns.old_pos_y[0..size.div_ceil(N)];
+
+        old_pos_x.copy_from_slice(pos_x);
+        old_pos_y.copy_from_slice(pos_y);
+
+        for i in 0..size.div_ceil(N) {
+            pos_x[i] += context
+                .chunk_lane_mask(i)
+                .select(motion_x[i], Simd::splat(0.0))
+                .cast::<Pos>()
+        }
+
+        for i in 0..size.div_ceil(N) {
+            pos_y[i] += context
+                .chunk_lane_mask(i)
+                .select(motion_y[i], Simd::splat(0.0))
+                .cast::<Pos>()
+        }
+    }
+
+    Behavior {
+        identifier: MOTION2_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::PosX
+            | StandardDataColumns::PosY
+            | StandardDataColumns::MotionX
+            | StandardDataColumns::MotionY,
+        order: MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+pub const GRAVITY2_BEHAVIOR_ID: &str = "gravity2";
+pub fn gravity2_behavior() -> Behavior<StandardColumns> {
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize) {
+        let context = &columns.handler_context;
+        let ticks_existed = &columns.ticks_existed[0..size.div_ceil(N)];
+
+        let motion_x = &mut columns.motion_x[0..size.div_ceil(N)];
+        let motion_y = &mut columns.motion_y[0..size.div_ceil(N)];
+        let gravity_x = &mut columns.gravity_x[0..size.div_ceil(N)];
+        let gravity_y = &mut columns.gravity_y[0..size.div_ceil(N)];
+
+        for i in 0..size.div_ceil(N) {
+            motion_x[i] += context.chunk_lane_mask(i).select(
+                gravity_x[i] * ticks_existed[i].cast::<f32>(),
+                Simd::splat(0.0),
+            );
+        }
+
+        for i in 0..size.div_ceil(N) {
+            motion_y[i] += context.chunk_lane_mask(i).select(
+                gravity_y[i] * ticks_existed[i].cast::<f32>(),
+                Simd::splat(0.0),
+            );
+        }
+    }
+
+    Behavior {
+        identifier: GRAVITY2_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::MotionX
+            | StandardDataColumns::MotionY
+            | StandardDataColumns::GravityX
+            | StandardDataColumns::GravityY,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
     }
 }
 
@@ -170,6 +310,7 @@ pub const GRAVITY3_BEHAVIOR_ID: &str = "gravity3";
 pub fn gravity3_behavior() -> Behavior<StandardColumns> {
     #[multiversion(targets = "simd")]
     fn act(columns: &mut StandardColumns, size: usize) {
+        let context = &columns.handler_context;
         let ticks_existed = &columns.ticks_existed[0..size.div_ceil(N)];
 
         let motion_x = &mut columns.motion_x[0..size.div_ceil(N)];
@@ -180,22 +321,38 @@ pub fn gravity3_behavior() -> Behavior<StandardColumns> {
         let gravity_z = &mut columns.gravity_z[0..size.div_ceil(N)];
 
         for i in 0..size.div_ceil(N) {
-            motion_x[i] += gravity_x[i] * ticks_existed[i].cast::<f32>();
+            motion_x[i] += context.chunk_lane_mask(i).select(
+                gravity_x[i] * ticks_existed[i].cast::<f32>(),
+                Simd::splat(0.0),
+            );
         }
 
         for i in 0..size.div_ceil(N) {
-            motion_y[i] += gravity_y[i] * ticks_existed[i].cast::<f32>();
+            motion_y[i] += context.chunk_lane_mask(i).select(
+                gravity_y[i] * ticks_existed[i].cast::<f32>(),
+                Simd::splat(0.0),
+            );
         }
 
         for i in 0..size.div_ceil(N) {
-            motion_z[i] += gravity_z[i] * ticks_existed[i].cast::<f32>();
+            motion_z[i] += context.chunk_lane_mask(i).select(
+                gravity_z[i] * ticks_existed[i].cast::<f32>(),
+                Simd::splat(0.0),
+            );
         }
     }
 
     Behavior {
         identifier: GRAVITY3_BEHAVIOR_ID,
-        required_columns: StandardDataColumns::MotionY | StandardDataColumns::GravityY,
-        act,
+        required_columns: StandardDataColumns::MotionX
+            | StandardDataColumns::MotionY
+            | StandardDataColumns::MotionZ
+            | StandardDataColumns::GravityX
+            | StandardDataColumns::GravityY
+            | StandardDataColumns::GravityZ,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
     }
 }
 
@@ -203,6 +360,7 @@ pub const ACCELERATION3_BEHAVIOR_ID: &str = "acceleration3";
 pub fn acceleration3_behavior() -> Behavior<StandardColumns> {
     #[multiversion(targets = "simd")]
     fn act(columns: &mut StandardColumns, size: usize) {
+        let context = &columns.handler_context;
         let speed_accel = &mut columns.speed_accel[0..size.div_ceil(N)];
 
         let forward_x = &mut columns.forward_x[0..size.div_ceil(N)];
@@ -213,15 +371,21 @@ pub fn acceleration3_behavior() -> Behavior<StandardColumns> {
         let motion_z = &mut columns.motion_z[0..size.div_ceil(N)];
 
         for i in 0..size.div_ceil(N) {
-            motion_x[i] += forward_x[i] * speed_accel[i];
+            motion_x[i] += context
+                .chunk_lane_mask(i)
+                .select(forward_x[i] * speed_accel[i], Simd::splat(0.0));
         }
 
         for i in 0..size.div_ceil(N) {
-            motion_y[i] += forward_y[i] * speed_accel[i];
+            motion_y[i] += context
+                .chunk_lane_mask(i)
+                .select(forward_y[i] * speed_accel[i], Simd::splat(0.0));
         }
 
         for i in 0..size.div_ceil(N) {
-            motion_z[i] += forward_z[i] * speed_accel[i];
+            motion_z[i] += context
+                .chunk_lane_mask(i)
+                .select(forward_z[i] * speed_accel[i], Simd::splat(0.0));
         }
     }
 
@@ -232,202 +396,2674 @@ pub fn acceleration3_behavior() -> Behavior<StandardColumns> {
             | StandardDataColumns::MotionY
             | StandardDataColumns::MotionZ
             | StandardDataColumns::Forward,
-        act,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+pub const CURVE_BEHAVIOR_ID: &str = "curve";
+// Turns the forward of danmaku around axis by turn_per_tick and their own TurnRate each tick. Makes
+// arcs together with acceleration3, without needing a whole rotation for each danmaku
+pub fn curve_behavior(turn_per_tick: f32, axis: UnitVector3<f32>) -> Behavior<StandardColumns> {
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize, turn_per_tick: f32, axis: Vector3<f32>) {
+        let turn_rate = &columns.turn_rate[0..size.div_ceil(N)];
+        let forward_x = &mut columns.forward_x[0..size.div_ceil(N)];
+        let forward_y = &mut columns.forward_y[0..size.div_ceil(N)];
+        let forward_z = &mut columns.forward_z[0..size.div_ceil(N)];
+
+        let (kx, ky, kz) = (
+            Simd::splat(axis.x),
+            Simd::splat(axis.y),
+            Simd::splat(axis.z),
+        );
+
+        for i in 0..size.div_ceil(N) {
+            let angle = Simd::splat(turn_per_tick) + turn_rate[i];
+            let (sin, cos) = (angle.sin(), angle.cos());
+            let (x, y, z) = (forward_x[i], forward_y[i], forward_z[i]);
+
+            // Rodrigues' rotation formula
+            let dot = (kx * x + ky * y + kz * z) * (Simd::splat(1.0) - cos);
+            forward_x[i] = x * cos + (ky * z - kz * y) * sin + kx * dot;
+            forward_y[i] = y * cos + (kz * x - kx * z) * sin + ky * dot;
+            forward_z[i] = z * cos + (kx * y - ky * x) * sin + kz * dot;
+        }
+    }
+
+    Behavior {
+        identifier: CURVE_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::Forward | StandardDataColumns::TurnRate,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, turn_per_tick, *axis)),
+    }
+}
+
+pub const DELAY_BEHAVIOR_ID: &str = "delay";
+pub fn delay_behavior() -> Behavior<StandardColumns> {
+    // Undoes the movement of danmaku which haven't reached their activation tick yet, and puts back
+    // the motion and forward they spawned with, so forces only start acting once they activate
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize) {
+        fn hold(waiting: &[Mask<i32, N>], held: &[Simd<f32, N>], column: &mut [Simd<f32, N>]) {
+            // Columns the handler doesn't have are empty, and have nothing to hold
+            for ((column, held), waiting) in column.iter_mut().zip(held).zip(waiting) {
+                *column = waiting.select(*held, *column);
+            }
+        }
+
+        let ticks_existed = &columns.ticks_existed[0..size.div_ceil(N)];
+        let activation_tick = &columns.activation_tick[0..size.div_ceil(N)];
+        let old_pos_x = &columns.old_pos_x[0..size.div_ceil(N)];
+        let old_pos_y = &columns.old_pos_y[0..size.div_ceil(N)];
+        let old_pos_z = &columns.old_pos_z[0..size.div_ceil(N)];
+
+        let pos_x = &mut columns.pos_x[0..size.div_ceil(N)];
+        let pos_y = &mut columns.pos_y[0..size.div_ceil(N)];
+        let pos_z = &mut columns.pos_z[0..size.div_ceil(N)];
+
+        let mut waiting = Vec::with_capacity(size.div_ceil(N));
+        for i in 0..size.div_ceil(N) {
+            let waiting_i16 = ticks_existed[i].simd_lt(activation_tick[i]);
+            let waiting_pos = waiting_i16.cast::<<Pos as SimdElement>::Mask>();
+
+            pos_x[i] = waiting_pos.select(old_pos_x[i], pos_x[i]);
+            pos_y[i] = waiting_pos.select(old_pos_y[i], pos_y[i]);
+            pos_z[i] = waiting_pos.select(old_pos_z[i], pos_z[i]);
+            waiting.push(waiting_i16.cast::<i32>());
+        }
+
+        hold(&waiting, &columns.held_motion_x, &mut columns.motion_x);
+        hold(&waiting, &columns.held_motion_y, &mut columns.motion_y);
+        hold(&waiting, &columns.held_motion_z, &mut columns.motion_z);
+        hold(&waiting, &columns.held_forward_x, &mut columns.forward_x);
+        hold(&waiting, &columns.held_forward_y, &mut columns.forward_y);
+        hold(&waiting, &columns.held_forward_z, &mut columns.forward_z);
+    }
+
+    Behavior {
+        identifier: DELAY_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::PosX
+            | StandardDataColumns::PosY
+            | StandardDataColumns::PosZ
+            | StandardDataColumns::ActivationTick,
+        order: DELAY_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+// Remembers the motion before the forces of this tick, for clamp_accel to compare against.
+// Anything using clamp_accel has to use this as well
+pub const ACCEL_BASE_BEHAVIOR_ID: &str = "accel_base";
+pub fn accel_base_behavior() -> Behavior<StandardColumns> {
+    fn act(columns: &mut StandardColumns, size: usize) {
+        let chunks = size.div_ceil(N);
+        columns.accel_base_x[0..chunks].copy_from_slice(&columns.motion_x[0..chunks]);
+        columns.accel_base_y[0..chunks].copy_from_slice(&columns.motion_y[0..chunks]);
+        columns.accel_base_z[0..chunks].copy_from_slice(&columns.motion_z[0..chunks]);
+    }
+
+    Behavior {
+        identifier: ACCEL_BASE_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::MotionX
+            | StandardDataColumns::MotionY
+            | StandardDataColumns::MotionZ
+            | StandardDataColumns::MaxAccel,
+        order: PRE_FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+// Limits how much the forces of this tick together can change the motion, compared to what
+// accel_base remembered
+pub const CLAMP_ACCEL_BEHAVIOR_ID: &str = "clamp_accel";
+pub fn clamp_accel_behavior() -> Behavior<StandardColumns> {
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize) {
+        let max_accel = &columns.max_accel[0..size.div_ceil(N)];
+        let base_x = &columns.accel_base_x[0..size.div_ceil(N)];
+        let base_y = &columns.accel_base_y[0..size.div_ceil(N)];
+        let base_z = &columns.accel_base_z[0..size.div_ceil(N)];
+
+        let motion_x = &mut columns.motion_x[0..size.div_ceil(N)];
+        let motion_y = &mut columns.motion_y[0..size.div_ceil(N)];
+        let motion_z = &mut columns.motion_z[0..size.div_ceil(N)];
+
+        for i in 0..size.div_ceil(N) {
+            let dx = motion_x[i] - base_x[i];
+            let dy = motion_y[i] - base_y[i];
+            let dz = motion_z[i] - base_z[i];
+
+            let accel = simd_len3(dx, dy, dz);
+            let factor = accel
+                .simd_gt(max_accel[i])
+                .select(max_accel[i] / accel, Simd::splat(1.0));
+
+            motion_x[i] = base_x[i] + dx * factor;
+            motion_y[i] = base_y[i] + dy * factor;
+            motion_z[i] = base_z[i] + dz * factor;
+        }
+    }
+
+    Behavior {
+        identifier: CLAMP_ACCEL_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::MotionX
+            | StandardDataColumns::MotionY
+            | StandardDataColumns::MotionZ
+            | StandardDataColumns::MaxAccel,
+        order: CLAMP_ACCEL_ORDER,
+        required_behaviors: &[ACCEL_BASE_BEHAVIOR_ID],
+        act: Box::new(act),
     }
 }
 
 pub const MANDATORY_END_BEHAVIOR_ID: &str = "mandatory_end";
+fn inherit<T: Copy>(
+    v: &mut T,
+    op: InheritOp,
+    parent: impl FnOnce() -> T,
+    add: impl FnOnce(T, T) -> T,
+) {
+    match op {
+        InheritOp::Add => *v = add(parent(), *v),
+        InheritOp::Overwrite => *v = parent(),
+        InheritOp::Ignore => {}
+    }
+}
+
+// Makes the data of a danmaku spawned by another relative to the one spawning it, like the next
+// stage of a danmaku inheriting where it died
+// Anchored spawns keep their position data as is, to be placed relative to the anchor after
+fn inherit_next_stage(
+    columns: &StandardColumns,
+    idx: usize,
+    next: &mut DanmakuSpawnData<StandardSpawnData, StandardDataColumns>,
+) {
+    let anchored = next.anchor.is_some();
+    add_missing_inherited(columns, idx, &mut next.behavior_data, anchored);
+    next.behavior_data
+        .iter_mut()
+        .for_each(|data| inherit_from_parent(columns, idx, data, anchored));
+
+    if let Some(anchor) = next.anchor {
+        place_at_anchor(&mut next.behavior_data, anchor);
+    }
+}
+
+// Data is only inherited onto data the child already has, so a child without any position or
+// motion would start at the origin and at rest, seemingly teleporting away from a fast parent. Any
+// of those asked for by next_stage_add_data gets a zero entry to add the parent's values to
+fn add_missing_inherited(
+    columns: &StandardColumns,
+    idx: usize,
+    data: &mut Vec<StandardSpawnData>,
+    anchored: bool,
+) {
+    use StandardDataColumns as C;
+
+    let add_data = columns.next_stage_add_data[idx];
+    for (column, zero) in [
+        (C::PosX, StandardSpawnData::PosX(0.0)),
+        (C::PosY, StandardSpawnData::PosY(0.0)),
+        (C::PosZ, StandardSpawnData::PosZ(0.0)),
+        (C::MotionX, StandardSpawnData::MotionX(0.0)),
+        (C::MotionY, StandardSpawnData::MotionY(0.0)),
+        (C::MotionZ, StandardSpawnData::MotionZ(0.0)),
+    ] {
+        let is_pos = matches!(column, C::PosX | C::PosY | C::PosZ);
+        if (anchored && is_pos)
+            || !add_data.contains(column)
+            || !columns.required_columns.contains(column)
+        {
+            continue;
+        }
+
+        let present = data
+            .iter()
+            .any(|d| std::mem::discriminant(d) == std::mem::discriminant(&zero));
+        if !present {
+            data.push(zero);
+        }
+    }
+}
+
+fn place_at_anchor(data: &mut Vec<StandardSpawnData>, anchor: Vector3<f32>) {
+    let mut offset = [
+        Pos::from(anchor.x),
+        Pos::from(anchor.y),
+        Pos::from(anchor.z),
+    ];
+    for d in data.iter_mut() {
+        let (v, axis) = match d {
+            StandardSpawnData::PosX(v) => (v, 0),
+            StandardSpawnData::PosY(v) => (v, 1),
+            StandardSpawnData::PosZ(v) => (v, 2),
+            _ => continue,
+        };
+        *v += std::mem::take(&mut offset[axis]);
+    }
+
+    // Axes without any position data would otherwise spawn at the origin
+    let [x, y, z] = offset;
+    for (pos, make) in [
+        (x, StandardSpawnData::PosX as fn(Pos) -> StandardSpawnData),
+        (y, StandardSpawnData::PosY),
+        (z, StandardSpawnData::PosZ),
+    ] {
+        if pos != 0.0 {
+            data.push(make(pos));
+        }
+    }
+}
+
+fn inherit_from_parent(
+    columns: &StandardColumns,
+    idx: usize,
+    data: &mut StandardSpawnData,
+    anchored: bool,
+) {
+    use StandardDataColumns as C;
+
+    let i = idx / N;
+    let j = idx % N;
+    let add_data = columns.next_stage_add_data[idx];
+    let overrides = &columns.next_stage_inherit[idx];
+
+    // Columns that aren't allocated have nothing to inherit
+    let op = |column: StandardDataColumns, default: InheritOp| {
+        if columns.required_columns.contains(column) {
+            overrides.get(&column).copied().unwrap_or(default)
+        } else {
+            InheritOp::Ignore
+        }
+    };
+    // Most columns are only added to when asked for by next_stage_add_data
+    let added = |column: StandardDataColumns| {
+        if anchored && matches!(column, C::PosX | C::PosY | C::PosZ) {
+            return InheritOp::Ignore;
+        }
+
+        let default = if add_data.contains(column) {
+            InheritOp::Add
+        } else {
+            InheritOp::Ignore
+        };
+        op(column, default)
+    };
+    let sum = |p: f32, v: f32| p + v;
+    let sum_pos = |p: Pos, v: Pos| p + v;
+    // Colors can't be added together, so adding them overwrites them too
+    let replace = |p: i32, _: i32| p;
+
+    match data {
+        StandardSpawnData::PosX(v) => inherit(v, added(C::PosX), || columns.pos_x[i][j], sum_pos),
+        StandardSpawnData::PosY(v) => inherit(v, added(C::PosY), || columns.pos_y[i][j], sum_pos),
+        StandardSpawnData::PosZ(v) => inherit(v, added(C::PosZ), || columns.pos_z[i][j], sum_pos),
+        StandardSpawnData::Orientation(v) => inherit(
+            v,
+            op(C::Orientation, InheritOp::Add),
+            || columns.orientation[idx],
+            |p, v| p * v,
+        ),
+        StandardSpawnData::Appearance { .. } => {}
+        StandardSpawnData::MainColor(v) => inherit(
+            v,
+            op(C::MainColor, InheritOp::Overwrite),
+            || columns.main_color[i][j],
+            replace,
+        ),
+        StandardSpawnData::SecondaryColor(v) => inherit(
+            v,
+            op(C::SecondaryColor, InheritOp::Overwrite),
+            || columns.secondary_color[i][j],
+            replace,
+        ),
+        StandardSpawnData::EndColor(v) => inherit(
+            v,
+            op(C::EndColor, InheritOp::Overwrite),
+            || columns.end_color[i][j],
+            replace,
+        ),
+        StandardSpawnData::Damage(v) => inherit(v, added(C::Damage), || columns.damage[i][j], sum),
+        StandardSpawnData::SizeX(v) => inherit(v, added(C::ScaleX), || columns.scale_x[i][j], sum),
+        StandardSpawnData::SizeY(v) => inherit(v, added(C::ScaleY), || columns.scale_y[i][j], sum),
+        StandardSpawnData::SizeZ(v) => inherit(v, added(C::ScaleZ), || columns.scale_z[i][j], sum),
+        StandardSpawnData::MotionX(v) => {
+            inherit(v, added(C::MotionX), || columns.motion_x[i][j], sum)
+        }
+        StandardSpawnData::MotionY(v) => {
+            inherit(v, added(C::MotionY), || columns.motion_y[i][j], sum)
+        }
+        StandardSpawnData::MotionZ(v) => {
+            inherit(v, added(C::MotionZ), || columns.motion_z[i][j], sum)
+        }
+        StandardSpawnData::GravityX(v) => {
+            inherit(v, added(C::GravityX), || columns.gravity_x[i][j], sum)
+        }
+        StandardSpawnData::GravityY(v) => {
+            inherit(v, added(C::GravityY), || columns.gravity_y[i][j], sum)
+        }
+        StandardSpawnData::GravityZ(v) => {
+            inherit(v, added(C::GravityZ), || columns.gravity_z[i][j], sum)
+        }
+        StandardSpawnData::SpeedAccel(v) => {
+            inherit(v, added(C::SpeedAccel), || columns.speed_accel[i][j], sum)
+        }
+        StandardSpawnData::MaxAccel(v) => {
+            inherit(v, added(C::MaxAccel), || columns.max_accel[i][j], sum)
+        }
+        StandardSpawnData::GravitySource(v) => inherit(
+            v,
+            op(C::GravitySource, InheritOp::Overwrite),
+            || {
+                Vector3::new(
+                    columns.gravity_source_x[i][j],
+                    columns.gravity_source_y[i][j],
+                    columns.gravity_source_z[i][j],
+                )
+            },
+            |p, v| p + v,
+        ),
+        StandardSpawnData::GravityStrength(v) => inherit(
+            v,
+            added(C::GravityStrength),
+            || columns.gravity_strength[i][j],
+            sum,
+        ),
+        StandardSpawnData::OrbitStrength(v) => inherit(
+            v,
+            added(C::OrbitStrength),
+            || columns.orbit_strength[i][j],
+            sum,
+        ),
+        StandardSpawnData::TurnRate(v) => {
+            inherit(v, added(C::TurnRate), || columns.turn_rate[i][j], sum)
+        }
+        // Relative to when the next stage spawns, so nothing to carry over
+        StandardSpawnData::ActivationTick(_) => {}
+        // Directions can't be added together, so adding them overwrites them too
+        StandardSpawnData::Forward(v) => inherit(
+            v,
+            op(C::Forward, InheritOp::Overwrite),
+            || {
+                UnitVector3::new_normalize(Vector3::new(
+                    columns.forward_x[i][j],
+                    columns.forward_y[i][j],
+                    columns.forward_z[i][j],
+                ))
+            },
+            |p, _| p,
+        ),
+        StandardSpawnData::Rotation(v) => inherit(
+            v,
+            op(C::Rotation, InheritOp::Add),
+            || columns.rotation[idx],
+            |p, v| p * v,
+        ),
+        StandardSpawnData::TrailLength(_) => {}
+        // Tags belong to the danmaku they were given to, so children keep their own
+        StandardSpawnData::UserData(_) => {}
+    }
+}
+
 pub fn mandatory_end() -> Behavior<StandardColumns> {
     #[multiversion(targets = "simd")]
     fn act(columns: &mut StandardColumns, size: usize) {
         let ticks_existed = &mut columns.ticks_existed[0..size.div_ceil(N)];
         let end_time = &mut columns.end_time[0..size.div_ceil(N)];
-        let next_stage = &mut columns.next_stage[0..size];
-        let next_stage_add_data = &mut columns.next_stage_add_data[0..size];
         let dead = &mut columns.dead[0..size];
+        let alive_chunks = &mut columns.alive_chunks;
+
+        // Immortal danmaku can live long enough to run out of ticks
+        for ticks in ticks_existed.iter_mut() {
+            *ticks = ticks.saturating_add(Simd::splat(1));
+        }
+
+        let mut newly_dead = vec![];
+        for i in 0..size.div_ceil(N) {
+            // Everything in the chunk has died and been handled already
+            if !alive_chunks[i] {
+                continue;
+            }
+
+            let expires = end_time[i].simd_ge(Simd::splat(0));
+            let this_dead = (expires & ticks_existed[i].simd_gt(end_time[i])).to_array();
+
+            for (j, &is_dead) in this_dead.iter().enumerate() {
+                let idx = i * N + j;
+                if idx >= size {
+                    break;
+                }
+
+                // Danmaku that were already dead have been recorded before
+                if is_dead && !dead[idx] {
+                    newly_dead.push(idx);
+                }
+
+                dead[idx] = dead[idx] || is_dead
+            }
+
+            alive_chunks[i] = dead[i * N..((i + 1) * N).min(size)].iter().any(|d| !d);
+        }
+
+        for idx in newly_dead {
+            columns.current_dead.push(idx);
+            columns.expired_this_tick.push(idx);
+            let mut next_stages = std::mem::take(&mut columns.next_stage[idx]);
+            next_stages
+                .iter_mut()
+                .for_each(|next| inherit_next_stage(columns, idx, next));
+
+            let preferred = (next_stages.len() == 1).then_some(idx);
+            columns.queue_spawns(idx, next_stages.into_iter().map(|d| (d, preferred)));
+        }
+    }
+
+    Behavior {
+        identifier: MANDATORY_END_BEHAVIOR_ID,
+        required_columns: EnumSet::empty(),
+        order: END_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+// mandatory_end ages danmaku after every other behavior has run, so this is the age they end the
+// tick at. Saturates like mandatory_end does, as immortal danmaku can get that old
+fn end_of_tick_age(ticks_existed: Simd<i16, N>) -> Simd<i16, N> {
+    ticks_existed.saturating_add(Simd::splat(1))
+}
 
-        let pos_x = &mut columns.pos_x;
-        let pos_y = &mut columns.pos_y;
-        let pos_z = &mut columns.pos_z;
+// How far through their lifetime danmaku are at the end of the tick, from 0 to 1, with danmaku
+// which end right away already at 1. Immortal danmaku have no lifetime, and are left out of the mask
+fn lifetime_progress(
+    ticks_existed: Simd<i16, N>,
+    end_time: Simd<i16, N>,
+) -> (Simd<f32, N>, Mask<i32, N>) {
+    let progress = (end_of_tick_age(ticks_existed).cast::<f32>()
+        / end_time.cast::<f32>().simd_max(Simd::splat(1.0)))
+    .simd_clamp(Simd::splat(0.0), Simd::splat(1.0));
 
-        let scale_x = &mut columns.scale_x;
-        let scale_y = &mut columns.scale_y;
-        let scale_z = &mut columns.scale_z;
+    (progress, end_time.simd_ge(Simd::splat(0)).cast::<i32>())
+}
 
-        let motion_x = &mut columns.motion_x;
-        let motion_y = &mut columns.motion_y;
-        let motion_z = &mut columns.motion_z;
+pub const COLOR_CYCLE_BEHAVIOR_ID: &str = "color_cycle";
+// Cycles the hue of danmaku once every period_ticks, starting from the color they spawned with
+pub fn color_cycle_behavior(period_ticks: i16) -> Result<Behavior<StandardColumns>, DanCoreError> {
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize, period_ticks: i16) {
+        let ticks_existed = &columns.ticks_existed[0..size.div_ceil(N)];
+        let spawn_color = &columns.spawn_main_color[0..size.div_ceil(N)];
+        let main_color = &mut columns.main_color[0..size.div_ceil(N)];
+        let old_main_color = &mut columns.old_main_color[0..size.div_ceil(N)];
 
-        let forward_x = &mut columns.forward_x;
-        let forward_y = &mut columns.forward_y;
-        let forward_z = &mut columns.forward_z;
+        old_main_color.copy_from_slice(main_color);
 
-        let gravity_x = &mut columns.gravity_x;
-        let gravity_y = &mut columns.gravity_y;
-        let gravity_z = &mut columns.gravity_z;
+        for i in 0..size.div_ceil(N) {
+            // The hue offset is computed from the spawn color each tick instead of being added on
+            // to the last color, which would drift as colors are rounded to whole channels
+            let age = end_of_tick_age(ticks_existed[i]);
+            let offset = ((age % Simd::splat(period_ticks)).cast::<f32>()
+                * Simd::splat(360.0 / period_ticks as f32))
+            .to_array();
 
-        let damage = &mut columns.damage;
+            for j in 0..N {
+                main_color[i][j] = ColorHex(spawn_color[i][j]).rotate_hue(offset[j]).0;
+            }
+        }
+    }
 
-        let orientation = &mut columns.orientation;
-        let rotation = &mut columns.rotation;
+    if period_ticks <= 0 {
+        return Err(DanCoreError::InvalidParameter("period_ticks"));
+    }
 
-        let speed_accel = &mut columns.speed_accel;
+    Ok(Behavior {
+        identifier: COLOR_CYCLE_BEHAVIOR_ID,
+        required_columns: EnumSet::only(StandardDataColumns::MainColor),
+        order: APPEARANCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, period_ticks)),
+    })
+}
 
-        let main_color = &mut columns.main_color;
-        let secondary_color = &mut columns.secondary_color;
+pub const COLOR_GRADIENT_BEHAVIOR_ID: &str = "color_gradient";
+pub fn color_gradient_behavior() -> Behavior<StandardColumns> {
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize) {
+        let ticks_existed = &columns.ticks_existed[0..size.div_ceil(N)];
+        let end_time = &columns.end_time[0..size.div_ceil(N)];
+        let main_color = &mut columns.main_color[0..size.div_ceil(N)];
+        let old_main_color = &mut columns.old_main_color[0..size.div_ceil(N)];
+        let start_color = &columns.spawn_main_color[0..size.div_ceil(N)];
+        let end_color = &columns.end_color[0..size.div_ceil(N)];
 
-        let add_spawns = &mut columns.add_spawns;
+        old_main_color.copy_from_slice(main_color);
 
         for i in 0..size.div_ceil(N) {
-            ticks_existed[i] += Simd::splat(1);
+            // Immortal danmaku have no lifetime to fade over, so they keep their color
+            let (progress, fades) = lifetime_progress(ticks_existed[i], end_time[i]);
+            let (progress, fades) = (progress.to_array(), fades.to_array());
+
+            for j in (0..N).filter(|j| fades[*j]) {
+                main_color[i][j] = ColorHex(start_color[i][j])
+                    .lerp(
+                        ColorHex(end_color[i][j]),
+                        progress[j],
+                        ColorLerpMode::default(),
+                    )
+                    .0;
+            }
         }
+    }
+
+    Behavior {
+        identifier: COLOR_GRADIENT_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::MainColor | StandardDataColumns::EndColor,
+        order: APPEARANCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+pub const SPEED_TO_COLOR_BEHAVIOR_ID: &str = "speed_to_color";
+pub fn speed_to_color_behavior(
+    slow: ColorHex,
+    fast: ColorHex,
+    max_speed: f32,
+) -> Behavior<StandardColumns> {
+    #[multiversion(targets = "simd")]
+    fn act(
+        columns: &mut StandardColumns,
+        size: usize,
+        slow: ColorHex,
+        fast: ColorHex,
+        max_speed: f32,
+    ) {
+        let motion_x = &columns.motion_x[0..size.div_ceil(N)];
+        let motion_y = &columns.motion_y[0..size.div_ceil(N)];
+        let motion_z = &columns.motion_z[0..size.div_ceil(N)];
+        let main_color = &mut columns.main_color[0..size.div_ceil(N)];
+        let old_main_color = &mut columns.old_main_color[0..size.div_ceil(N)];
+
+        old_main_color.copy_from_slice(main_color);
 
         for i in 0..size.div_ceil(N) {
-            let this_dead = ticks_existed[i].simd_gt(end_time[i]).to_array();
+            let speed = simd_len3(motion_x[i], motion_y[i], motion_z[i]);
+            let progress = (speed / Simd::splat(max_speed))
+                .simd_clamp(Simd::splat(0.0), Simd::splat(1.0))
+                .to_array();
 
             for j in 0..N {
-                let idx = i * N + j;
-                let add_data = next_stage_add_data[idx];
+                main_color[i][j] = slow.lerp(fast, progress[j], ColorLerpMode::default()).0;
+            }
+        }
+    }
 
-                let value_or_simd = |vec: &Vec<Simd<f32, N>>, required| {
-                    if (columns.required_columns & add_data).contains(required) {
-                        vec[i][j]
-                    } else {
-                        0.0
-                    }
-                };
-                let is_dead = this_dead[j];
-
-                if is_dead && !columns.current_dead.contains(&idx) {
-                    columns.current_dead.push(idx);
-                    let mut next_stages = std::mem::take(&mut next_stage[idx]);
-                    next_stages.iter_mut().for_each(|next| {
-                        next.behavior_data.iter_mut().for_each(|data| match data {
-                            StandardSpawnData::PosX(ref mut v) => {
-                                *v += value_or_simd(pos_x, StandardDataColumns::PosY)
-                            }
-                            StandardSpawnData::PosY(ref mut v) => {
-                                *v += value_or_simd(pos_y, StandardDataColumns::PosY)
-                            }
-                            StandardSpawnData::PosZ(ref mut v) => {
-                                *v += value_or_simd(pos_z, StandardDataColumns::PosY)
-                            }
-                            StandardSpawnData::Orientation(ref mut v) => {
-                                if columns
-                                    .required_columns
-                                    .contains(StandardDataColumns::Orientation)
-                                {
-                                    *v = orientation[idx] * *v
-                                }
-                            }
-                            StandardSpawnData::Appearance { .. } => {}
-                            StandardSpawnData::MainColor(ref mut v) => {
-                                if columns
-                                    .required_columns
-                                    .contains(StandardDataColumns::MainColor)
-                                {
-                                    *v = main_color[i][j]
-                                }
-                            }
-                            StandardSpawnData::SecondaryColor(ref mut v) => {
-                                if columns
-                                    .required_columns
-                                    .contains(StandardDataColumns::SecondaryColor)
-                                {
-                                    *v = secondary_color[i][j]
-                                }
-                            }
-                            StandardSpawnData::Damage(ref mut v) => {
-                                *v += value_or_simd(damage, StandardDataColumns::Damage)
-                            }
-                            StandardSpawnData::SizeX(ref mut v) => {
-                                *v += value_or_simd(scale_x, StandardDataColumns::ScaleX)
-                            }
-                            StandardSpawnData::SizeY(ref mut v) => {
-                                *v += value_or_simd(scale_y, StandardDataColumns::ScaleY)
-                            }
-                            StandardSpawnData::SizeZ(ref mut v) => {
-                                *v += value_or_simd(scale_z, StandardDataColumns::ScaleZ)
-                            }
-                            StandardSpawnData::MotionX(ref mut v) => {
-                                *v += value_or_simd(motion_x, StandardDataColumns::MotionX)
-                            }
-                            StandardSpawnData::MotionY(ref mut v) => {
-                                *v += value_or_simd(motion_y, StandardDataColumns::MotionY)
-                            }
-                            StandardSpawnData::MotionZ(ref mut v) => {
-                                *v += value_or_simd(motion_z, StandardDataColumns::MotionZ)
-                            }
-                            StandardSpawnData::GravityX(ref mut v) => {
-                                *v += value_or_simd(gravity_x, StandardDataColumns::GravityX)
-                            }
-                            StandardSpawnData::GravityY(ref mut v) => {
-                                *v += value_or_simd(gravity_y, StandardDataColumns::GravityY)
-                            }
-                            StandardSpawnData::GravityZ(ref mut v) => {
-                                *v += value_or_simd(gravity_z, StandardDataColumns::GravityZ)
-                            }
-                            StandardSpawnData::SpeedAccel(ref mut v) => {
-                                *v += value_or_simd(speed_accel, StandardDataColumns::SpeedAccel)
-                            }
-                            StandardSpawnData::Forward(ref mut v) => {
-                                if columns
-                                    .required_columns
-                                    .contains(StandardDataColumns::Forward)
-                                {
-                                    *v = UnitVector3::new_normalize(Vector3::new(
-                                        forward_x[i][j],
-                                        forward_y[i][j],
-                                        forward_z[i][j],
-                                    ))
-                                }
-                            }
-                            StandardSpawnData::Rotation(ref mut v) => {
-                                if columns
-                                    .required_columns
-                                    .contains(StandardDataColumns::Orientation)
-                                {
-                                    *v = rotation[idx] * *v
-                                }
-                            }
-                        })
-                    });
+    Behavior {
+        identifier: SPEED_TO_COLOR_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::MainColor
+            | StandardDataColumns::MotionX
+            | StandardDataColumns::MotionY
+            | StandardDataColumns::MotionZ,
+        order: APPEARANCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, slow, fast, max_speed)),
+    }
+}
 
-                    if next_stages.len() == 1 {
-                        add_spawns
-                            .append(&mut next_stages.into_iter().map(|d| (d, Some(idx))).collect());
-                    } else {
-                        add_spawns
-                            .append(&mut next_stages.into_iter().map(|d| (d, None)).collect());
-                    }
+pub const ANIMATE_PROPERTY_BEHAVIOR_ID: &str = "animate_property";
+pub fn animate_property_behavior(
+    key: &'static str,
+    from: f32,
+    to: f32,
+) -> Behavior<StandardColumns> {
+    fn act(columns: &mut StandardColumns, size: usize, key: &'static str, from: f32, to: f32) {
+        for i in 0..size.div_ceil(N) {
+            // Immortal danmaku have no lifetime to animate over, so they keep the value they have
+            let (progress, animates) =
+                lifetime_progress(columns.ticks_existed[i], columns.end_time[i]);
+
+            for j in (0..N).filter(|j| animates.test(*j)) {
+                let idx = i * N + j;
+                if idx >= size {
+                    break;
+                }
+                if columns.dead[idx] {
+                    continue;
                 }
 
-                dead[idx] = dead[idx] || is_dead
+                columns.render_properties[idx].insert(key, from + (to - from) * progress[j]);
             }
         }
     }
 
     Behavior {
-        identifier: MANDATORY_END_BEHAVIOR_ID,
-        required_columns: EnumSet::EMPTY,
-        act,
+        identifier: ANIMATE_PROPERTY_BEHAVIOR_ID,
+        required_columns: EnumSet::only(StandardDataColumns::Appearance),
+        order: APPEARANCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, key, from, to)),
     }
 }
 
-pub trait StandardTopHandlerExt {
-    fn register_standard_behaviors(&mut self);
+pub const KILL_IF_PROPERTY_BEHAVIOR_ID: &str = "kill_if_property";
+// Kills danmaku once a render property goes above the threshold, without spawning their next
+// stages. Shares its order with animate_property, whose id sorts first, so it sees the new values
+pub fn kill_if_property_behavior(key: &'static str, threshold: f32) -> Behavior<StandardColumns> {
+    fn act(columns: &mut StandardColumns, size: usize, key: &'static str, threshold: f32) {
+        for idx in 0..size {
+            if columns.dead[idx] {
+                continue;
+            }
+
+            if columns.render_properties[idx]
+                .get(key)
+                .is_some_and(|v| *v > threshold)
+            {
+                columns.kill(idx);
+            }
+        }
+    }
+
+    Behavior {
+        identifier: KILL_IF_PROPERTY_BEHAVIOR_ID,
+        required_columns: EnumSet::only(StandardDataColumns::Appearance),
+        order: APPEARANCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, key, threshold)),
+    }
 }
 
-impl StandardTopHandlerExt for TopDanmakuBehaviorsHandler<StandardColumns> {
-    fn register_standard_behaviors(&mut self) {
-        self.register_behavior(motion1_behavior());
-        self.register_behavior(gravity1_behavior());
-        self.register_behavior(acceleration1_behavior());
-        self.register_behavior(rotate_orientation_behavior());
-        self.register_behavior(rotate_forward_behavior());
-        self.register_behavior(motion3_behavior());
-        self.register_behavior(gravity3_behavior());
-        self.register_behavior(acceleration3_behavior());
-        self.register_behavior(mandatory_end());
+// The behaviors which leave danmaku outside the lane mask alone, and so can be made conditional
+pub const LANE_MASKED_BEHAVIORS: &[&str] = &[
+    MOTION1_BEHAVIOR_ID,
+    GRAVITY1_BEHAVIOR_ID,
+    ACCELERATION1_BEHAVIOR_ID,
+    MOTION3_BEHAVIOR_ID,
+    MOTION2_BEHAVIOR_ID,
+    GRAVITY2_BEHAVIOR_ID,
+    GRAVITY3_BEHAVIOR_ID,
+    ACCELERATION3_BEHAVIOR_ID,
+    KILL_IF_PROPERTY_BEHAVIOR_ID,
+    CULL_BEHAVIOR_ID,
+    SPAWNER_BEHAVIOR_ID,
+    SPLIT_BEHAVIOR_ID,
+];
+// Runs the registered behavior inner only for danmaku whose predicate column isn't zero, like to
+// hold some danmaku of a pattern in place. The others are left out of the lane mask of the handler
+// context, so inner neither changes, kills nor spawns anything for them. Takes its own identifier,
+// so inner can also be used by itself
+pub fn conditional_behavior(
+    handler: &TopDanmakuBehaviorsHandler<StandardColumns>,
+    identifier: &'static str,
+    predicate: StandardDataColumns,
+    inner: &'static str,
+) -> Result<Behavior<StandardColumns>, DanCoreError> {
+    if !LANE_MASKED_BEHAVIORS.contains(&inner) {
+        return Err(DanCoreError::InvalidParameter("inner"));
+    }
+    if matches!(
+        predicate,
+        StandardDataColumns::SpawnInterval
+            | StandardDataColumns::Orientation
+            | StandardDataColumns::Appearance
+            | StandardDataColumns::GravitySource
+            | StandardDataColumns::Rotation
+            | StandardDataColumns::Forward
+            | StandardDataColumns::Trail
+    ) {
+        return Err(DanCoreError::InvalidParameter("predicate"));
+    }
+    let inner = handler
+        .registered_behavior(inner)
+        .ok_or_else(|| DanCoreError::UnknownBehavior(inner.to_string()))?;
+
+    Ok(Behavior {
+        identifier,
+        required_columns: inner.required_columns | predicate,
+        order: inner.order,
+        required_behaviors: inner.required_behaviors,
+        act: Box::new(move |columns, size| {
+            let flagged = columns.flagged_by(predicate, size);
+            if !flagged.contains(&true) {
+                return;
+            }
+
+            columns.handler_context.lane_mask = Some(flagged);
+            (inner.act)(columns, size);
+            columns.handler_context.lane_mask = None;
+        }),
+    })
+}
+
+pub const SCALE_OVER_LIFETIME_BEHAVIOR_ID: &str = "scale_over_lifetime";
+pub fn scale_over_lifetime_behavior(from: f32, to: f32) -> Behavior<StandardColumns> {
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize, from: f32, to: f32) {
+        let ticks_existed = &columns.ticks_existed[0..size.div_ceil(N)];
+        let end_time = &columns.end_time[0..size.div_ceil(N)];
+
+        columns.old_scale_x[0..size.div_ceil(N)]
+            .copy_from_slice(&columns.scale_x[0..size.div_ceil(N)]);
+        columns.old_scale_y[0..size.div_ceil(N)]
+            .copy_from_slice(&columns.scale_y[0..size.div_ceil(N)]);
+        columns.old_scale_z[0..size.div_ceil(N)]
+            .copy_from_slice(&columns.scale_z[0..size.div_ceil(N)]);
+
+        for i in 0..size.div_ceil(N) {
+            // Immortal danmaku have no lifetime to scale over, so they keep their scale
+            let (progress, scales) = lifetime_progress(ticks_existed[i], end_time[i]);
+            let scale = Simd::splat(from) + Simd::splat(to - from) * progress;
+
+            columns.scale_x[i] = scales.select(scale, columns.scale_x[i]);
+            columns.scale_y[i] = scales.select(scale, columns.scale_y[i]);
+            columns.scale_z[i] = scales.select(scale, columns.scale_z[i]);
+        }
+    }
+
+    Behavior {
+        identifier: SCALE_OVER_LIFETIME_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::ScaleX
+            | StandardDataColumns::ScaleY
+            | StandardDataColumns::ScaleZ,
+        order: APPEARANCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, from, to)),
+    }
+}
+
+pub const BOUNCE_BEHAVIOR_ID: &str = "bounce";
+pub fn bounce_behavior(min: Vector3<f32>, max: Vector3<f32>) -> Behavior<StandardColumns> {
+    #[inline]
+    fn bounce_axis(
+        pos: &mut [Simd<Pos, N>],
+        old_pos: &mut [Simd<Pos, N>],
+        motion: &mut [Simd<f32, N>],
+        forward: &mut [Simd<f32, N>],
+        min: f32,
+        max: f32,
+    ) {
+        let zero = Simd::splat(0.0);
+        let (min, max) = (min as Pos, max as Pos);
+
+        for i in 0..pos.len() {
+            let outside = pos[i].simd_lt(Simd::splat(min)) | pos[i].simd_gt(Simd::splat(max));
+            let below = pos[i].simd_lt(Simd::splat(min)).cast::<i32>();
+            let above = pos[i].simd_gt(Simd::splat(max)).cast::<i32>();
+
+            // Bullets already outside are clamped back in, and only reflected if still heading out,
+            // so they don't flip back and forth on the next tick
+            pos[i] = pos[i].simd_clamp(Simd::splat(min), Simd::splat(max));
+            // Like wrap_bounds, so bullets aren't drawn outside while interpolating to the wall
+            old_pos[i] = outside.select(
+                old_pos[i].simd_clamp(Simd::splat(min), Simd::splat(max)),
+                old_pos[i],
+            );
+
+            let flip_motion = (below & motion[i].simd_lt(zero)) | (above & motion[i].simd_gt(zero));
+            motion[i] = flip_motion.select(-motion[i], motion[i]);
+
+            if let Some(forward) = forward.get_mut(i) {
+                let flip_forward =
+                    (below & forward.simd_lt(zero)) | (above & forward.simd_gt(zero));
+                *forward = flip_forward.select(-*forward, *forward);
+            }
+        }
+    }
+
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize, min: Vector3<f32>, max: Vector3<f32>) {
+        let has_forward = columns
+            .required_columns
+            .contains(StandardDataColumns::Forward);
+        let forward_size = if has_forward { size.div_ceil(N) } else { 0 };
+
+        bounce_axis(
+            &mut columns.pos_x[0..size.div_ceil(N)],
+            &mut columns.old_pos_x[0..size.div_ceil(N)],
+            &mut columns.motion_x[0..size.div_ceil(N)],
+            &mut columns.forward_x[0..forward_size],
+            min.x,
+            max.x,
+        );
+        bounce_axis(
+            &mut columns.pos_y[0..size.div_ceil(N)],
+            &mut columns.old_pos_y[0..size.div_ceil(N)],
+            &mut columns.motion_y[0..size.div_ceil(N)],
+            &mut columns.forward_y[0..forward_size],
+            min.y,
+            max.y,
+        );
+        bounce_axis(
+            &mut columns.pos_z[0..size.div_ceil(N)],
+            &mut columns.old_pos_z[0..size.div_ceil(N)],
+            &mut columns.motion_z[0..size.div_ceil(N)],
+            &mut columns.forward_z[0..forward_size],
+            min.z,
+            max.z,
+        );
+    }
+
+    Behavior {
+        identifier: BOUNCE_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::PosX
+            | StandardDataColumns::PosY
+            | StandardDataColumns::PosZ
+            | StandardDataColumns::MotionX
+            | StandardDataColumns::MotionY
+            | StandardDataColumns::MotionZ,
+        order: POST_MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, min, max)),
+    }
+}
+
+pub const WRAP_BOUNDS_BEHAVIOR_ID: &str = "wrap_bounds";
+// Bullets leaving one side of the bounds come back in on the opposite side
+pub fn wrap_bounds_behavior(min: Vector3<f32>, max: Vector3<f32>) -> Behavior<StandardColumns> {
+    #[inline]
+    fn wrap_axis(pos: &mut [Simd<Pos, N>], old_pos: &mut [Simd<Pos, N>], min: f32, max: f32) {
+        let min = Simd::splat(min as Pos);
+        let max = Simd::splat(max as Pos);
+        let width = max - min;
+
+        for i in 0..pos.len() {
+            let outside = pos[i].simd_lt(min) | pos[i].simd_gt(max);
+            // Bullets far outside wrap around as many times as needed to end up inside
+            let wrapped = pos[i] - width * ((pos[i] - min) / width).floor();
+            pos[i] = outside.select(wrapped, pos[i]);
+
+            // Interpolating from the old position would draw the bullet across the whole field
+            old_pos[i] = outside.select(pos[i], old_pos[i]);
+        }
+    }
+
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize, min: Vector3<f32>, max: Vector3<f32>) {
+        wrap_axis(
+            &mut columns.pos_x[0..size.div_ceil(N)],
+            &mut columns.old_pos_x[0..size.div_ceil(N)],
+            min.x,
+            max.x,
+        );
+        wrap_axis(
+            &mut columns.pos_y[0..size.div_ceil(N)],
+            &mut columns.old_pos_y[0..size.div_ceil(N)],
+            min.y,
+            max.y,
+        );
+        wrap_axis(
+            &mut columns.pos_z[0..size.div_ceil(N)],
+            &mut columns.old_pos_z[0..size.div_ceil(N)],
+            min.z,
+            max.z,
+        );
+    }
+
+    Behavior {
+        identifier: WRAP_BOUNDS_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::PosX
+            | StandardDataColumns::PosY
+            | StandardDataColumns::PosZ,
+        order: POST_MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, min, max)),
+    }
+}
+
+// Added to the squared distance to the gravity source, so bullets right on top of it don't get
+// flung away with an infinite acceleration
+const POINT_GRAVITY_SOFTENING: f32 = 0.01;
+
+#[multiversion(targets = "simd")]
+fn point_gravity_act(columns: &mut StandardColumns, size: usize, inverse_square: bool) {
+    let pos_x = &columns.pos_x[0..size.div_ceil(N)];
+    let pos_y = &columns.pos_y[0..size.div_ceil(N)];
+    let pos_z = &columns.pos_z[0..size.div_ceil(N)];
+    let source_x = &columns.gravity_source_x[0..size.div_ceil(N)];
+    let source_y = &columns.gravity_source_y[0..size.div_ceil(N)];
+    let source_z = &columns.gravity_source_z[0..size.div_ceil(N)];
+    let strength = &columns.gravity_strength[0..size.div_ceil(N)];
+
+    let motion_x = &mut columns.motion_x[0..size.div_ceil(N)];
+    let motion_y = &mut columns.motion_y[0..size.div_ceil(N)];
+    let motion_z = &mut columns.motion_z[0..size.div_ceil(N)];
+
+    for i in 0..size.div_ceil(N) {
+        let dx = (source_x[i].cast::<Pos>() - pos_x[i]).cast::<f32>();
+        let dy = (source_y[i].cast::<Pos>() - pos_y[i]).cast::<f32>();
+        let dz = (source_z[i].cast::<Pos>() - pos_z[i]).cast::<f32>();
+
+        let dist_sq = dx * dx + dy * dy + dz * dz + Simd::splat(POINT_GRAVITY_SOFTENING);
+        let mut factor = strength[i] / dist_sq.sqrt();
+        if inverse_square {
+            factor /= dist_sq;
+        }
+
+        motion_x[i] += dx * factor;
+        motion_y[i] += dy * factor;
+        motion_z[i] += dz * factor;
+    }
+}
+
+const POINT_GRAVITY_REQUIRED_COLUMNS: EnumSet<StandardDataColumns> = enumset::enum_set!(
+    StandardDataColumns::PosX
+        | StandardDataColumns::PosY
+        | StandardDataColumns::PosZ
+        | StandardDataColumns::MotionX
+        | StandardDataColumns::MotionY
+        | StandardDataColumns::MotionZ
+        | StandardDataColumns::GravitySource
+        | StandardDataColumns::GravityStrength
+);
+
+pub const POINT_GRAVITY_BEHAVIOR_ID: &str = "point_gravity";
+pub fn point_gravity_behavior() -> Behavior<StandardColumns> {
+    Behavior {
+        identifier: POINT_GRAVITY_BEHAVIOR_ID,
+        required_columns: POINT_GRAVITY_REQUIRED_COLUMNS,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(|columns, size| point_gravity_act(columns, size, false)),
+    }
+}
+
+pub const POINT_GRAVITY_INVERSE_SQUARE_BEHAVIOR_ID: &str = "point_gravity_inverse_square";
+pub fn point_gravity_inverse_square_behavior() -> Behavior<StandardColumns> {
+    Behavior {
+        identifier: POINT_GRAVITY_INVERSE_SQUARE_BEHAVIOR_ID,
+        required_columns: POINT_GRAVITY_REQUIRED_COLUMNS,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(|columns, size| point_gravity_act(columns, size, true)),
+    }
+}
+
+pub const RADIAL_ACCEL_BEHAVIOR_ID: &str = "radial_accel";
+pub fn radial_accel_behavior(origin: Vector3<f32>) -> Behavior<StandardColumns> {
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize, origin: Vector3<f32>) {
+        let pos_x = &columns.pos_x[0..size.div_ceil(N)];
+        let pos_y = &columns.pos_y[0..size.div_ceil(N)];
+        let pos_z = &columns.pos_z[0..size.div_ceil(N)];
+        let speed_accel = &columns.speed_accel[0..size.div_ceil(N)];
+
+        let motion_x = &mut columns.motion_x[0..size.div_ceil(N)];
+        let motion_y = &mut columns.motion_y[0..size.div_ceil(N)];
+        let motion_z = &mut columns.motion_z[0..size.div_ceil(N)];
+
+        for i in 0..size.div_ceil(N) {
+            let dx = (pos_x[i] - Simd::splat(origin.x as Pos)).cast::<f32>();
+            let dy = (pos_y[i] - Simd::splat(origin.y as Pos)).cast::<f32>();
+            let dz = (pos_z[i] - Simd::splat(origin.z as Pos)).cast::<f32>();
+
+            // Bullets right on the origin have no direction to be pushed in, so they are left alone
+            let (nx, ny, nz) = simd_normalize3(dx, dy, dz);
+
+            motion_x[i] += nx * speed_accel[i];
+            motion_y[i] += ny * speed_accel[i];
+            motion_z[i] += nz * speed_accel[i];
+        }
+    }
+
+    Behavior {
+        identifier: RADIAL_ACCEL_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::PosX
+            | StandardDataColumns::PosY
+            | StandardDataColumns::PosZ
+            | StandardDataColumns::MotionX
+            | StandardDataColumns::MotionY
+            | StandardDataColumns::MotionZ
+            | StandardDataColumns::SpeedAccel,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, origin)),
+    }
+}
+
+pub const ORBIT_BEHAVIOR_ID: &str = "orbit";
+pub fn orbit_behavior(center: Vector3<f32>, axis: UnitVector3<f32>) -> Behavior<StandardColumns> {
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize, center: Vector3<f32>, axis: Vector3<f32>) {
+        let pos_x = &columns.pos_x[0..size.div_ceil(N)];
+        let pos_y = &columns.pos_y[0..size.div_ceil(N)];
+        let pos_z = &columns.pos_z[0..size.div_ceil(N)];
+        let orbit_strength = &columns.orbit_strength[0..size.div_ceil(N)];
+
+        let motion_x = &mut columns.motion_x[0..size.div_ceil(N)];
+        let motion_y = &mut columns.motion_y[0..size.div_ceil(N)];
+        let motion_z = &mut columns.motion_z[0..size.div_ceil(N)];
+
+        let (ax, ay, az) = (
+            Simd::splat(axis.x),
+            Simd::splat(axis.y),
+            Simd::splat(axis.z),
+        );
+
+        for i in 0..size.div_ceil(N) {
+            let dx = (pos_x[i] - Simd::splat(center.x as Pos)).cast::<f32>();
+            let dy = (pos_y[i] - Simd::splat(center.y as Pos)).cast::<f32>();
+            let dz = (pos_z[i] - Simd::splat(center.z as Pos)).cast::<f32>();
+
+            // The axis crossed with the radius is both orthogonal to the radius and to the axis
+            let tx = ay * dz - az * dy;
+            let ty = az * dx - ax * dz;
+            let tz = ax * dy - ay * dx;
+
+            // Bullets on the axis have no radius to go around, so they are left alone
+            let (nx, ny, nz) = simd_normalize3(tx, ty, tz);
+
+            motion_x[i] += nx * orbit_strength[i];
+            motion_y[i] += ny * orbit_strength[i];
+            motion_z[i] += nz * orbit_strength[i];
+        }
+    }
+
+    Behavior {
+        identifier: ORBIT_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::PosX
+            | StandardDataColumns::PosY
+            | StandardDataColumns::PosZ
+            | StandardDataColumns::MotionX
+            | StandardDataColumns::MotionY
+            | StandardDataColumns::MotionZ
+            | StandardDataColumns::OrbitStrength,
+        order: FORCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, center, axis.into_inner())),
+    }
+}
+
+pub const FACE_MOTION_BEHAVIOR_ID: &str = "face_motion";
+pub fn face_motion_behavior() -> Behavior<StandardColumns> {
+    #[multiversion(targets = "simd")]
+    fn act(columns: &mut StandardColumns, size: usize) {
+        let motion_x = &columns.motion_x[0..size.div_ceil(N)];
+        let motion_y = &columns.motion_y[0..size.div_ceil(N)];
+        let motion_z = &columns.motion_z[0..size.div_ceil(N)];
+        let orientation = &mut columns.orientation[0..size];
+        let old_orientation = &mut columns.old_orientation[0..size];
+
+        old_orientation.copy_from_slice(orientation);
+
+        for i in 0..size.div_ceil(N) {
+            for j in 0..N {
+                let idx = i * N + j;
+                if idx >= size {
+                    break;
+                }
+
+                let motion = Vector3::new(motion_x[i][j], motion_y[i][j], motion_z[i][j]);
+                if motion.norm_squared() <= f32::EPSILON {
+                    continue;
+                }
+
+                // rotation_between gives up on opposite vectors, where any half turn will do
+                orientation[idx] = UnitQuaternion::rotation_between(&Vector3::z(), &motion)
+                    .unwrap_or_else(|| {
+                        UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f32::consts::PI)
+                    });
+            }
+        }
+    }
+
+    Behavior {
+        identifier: FACE_MOTION_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::Orientation
+            | StandardDataColumns::MotionX
+            | StandardDataColumns::MotionY
+            | StandardDataColumns::MotionZ,
+        order: POST_MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+pub const TRAIL_BEHAVIOR_ID: &str = "trail";
+pub fn trail_behavior() -> Behavior<StandardColumns> {
+    // Trails are a list per danmaku, so there is nothing to vectorize here
+    fn act(columns: &mut StandardColumns, size: usize) {
+        let pos_x = LogicalColumn::new(&columns.pos_x, size);
+        let pos_y = LogicalColumn::new(&columns.pos_y, size);
+        let pos_z = LogicalColumn::new(&columns.pos_z, size);
+
+        for idx in 0..size {
+            if columns.dead[idx] {
+                continue;
+            }
+
+            let max_len = columns.trail_length[idx] as usize;
+            if max_len == 0 {
+                continue;
+            }
+
+            let trail = &mut columns.trail[idx];
+            if trail.len() >= max_len {
+                trail.drain(..=trail.len() - max_len);
+            }
+
+            trail.push(Vector3::new(
+                pos_to_f32(pos_x[idx]),
+                pos_to_f32(pos_y[idx]),
+                pos_to_f32(pos_z[idx]),
+            ));
+        }
+    }
+
+    Behavior {
+        identifier: TRAIL_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::PosX
+            | StandardDataColumns::PosY
+            | StandardDataColumns::PosZ
+            | StandardDataColumns::Trail,
+        order: POST_MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+pub const CULL_BEHAVIOR_ID: &str = "cull";
+// Removes danmaku that leave the bounds of the handler context, without spawning their next stages
+pub fn cull_behavior() -> Behavior<StandardColumns> {
+    fn act(columns: &mut StandardColumns, size: usize) {
+        if columns.handler_context.bounds.is_none() {
+            return;
+        }
+
+        for idx in 0..size {
+            if columns.dead[idx] {
+                continue;
+            }
+
+            let pos = Vector3::new(
+                pos_to_f32(columns.pos_x[idx / N][idx % N]),
+                pos_to_f32(columns.pos_y[idx / N][idx % N]),
+                pos_to_f32(columns.pos_z[idx / N][idx % N]),
+            );
+            if !columns.handler_context.in_bounds(pos) {
+                columns.kill(idx);
+            }
+        }
+    }
+
+    Behavior {
+        identifier: CULL_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::PosX
+            | StandardDataColumns::PosY
+            | StandardDataColumns::PosZ,
+        order: POST_MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+pub const SPAWNER_BEHAVIOR_ID: &str = "spawner";
+// Spawns the next stages of a danmaku every interval ticks, while leaving the danmaku itself alive.
+// They still spawn one last time when it dies
+pub fn spawner_behavior(interval: i16) -> Result<Behavior<StandardColumns>, DanCoreError> {
+    fn act(columns: &mut StandardColumns, size: usize, interval: i16) {
+        for i in 0..size.div_ceil(N) {
+            if !columns.alive_chunks[i] {
+                continue;
+            }
+
+            let due = (columns.ticks_existed[i] - columns.last_spawn_tick[i])
+                .simd_ge(Simd::splat(interval))
+                .to_array();
+
+            for (j, &due) in due.iter().enumerate() {
+                let idx = i * N + j;
+                if idx >= size {
+                    break;
+                }
+                if !due || columns.dead[idx] || !columns.handler_context.lane_flagged(idx) {
+                    continue;
+                }
+
+                columns.last_spawn_tick[i][j] = columns.ticks_existed[i][j];
+
+                let mut spawns = columns.next_stage[idx].clone();
+                spawns
+                    .iter_mut()
+                    .for_each(|spawn| inherit_next_stage(columns, idx, spawn));
+                columns.queue_spawns(idx, spawns.into_iter().map(|d| (d, None)));
+            }
+        }
+    }
+
+    // Would spawn every tick, whatever the interval
+    if interval <= 0 {
+        return Err(DanCoreError::InvalidParameter("interval"));
+    }
+
+    Ok(Behavior {
+        identifier: SPAWNER_BEHAVIOR_ID,
+        required_columns: EnumSet::only(StandardDataColumns::SpawnInterval),
+        order: POST_MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, interval)),
+    })
+}
+
+// The fan turns around the up of the danmaku, with the part along forward taken out so the fan
+// always goes through forward. Danmaku facing straight up fan around their right instead
+fn fan_axis(forward: Vector3<f32>, orientation: &UnitQuaternion<f32>) -> UnitVector3<f32> {
+    let forward = UnitVector3::new_normalize(forward);
+    let perpendicular = |v: Vector3<f32>| v - forward.into_inner() * v.dot(&forward);
+
+    UnitVector3::try_new(perpendicular(orientation * Vector3::y()), 1e-4)
+        .or_else(|| UnitVector3::try_new(perpendicular(orientation * Vector3::x()), 1e-4))
+        .unwrap_or_else(Vector3::y_axis)
+}
+
+pub const SPLIT_BEHAVIOR_ID: &str = "split";
+// Once at_tick old, replaces danmaku with count copies of each of their next stages, fanned out
+// around their up with spread_radians between neighbors
+pub fn split_behavior(
+    at_tick: i16,
+    count: usize,
+    spread_radians: f32,
+) -> Behavior<StandardColumns> {
+    fn act(columns: &mut StandardColumns, size: usize, at_tick: i16, count: usize, spread: f32) {
+        for i in 0..size.div_ceil(N) {
+            if !columns.alive_chunks[i] {
+                continue;
+            }
+
+            let due = columns.ticks_existed[i]
+                .simd_eq(Simd::splat(at_tick))
+                .to_array();
+
+            for (j, &due) in due.iter().enumerate() {
+                let idx = i * N + j;
+                if idx >= size {
+                    break;
+                }
+                if !due || columns.dead[idx] || !columns.handler_context.lane_flagged(idx) {
+                    continue;
+                }
+
+                let forward = Vector3::new(
+                    columns.forward_x[i][j],
+                    columns.forward_y[i][j],
+                    columns.forward_z[i][j],
+                );
+                let axis = fan_axis(forward, &columns.orientation[idx]);
+                let stages = std::mem::take(&mut columns.next_stage[idx]);
+                let middle = count.saturating_sub(1) as f32 / 2.0;
+
+                for k in 0..count {
+                    let fan = UnitQuaternion::from_axis_angle(&axis, (k as f32 - middle) * spread);
+                    let child_forward = rotate_forward(&fan, forward);
+
+                    for stage in stages.iter() {
+                        let mut child = stage.clone();
+                        inherit_next_stage(columns, idx, &mut child);
+                        child
+                            .behavior_data
+                            .retain(|d| !matches!(d, StandardSpawnData::Forward(_)));
+                        child
+                            .behavior_data
+                            .push(StandardSpawnData::Forward(child_forward));
+                        columns.queue_spawns(idx, [(child, None)]);
+                    }
+                }
+
+                // Killing doesn't spawn next stages, so they only come out of the split
+                columns.kill(idx);
+            }
+        }
+    }
+
+    Behavior {
+        identifier: SPLIT_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::Forward | StandardDataColumns::Orientation,
+        order: POST_MOTION_ORDER,
+        required_behaviors: &[],
+        act: Box::new(move |columns, size| act(columns, size, at_tick, count, spread_radians)),
+    }
+}
+
+pub const APPEARANCE2_BEHAVIOR_ID: &str = "appearance2";
+pub fn appearance2_behavior() -> Behavior<StandardColumns> {
+    // Like appearance, but without a z scale, which is then rendered at the default scale
+    fn act(_columns: &mut StandardColumns, _size: usize) {}
+
+    Behavior {
+        identifier: APPEARANCE2_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::Appearance
+            | StandardDataColumns::MainColor
+            | StandardDataColumns::SecondaryColor
+            | StandardDataColumns::ScaleX
+            | StandardDataColumns::ScaleY,
+        order: APPEARANCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+pub const APPEARANCE_BEHAVIOR_ID: &str = "appearance";
+pub fn appearance_behavior() -> Behavior<StandardColumns> {
+    // Does nothing by itself, but allocates the columns needed to render danmaku
+    fn act(_columns: &mut StandardColumns, _size: usize) {}
+
+    Behavior {
+        identifier: APPEARANCE_BEHAVIOR_ID,
+        required_columns: StandardDataColumns::Appearance
+            | StandardDataColumns::MainColor
+            | StandardDataColumns::SecondaryColor
+            | StandardDataColumns::ScaleX
+            | StandardDataColumns::ScaleY
+            | StandardDataColumns::ScaleZ,
+        order: APPEARANCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+pub const DAMAGE_BEHAVIOR_ID: &str = "damage";
+pub fn damage_behavior() -> Behavior<StandardColumns> {
+    // Does nothing by itself, but allocates the column collect_damage sums up
+    fn act(_columns: &mut StandardColumns, _size: usize) {}
+
+    Behavior {
+        identifier: DAMAGE_BEHAVIOR_ID,
+        required_columns: EnumSet::only(StandardDataColumns::Damage),
+        order: APPEARANCE_ORDER,
+        required_behaviors: &[],
+        act: Box::new(act),
+    }
+}
+
+pub trait StandardTopHandlerExt {
+    fn register_standard_behaviors(&mut self);
+}
+
+impl StandardTopHandlerExt for TopDanmakuBehaviorsHandler<StandardColumns> {
+    fn register_standard_behaviors(&mut self) {
+        self.register_behavior(motion1_behavior());
+        self.register_behavior(gravity1_behavior());
+        self.register_behavior(acceleration1_behavior());
+        self.register_behavior(rotate_orientation_behavior());
+        self.register_behavior(rotate_forward_behavior());
+        self.register_behavior(motion3_behavior());
+        self.register_behavior(gravity3_behavior());
+        self.register_behavior(motion2_behavior());
+        self.register_behavior(gravity2_behavior());
+        self.register_behavior(appearance2_behavior());
+        self.register_behavior(acceleration3_behavior());
+        self.register_behavior(color_gradient_behavior());
+        self.register_behavior(point_gravity_behavior());
+        self.register_behavior(point_gravity_inverse_square_behavior());
+        self.register_behavior(face_motion_behavior());
+        self.register_behavior(delay_behavior());
+        self.register_behavior(trail_behavior());
+        self.register_behavior(accel_base_behavior());
+        self.register_behavior(clamp_accel_behavior());
+        self.register_behavior(cull_behavior());
+        self.register_behavior(appearance_behavior());
+        self.register_behavior(damage_behavior());
+        self.register_behavior(mandatory_end());
+        // Even danmaku without any other behaviors age and expire, like static markers, which
+        // are only rendered with appearance though
+        self.add_implicit_behavior(MANDATORY_END_BEHAVIOR_ID);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::danmaku::data::IMMORTAL_END_TIME;
+    use crate::danmaku::standard::test_util::{spawn, standard_handler};
+    use crate::form::Form;
+
+    #[test]
+    fn standard_behaviors_are_all_registered_with_their_columns() {
+        use StandardDataColumns as C;
+
+        let handler = standard_handler();
+        let registered = handler.registered_behaviors();
+
+        let mut expected = vec![
+            MOTION1_BEHAVIOR_ID,
+            GRAVITY1_BEHAVIOR_ID,
+            ACCELERATION1_BEHAVIOR_ID,
+            ROTATE_ORIENTATION_BEHAVIOR_ID,
+            ROTATE_FORWARD_BEHAVIOR_ID,
+            MOTION3_BEHAVIOR_ID,
+            GRAVITY3_BEHAVIOR_ID,
+            MOTION2_BEHAVIOR_ID,
+            GRAVITY2_BEHAVIOR_ID,
+            APPEARANCE2_BEHAVIOR_ID,
+            ACCELERATION3_BEHAVIOR_ID,
+            COLOR_GRADIENT_BEHAVIOR_ID,
+            POINT_GRAVITY_BEHAVIOR_ID,
+            POINT_GRAVITY_INVERSE_SQUARE_BEHAVIOR_ID,
+            FACE_MOTION_BEHAVIOR_ID,
+            DELAY_BEHAVIOR_ID,
+            TRAIL_BEHAVIOR_ID,
+            ACCEL_BASE_BEHAVIOR_ID,
+            CLAMP_ACCEL_BEHAVIOR_ID,
+            CULL_BEHAVIOR_ID,
+            APPEARANCE_BEHAVIOR_ID,
+            DAMAGE_BEHAVIOR_ID,
+            MANDATORY_END_BEHAVIOR_ID,
+        ];
+        expected.sort();
+        let ids: Vec<_> = registered.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, expected);
+
+        let (_, motion3_columns) = registered
+            .iter()
+            .find(|(id, _)| *id == MOTION3_BEHAVIOR_ID)
+            .unwrap();
+        assert_eq!(
+            *motion3_columns,
+            C::PosX | C::PosY | C::PosZ | C::MotionX | C::MotionY | C::MotionZ
+        );
+    }
+
+    #[test]
+    fn color_cycle_returns_to_the_spawn_color_after_a_period() {
+        let mut handler = standard_handler();
+        handler.register_behavior(color_cycle_behavior(8).unwrap());
+        let id = handler
+            .spawn_one(spawn(
+                100,
+                &[COLOR_CYCLE_BEHAVIOR_ID],
+                vec![StandardSpawnData::MainColor(0xFF0000)],
+            ))
+            .unwrap();
+
+        for _ in 0..4 {
+            handler.tick();
+        }
+        // Half a period is half way around the hue circle
+        assert_eq!(handler.get_danmaku(id).unwrap().main_color, 0x00FFFF);
+
+        for _ in 0..4 {
+            handler.tick();
+        }
+        assert_eq!(handler.get_danmaku(id).unwrap().main_color, 0xFF0000);
+    }
+
+    #[test]
+    fn color_cycle_rejects_periods_below_one() {
+        assert!(matches!(
+            color_cycle_behavior(0),
+            Err(DanCoreError::InvalidParameter("period_ticks"))
+        ));
+        assert!(color_cycle_behavior(-5).is_err());
+    }
+
+    #[test]
+    fn color_gradient_is_half_way_at_half_the_lifetime() {
+        let mut handler = standard_handler();
+        let data = vec![
+            StandardSpawnData::MainColor(0xFF0000),
+            StandardSpawnData::EndColor(0x0000FF),
+        ];
+        let id = handler
+            .spawn_one(spawn(20, &[COLOR_GRADIENT_BEHAVIOR_ID], data.clone()))
+            .unwrap();
+        let immortal = handler
+            .spawn_one(spawn(-1, &[COLOR_GRADIENT_BEHAVIOR_ID], data))
+            .unwrap();
+
+        for _ in 0..10 {
+            handler.tick();
+        }
+
+        // Red to blue goes the short way around the hue circle, through magenta
+        assert_eq!(handler.get_danmaku(id).unwrap().main_color, 0xFF00FF);
+        assert_eq!(handler.get_danmaku(immortal).unwrap().main_color, 0xFF0000);
+    }
+
+    #[test]
+    fn color_gradient_ends_right_away_for_end_time_zero() {
+        let mut handler = standard_handler();
+        let id = handler
+            .spawn_one(spawn(
+                0,
+                &[COLOR_GRADIENT_BEHAVIOR_ID],
+                vec![
+                    StandardSpawnData::MainColor(0xFF0000),
+                    StandardSpawnData::EndColor(0x0000FF),
+                ],
+            ))
+            .unwrap();
+
+        // Checked through the death callback, as the danmaku expires in the same tick
+        let colors = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let seen = colors.clone();
+        handler.set_on_death(move |_, view| seen.borrow_mut().push((view.id, view.main_color)));
+        handler.tick();
+
+        assert_eq!(*colors.borrow(), vec![(id, 0x0000FF)]);
+    }
+
+    #[test]
+    fn bounce_flips_motion_heading_into_a_wall() {
+        let mut handler = standard_handler();
+        handler.register_behavior(bounce_behavior(
+            Vector3::new(-10.0, -10.0, -10.0),
+            Vector3::new(10.0, 10.0, 10.0),
+        ));
+        let behaviors = [MOTION3_BEHAVIOR_ID, BOUNCE_BEHAVIOR_ID];
+        let into_wall = handler
+            .spawn_one(spawn(
+                100,
+                &behaviors,
+                vec![
+                    StandardSpawnData::PosX(9.5),
+                    StandardSpawnData::MotionX(1.0),
+                ],
+            ))
+            .unwrap();
+        // Already outside, but heading back in
+        let returning = handler
+            .spawn_one(spawn(
+                100,
+                &behaviors,
+                vec![
+                    StandardSpawnData::PosX(-15.0),
+                    StandardSpawnData::MotionX(1.0),
+                ],
+            ))
+            .unwrap();
+
+        handler.tick();
+
+        let view = handler.get_danmaku(into_wall).unwrap();
+        assert_eq!(view.position.x, 10.0);
+        assert_eq!(view.motion.x, -1.0);
+
+        let view = handler.get_danmaku(returning).unwrap();
+        assert_eq!(view.position.x, -10.0);
+        assert_eq!(view.motion.x, 1.0);
+
+        // Interpolating from the old position stays inside the bounds
+        let (min, max) = handler.scene_bounds(0.0).unwrap();
+        assert_eq!((min.x, max.x), (-10.0, 9.5));
+    }
+
+    #[test]
+    fn wrap_bounds_brings_danmaku_back_on_the_opposite_side() {
+        let mut handler = standard_handler();
+        handler.register_behavior(wrap_bounds_behavior(
+            Vector3::repeat(-10.0),
+            Vector3::repeat(10.0),
+        ));
+        let id = handler
+            .spawn_one(spawn(
+                100,
+                &[
+                    MOTION3_BEHAVIOR_ID,
+                    WRAP_BOUNDS_BEHAVIOR_ID,
+                    APPEARANCE_BEHAVIOR_ID,
+                ],
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::PosX(Pos::from(9.0_f32)),
+                    StandardSpawnData::MotionX(1.5),
+                ],
+            ))
+            .unwrap();
+
+        handler.tick();
+        let danmaku = handler.get_danmaku(id).unwrap();
+        assert!((danmaku.position.x - -9.5).abs() < 1e-5);
+        assert_eq!(danmaku.motion.x, 1.5);
+
+        // No streak across the field, as the old position wrapped as well
+        for partial_ticks in [0.0, 0.5, 1.0] {
+            let x = handler.render_data(partial_ticks)[0].model_mat.column(3).x;
+            assert!((x - -9.5).abs() < 1e-5);
+        }
+
+        handler.tick();
+        assert!((handler.get_danmaku(id).unwrap().position.x - -8.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn point_gravity_pulls_towards_the_source() {
+        let mut handler = standard_handler();
+        let data = |strength: f32| {
+            vec![
+                StandardSpawnData::PosX(3.0),
+                StandardSpawnData::PosY(4.0),
+                StandardSpawnData::GravitySource(Vector3::zeros()),
+                StandardSpawnData::GravityStrength(strength),
+            ]
+        };
+        let attracted = handler
+            .spawn_one(spawn(100, &[POINT_GRAVITY_BEHAVIOR_ID], data(1.0)))
+            .unwrap();
+        let repelled = handler
+            .spawn_one(spawn(100, &[POINT_GRAVITY_BEHAVIOR_ID], data(-1.0)))
+            .unwrap();
+
+        handler.tick();
+
+        let to_source = Vector3::new(-3.0, -4.0, 0.0).normalize();
+        let motion = handler.get_danmaku(attracted).unwrap().motion;
+        assert!((motion.normalize() - to_source).norm() < 1e-4);
+        assert!((motion.norm() - 1.0).abs() < 1e-3);
+
+        let motion = handler.get_danmaku(repelled).unwrap().motion;
+        assert!((motion.normalize() + to_source).norm() < 1e-4);
+    }
+
+    #[test]
+    fn inverse_square_point_gravity_weakens_with_distance() {
+        let mut handler = standard_handler();
+        let ids: Vec<i128> = [1.0, 2.0]
+            .into_iter()
+            .map(|x| {
+                handler
+                    .spawn_one(spawn(
+                        100,
+                        &[POINT_GRAVITY_INVERSE_SQUARE_BEHAVIOR_ID],
+                        vec![
+                            StandardSpawnData::PosX(x),
+                            StandardSpawnData::GravitySource(Vector3::zeros()),
+                            StandardSpawnData::GravityStrength(1.0),
+                        ],
+                    ))
+                    .unwrap()
+            })
+            .collect();
+
+        handler.tick();
+
+        let near = handler.get_danmaku(ids[0]).unwrap().motion.x;
+        let far = handler.get_danmaku(ids[1]).unwrap().motion.x;
+        assert!(near < 0.0 && far < 0.0);
+        assert!((near / far - 4.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn face_motion_points_z_along_the_motion() {
+        let mut handler = standard_handler();
+        let moving = handler
+            .spawn_one(spawn(
+                100,
+                &[FACE_MOTION_BEHAVIOR_ID],
+                vec![StandardSpawnData::MotionX(2.0)],
+            ))
+            .unwrap();
+        let turned = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), 1.0);
+        let resting = handler
+            .spawn_one(spawn(
+                100,
+                &[FACE_MOTION_BEHAVIOR_ID],
+                vec![StandardSpawnData::Orientation(turned)],
+            ))
+            .unwrap();
+
+        handler.tick();
+
+        let orientation = handler.get_danmaku(moving).unwrap().orientation;
+        assert!((orientation * Vector3::z() - Vector3::x()).norm() < 1e-5);
+        // Without motion there is no direction to face, so the orientation is kept
+        let orientation = handler.get_danmaku(resting).unwrap().orientation;
+        assert!(orientation.angle_to(&turned) < 1e-5);
+    }
+
+    fn spawn_around(
+        handler: &mut TopDanmakuBehaviorsHandler<StandardColumns>,
+        behavior: &'static str,
+        center: Vector3<f32>,
+        extra: StandardSpawnData,
+    ) -> Vec<(i128, Vector3<f32>)> {
+        [
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(-2.0, 1.0, 0.0),
+            Vector3::new(0.5, -3.0, 1.0),
+            Vector3::new(0.0, 0.0, -4.0),
+            Vector3::new(3.0, 3.0, 3.0),
+        ]
+        .into_iter()
+        .map(|offset| {
+            let pos = center + offset;
+            let id = handler
+                .spawn_one(spawn(
+                    100,
+                    &[behavior],
+                    vec![
+                        StandardSpawnData::PosX(Pos::from(pos.x)),
+                        StandardSpawnData::PosY(Pos::from(pos.y)),
+                        StandardSpawnData::PosZ(Pos::from(pos.z)),
+                        extra.clone(),
+                    ],
+                ))
+                .unwrap();
+            (id, offset)
+        })
+        .collect()
+    }
+
+    #[test]
+    fn radial_accel_pushes_away_from_the_origin() {
+        let origin = Vector3::new(1.0, 2.0, 0.0);
+        let mut handler = standard_handler();
+        handler.register_behavior(radial_accel_behavior(origin));
+        let danmaku = spawn_around(
+            &mut handler,
+            RADIAL_ACCEL_BEHAVIOR_ID,
+            origin,
+            StandardSpawnData::SpeedAccel(0.5),
+        );
+
+        handler.tick();
+
+        for (id, offset) in danmaku {
+            let motion = handler.get_danmaku(id).unwrap().motion;
+            assert!((motion - offset.normalize() * 0.5).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn orbit_accelerates_orthogonal_to_the_radius() {
+        let center = Vector3::new(-1.0, 0.0, 2.0);
+        let axis = Vector3::z_axis();
+        let mut handler = standard_handler();
+        handler.register_behavior(orbit_behavior(center, axis));
+        let danmaku = spawn_around(
+            &mut handler,
+            ORBIT_BEHAVIOR_ID,
+            center,
+            StandardSpawnData::OrbitStrength(0.25),
+        );
+
+        handler.tick();
+
+        for (id, offset) in danmaku {
+            let motion = handler.get_danmaku(id).unwrap().motion;
+            assert!(motion.dot(&offset).abs() < 1e-5);
+            assert!(motion.dot(&axis).abs() < 1e-5);
+            if offset.xy().norm() > 0.0 {
+                assert!((motion.norm() - 0.25).abs() < 1e-5);
+            } else {
+                // On the axis, there is no way around it
+                assert_eq!(motion, Vector3::zeros());
+            }
+        }
+    }
+
+    #[test]
+    fn clamp_accel_bounds_the_change_in_motion() {
+        let mut handler = standard_handler();
+        let id = handler
+            .spawn_one(spawn(
+                100,
+                &[
+                    MOTION3_BEHAVIOR_ID,
+                    ACCELERATION3_BEHAVIOR_ID,
+                    ACCEL_BASE_BEHAVIOR_ID,
+                    CLAMP_ACCEL_BEHAVIOR_ID,
+                ],
+                vec![
+                    StandardSpawnData::MotionX(1.0),
+                    StandardSpawnData::SpeedAccel(10.0),
+                    StandardSpawnData::Forward(Vector3::x_axis()),
+                    StandardSpawnData::MaxAccel(0.5),
+                ],
+            ))
+            .unwrap();
+
+        for tick in 1..=3 {
+            handler.tick();
+            let motion = handler.get_danmaku(id).unwrap().motion;
+            assert!((motion - Vector3::new(1.0 + 0.5 * tick as f32, 0.0, 0.0)).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn clamp_accel_is_rejected_without_accel_base() {
+        let mut handler = standard_handler();
+        let spawned = handler.spawn_one(spawn(
+            100,
+            &[
+                MOTION3_BEHAVIOR_ID,
+                ACCELERATION3_BEHAVIOR_ID,
+                CLAMP_ACCEL_BEHAVIOR_ID,
+            ],
+            vec![
+                StandardSpawnData::SpeedAccel(10.0),
+                StandardSpawnData::MaxAccel(0.5),
+            ],
+        ));
+
+        assert_eq!(spawned, None);
+        assert_eq!(handler.live_count(), 0);
+        assert_eq!(
+            handler.reserve(&[ACCELERATION3_BEHAVIOR_ID, CLAMP_ACCEL_BEHAVIOR_ID], 8),
+            Err(DanCoreError::MissingBehavior(
+                CLAMP_ACCEL_BEHAVIOR_ID,
+                ACCEL_BASE_BEHAVIOR_ID
+            ))
+        );
+    }
+
+    #[test]
+    fn curve_turns_forward_by_the_total_angle() {
+        let curve = curve_behavior(0.1, Vector3::z_axis());
+        // Not a multiple of N, with a turn rate of its own for each
+        let size = 2 * N + 1;
+        let mut columns = StandardColumns::new(size, curve.required_columns);
+        for i in 0..size {
+            columns.add_danmaku_at_idx(
+                i,
+                spawn(
+                    100,
+                    &[],
+                    vec![
+                        StandardSpawnData::Forward(Vector3::x_axis()),
+                        StandardSpawnData::TurnRate(i as f32 * 0.01),
+                    ],
+                ),
+                i as i128,
+            );
+        }
+
+        for _ in 0..10 {
+            (curve.act)(&mut columns, size);
+        }
+
+        for i in 0..size {
+            let angle = 10.0 * (0.1 + i as f32 * 0.01);
+            let forward = Vector3::new(
+                columns.forward_x[i / N][i % N],
+                columns.forward_y[i / N][i % N],
+                columns.forward_z[i / N][i % N],
+            );
+            assert!((forward - Vector3::new(angle.cos(), angle.sin(), 0.0)).norm() < 1e-4);
+        }
+    }
+
+    // Not a multiple of N, so the last chunk has padding lanes
+    fn partial_chunk_columns(
+        behavior: &Behavior<StandardColumns>,
+        data: impl Fn(usize) -> Vec<StandardSpawnData>,
+    ) -> (StandardColumns, usize) {
+        let size = 2 * N + 1;
+        let mut columns = StandardColumns::new(size, behavior.required_columns);
+        for i in 0..size {
+            columns.add_danmaku_at_idx(i, spawn(100, &[], data(i)), i as i128);
+        }
+        (columns, size)
+    }
+
+    #[test]
+    fn motion1_moves_a_partly_used_last_chunk() {
+        let motion1 = motion1_behavior();
+        let (mut columns, size) = partial_chunk_columns(&motion1, |i| {
+            vec![
+                StandardSpawnData::PosZ(Pos::from(i as f32)),
+                StandardSpawnData::MotionZ(i as f32 * 0.5),
+            ]
+        });
+
+        (motion1.act)(&mut columns, size);
+        (motion1.act)(&mut columns, size);
+
+        for i in 0..size {
+            assert_eq!(pos_to_f32(columns.pos_z[i / N][i % N]), 2.0 * i as f32);
+            assert_eq!(pos_to_f32(columns.old_pos_z[i / N][i % N]), 1.5 * i as f32);
+        }
+    }
+
+    #[test]
+    fn gravity1_pulls_a_partly_used_last_chunk() {
+        let gravity1 = gravity1_behavior();
+        let (mut columns, size) = partial_chunk_columns(&gravity1, |i| {
+            vec![StandardSpawnData::GravityY(i as f32 * 0.5)]
+        });
+        for ticks in columns.ticks_existed.iter_mut() {
+            *ticks = Simd::splat(2);
+        }
+
+        (gravity1.act)(&mut columns, size);
+
+        for i in 0..size {
+            assert_eq!(columns.motion_y[i / N][i % N], i as f32);
+        }
+    }
+
+    #[test]
+    fn rotate_forward_turns_a_partly_used_last_chunk() {
+        let rotate_forward = rotate_forward_behavior();
+        let (mut columns, size) = partial_chunk_columns(&rotate_forward, |i| {
+            vec![
+                StandardSpawnData::Forward(Vector3::x_axis()),
+                StandardSpawnData::Rotation(UnitQuaternion::from_axis_angle(
+                    &Vector3::z_axis(),
+                    i as f32 * 0.1,
+                )),
+            ]
+        });
+
+        (rotate_forward.act)(&mut columns, size);
+
+        for i in 0..size {
+            let angle = i as f32 * 0.1;
+            let forward = Vector3::new(
+                columns.forward_x[i / N][i % N],
+                columns.forward_y[i / N][i % N],
+                columns.forward_z[i / N][i % N],
+            );
+            assert!((forward - Vector3::new(angle.cos(), angle.sin(), 0.0)).norm() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn delayed_danmaku_hold_still_until_they_activate() {
+        let mut handler = standard_handler();
+        handler.register_behavior(curve_behavior(
+            std::f32::consts::FRAC_PI_2,
+            Vector3::z_axis(),
+        ));
+        let id = handler
+            .spawn_one(spawn(
+                100,
+                &[
+                    DELAY_BEHAVIOR_ID,
+                    MOTION3_BEHAVIOR_ID,
+                    GRAVITY3_BEHAVIOR_ID,
+                    ACCELERATION3_BEHAVIOR_ID,
+                    CURVE_BEHAVIOR_ID,
+                ],
+                vec![
+                    StandardSpawnData::ActivationTick(10),
+                    StandardSpawnData::PosX(Pos::from(3.0_f32)),
+                    StandardSpawnData::MotionX(1.0),
+                    StandardSpawnData::GravityY(-0.1),
+                    StandardSpawnData::SpeedAccel(0.5),
+                    StandardSpawnData::Forward(Vector3::x_axis()),
+                ],
+            ))
+            .unwrap();
+
+        for _ in 0..10 {
+            handler.tick();
+            let danmaku = handler.get_danmaku(id).unwrap();
+            assert_eq!(danmaku.position, Vector3::new(3.0, 0.0, 0.0));
+            assert_eq!(danmaku.motion, Vector3::new(1.0, 0.0, 0.0));
+        }
+
+        handler.tick();
+
+        // Accelerates along the forward it spawned with, before the curve turns it, as if the
+        // forces had only started now. Gravity still grows with the age though
+        let danmaku = handler.get_danmaku(id).unwrap();
+        assert!((danmaku.motion - Vector3::new(1.5, -1.0, 0.0)).norm() < 1e-5);
+        assert!((danmaku.position - Vector3::new(4.5, -1.0, 0.0)).norm() < 1e-5);
+    }
+
+    #[test]
+    fn cull_follows_the_bounds_of_the_context() {
+        let mut handler = standard_handler();
+        let at = |handler: &mut TopDanmakuBehaviorsHandler<StandardColumns>, x: f32| {
+            handler
+                .spawn_one(spawn(
+                    100,
+                    &[MOTION3_BEHAVIOR_ID, CULL_BEHAVIOR_ID],
+                    vec![StandardSpawnData::PosX(Pos::from(x))],
+                ))
+                .unwrap()
+        };
+        let near = at(&mut handler, 0.5);
+        let far = at(&mut handler, 5.0);
+
+        // Nothing is culled without bounds
+        handler.tick();
+        assert_eq!(handler.live_count(), 2);
+
+        handler.context_mut().bounds = Some((Vector3::repeat(-10.0), Vector3::repeat(10.0)));
+        handler.tick();
+        assert_eq!(handler.live_count(), 2);
+
+        handler.context_mut().bounds = Some((Vector3::repeat(-1.0), Vector3::repeat(1.0)));
+        handler.tick();
+        assert!(handler.get_danmaku(near).is_some());
+        assert!(handler.get_danmaku(far).is_none());
+    }
+
+    #[test]
+    fn stationary_spawner_emits_one_child_per_interval() {
+        let mut handler = standard_handler();
+        handler.register_behavior(spawner_behavior(5).unwrap());
+
+        let mut emitter = spawn(
+            1000,
+            &[MOTION3_BEHAVIOR_ID, SPAWNER_BEHAVIOR_ID],
+            vec![StandardSpawnData::PosX(Pos::from(5.0_f32))],
+        );
+        emitter.next_stage.push(spawn(
+            1000,
+            &[MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID],
+            vec![
+                StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                },
+                StandardSpawnData::MotionY(1.0),
+            ],
+        ));
+        let emitter = handler.spawn_one(emitter).unwrap();
+
+        for tick in 1..=30 {
+            handler.tick();
+            let children = handler.live_count() - 1;
+            assert_eq!(children, (tick - 1) / 5, "after {} ticks", tick);
+        }
+
+        let emitter = handler.get_danmaku(emitter).unwrap();
+        assert_eq!(emitter.position, Vector3::new(5.0, 0.0, 0.0));
+
+        // Each child started at the emitter, an interval after the one before it
+        let mut children: Vec<Vector3<f32>> = handler
+            .render_data(1.0)
+            .iter()
+            .map(|data| data.model_mat.column(3).xyz())
+            .collect();
+        children.sort_by(|a, b| a.y.total_cmp(&b.y));
+        assert_eq!(children.len(), 5);
+        for (i, child) in children.iter().enumerate() {
+            assert_eq!(child.x, 5.0);
+            assert_eq!(child.y - children[0].y, 5.0 * i as f32);
+        }
+    }
+
+    #[test]
+    fn spawner_rejects_intervals_below_one() {
+        assert!(matches!(
+            spawner_behavior(0),
+            Err(DanCoreError::InvalidParameter("interval"))
+        ));
+        assert!(spawner_behavior(-5).is_err());
+        assert!(spawner_behavior(1).is_ok());
+    }
+
+    #[test]
+    fn speed_to_color_shifts_towards_fast_as_danmaku_speed_up() {
+        let mut handler = standard_handler();
+        handler.register_behavior(speed_to_color_behavior(
+            ColorHex(0x0000FF),
+            ColorHex(0xFF0000),
+            2.0,
+        ));
+        let id = handler
+            .spawn_one(spawn(
+                100,
+                &[ACCELERATION3_BEHAVIOR_ID, SPEED_TO_COLOR_BEHAVIOR_ID],
+                vec![
+                    StandardSpawnData::MainColor(0x0000FF),
+                    StandardSpawnData::SpeedAccel(0.5),
+                    StandardSpawnData::Forward(Vector3::x_axis()),
+                ],
+            ))
+            .unwrap();
+
+        // The hue goes from blue at 240 degrees up through magenta to red at 360
+        let mut last_hue = 240.0;
+        for _ in 0..3 {
+            handler.tick();
+            let color = ColorHex(handler.get_danmaku(id).unwrap().main_color);
+            let hue = color.to_rgb().to_hsv().h;
+            assert!(hue > last_hue);
+            last_hue = hue;
+        }
+
+        // Reaches max_speed, and stays at the fast color as it keeps speeding up
+        for _ in 0..3 {
+            handler.tick();
+            assert_eq!(handler.get_danmaku(id).unwrap().main_color, 0xFF0000);
+        }
+    }
+
+    #[test]
+    fn animated_property_reaches_to_at_end_time() {
+        let mut handler = standard_handler();
+        handler.register_behavior(animate_property_behavior("glow", 2.0, 12.0));
+        handler
+            .spawn_one(spawn(
+                10,
+                &[APPEARANCE_BEHAVIOR_ID, ANIMATE_PROPERTY_BEHAVIOR_ID],
+                vec![StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                }],
+            ))
+            .unwrap();
+        let glow = |handler: &mut TopDanmakuBehaviorsHandler<StandardColumns>| {
+            handler.render_data(1.0)[0].render_properties["glow"]
+        };
+
+        for _ in 0..5 {
+            handler.tick();
+        }
+        assert_eq!(glow(&mut handler), 7.0);
+
+        for _ in 0..5 {
+            handler.tick();
+        }
+        assert_eq!(glow(&mut handler), 12.0);
+    }
+
+    #[test]
+    fn animated_property_copes_with_the_oldest_danmaku() {
+        let animate = animate_property_behavior("glow", 2.0, 12.0);
+        let mut columns = StandardColumns::new(2, animate.required_columns);
+        for (idx, end_time) in [(0, IMMORTAL_END_TIME), (1, i16::MAX)] {
+            let appearance = StandardSpawnData::Appearance {
+                form: &Form::SPHERE,
+            };
+            let mut d = spawn(end_time, &[], vec![appearance]);
+            d.render_properties.insert("glow", 5.0);
+            columns.add_danmaku_at_idx(idx, d, idx as i128);
+            columns.ticks_existed[idx / N][idx % N] = i16::MAX;
+        }
+
+        (animate.act)(&mut columns, 2);
+
+        // Immortal danmaku keep their value, instead of jumping straight to the end
+        assert_eq!(columns.render_properties[0]["glow"], 5.0);
+        assert_eq!(columns.render_properties[1]["glow"], 12.0);
+    }
+
+    #[test]
+    fn scale_over_lifetime_reaches_to_at_end_time() {
+        let mut handler = standard_handler();
+        handler.register_behavior(scale_over_lifetime_behavior(1.0, 3.0));
+        let scaling = |end_time| {
+            spawn(
+                end_time,
+                &[APPEARANCE_BEHAVIOR_ID, SCALE_OVER_LIFETIME_BEHAVIOR_ID],
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::SizeX(1.5),
+                    StandardSpawnData::SizeY(1.5),
+                    StandardSpawnData::SizeZ(1.5),
+                ],
+            )
+        };
+        let id = handler.spawn_one(scaling(10)).unwrap();
+        let immortal = handler.spawn_one(scaling(IMMORTAL_END_TIME)).unwrap();
+        // Rendering at the start of the tick shows the scale of the tick before
+        let old_scale = |handler: &mut TopDanmakuBehaviorsHandler<StandardColumns>| {
+            let rendered = handler.render_data_with_ids(0.0);
+            let (_, data) = rendered
+                .iter()
+                .find(|(rendered, _)| *rendered == id)
+                .unwrap();
+            data.model_mat.column(0).norm()
+        };
+
+        for _ in 0..5 {
+            handler.tick();
+        }
+        assert_eq!(handler.get_danmaku(id).unwrap().scale, Vector3::repeat(2.0));
+        assert!((old_scale(&mut handler) - 1.8).abs() < 1e-5);
+
+        for _ in 0..5 {
+            handler.tick();
+        }
+        assert_eq!(handler.get_danmaku(id).unwrap().scale, Vector3::repeat(3.0));
+        assert!((old_scale(&mut handler) - 2.8).abs() < 1e-5);
+
+        // Immortal danmaku have no lifetime, so they keep the scale they spawned with
+        let immortal = handler.get_danmaku(immortal).unwrap();
+        assert_eq!(immortal.scale, Vector3::repeat(1.5));
+    }
+
+    #[test]
+    fn kill_if_property_kills_once_the_property_passes_the_threshold() {
+        let mut handler = standard_handler();
+        handler.register_behavior(kill_if_property_behavior("glow", 1.0));
+        let with_glow = |glow: f32| {
+            let mut d = spawn(
+                100,
+                &[APPEARANCE_BEHAVIOR_ID, KILL_IF_PROPERTY_BEHAVIOR_ID],
+                vec![StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                }],
+            );
+            d.render_properties.insert("glow", glow);
+            d
+        };
+        let bright = handler.spawn_one(with_glow(2.0)).unwrap();
+        let dim = handler.spawn_one(with_glow(0.5)).unwrap();
+        let at_threshold = handler.spawn_one(with_glow(1.0)).unwrap();
+
+        handler.tick();
+        assert!(handler.get_danmaku(bright).is_none());
+        assert!(handler.get_danmaku(dim).is_some());
+        assert!(handler.get_danmaku(at_threshold).is_some());
+    }
+
+    #[test]
+    fn kill_if_property_follows_an_animated_property() {
+        let mut handler = standard_handler();
+        handler.register_behavior(animate_property_behavior("glow", 0.0, 10.0));
+        handler.register_behavior(kill_if_property_behavior("glow", 4.5));
+        let id = handler
+            .spawn_one(spawn(
+                10,
+                &[
+                    APPEARANCE_BEHAVIOR_ID,
+                    ANIMATE_PROPERTY_BEHAVIOR_ID,
+                    KILL_IF_PROPERTY_BEHAVIOR_ID,
+                ],
+                vec![StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                }],
+            ))
+            .unwrap();
+
+        // The glow goes up by 1 each tick, passing 4.5 on the fifth
+        for _ in 0..4 {
+            handler.tick();
+            assert!(handler.get_danmaku(id).is_some());
+        }
+        handler.tick();
+        assert!(handler.get_danmaku(id).is_none());
+    }
+
+    #[test]
+    fn trail_follows_the_path_of_the_danmaku() {
+        let mut handler = standard_handler();
+        handler
+            .spawn_one(spawn(
+                100,
+                &[
+                    MOTION3_BEHAVIOR_ID,
+                    TRAIL_BEHAVIOR_ID,
+                    APPEARANCE_BEHAVIOR_ID,
+                ],
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::MotionX(1.0),
+                    StandardSpawnData::MotionY(0.5),
+                    StandardSpawnData::TrailLength(3),
+                ],
+            ))
+            .unwrap();
+
+        handler.tick();
+        handler.tick();
+        assert_eq!(
+            handler.render_data(1.0)[0].trail,
+            &[Vector3::new(1.0, 0.5, 0.0), Vector3::new(2.0, 1.0, 0.0)]
+        );
+
+        for _ in 0..3 {
+            handler.tick();
+        }
+        // Only the last TrailLength positions are kept, oldest first
+        assert_eq!(
+            handler.render_data(1.0)[0].trail,
+            &[
+                Vector3::new(3.0, 1.5, 0.0),
+                Vector3::new(4.0, 2.0, 0.0),
+                Vector3::new(5.0, 2.5, 0.0),
+            ]
+        );
+    }
+
+    fn next_stage_motion_x(inherit: Option<InheritOp>) -> f32 {
+        let behaviors = [MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID];
+        let with_motion = |end_time, motion_x| {
+            spawn(
+                end_time,
+                &behaviors,
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::MotionX(motion_x),
+                ],
+            )
+        };
+
+        let mut handler = standard_handler();
+        let mut parent = with_motion(1, 2.0);
+        if let Some(op) = inherit {
+            parent
+                .next_stage_inherit
+                .insert(StandardDataColumns::MotionX, op);
+        }
+        parent.next_stage.push(with_motion(100, 3.0));
+        handler.spawn_one(parent).unwrap();
+
+        handler.tick();
+        handler.tick();
+        let rendered = handler.render_data_with_ids(1.0);
+        assert_eq!(rendered.len(), 1);
+        let next_id = rendered[0].0;
+        handler.get_danmaku(next_id).unwrap().motion.x
+    }
+
+    #[test]
+    fn next_stages_combine_motion_by_the_inherit_op() {
+        assert_eq!(next_stage_motion_x(None), 5.0);
+        assert_eq!(next_stage_motion_x(Some(InheritOp::Add)), 5.0);
+        assert_eq!(next_stage_motion_x(Some(InheritOp::Overwrite)), 2.0);
+        assert_eq!(next_stage_motion_x(Some(InheritOp::Ignore)), 3.0);
+    }
+
+    #[test]
+    fn anchored_next_stages_ignore_where_the_parent_died() {
+        let behaviors = [MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID];
+        let appearance = StandardSpawnData::Appearance {
+            form: &Form::SPHERE,
+        };
+        let mut handler = standard_handler();
+        for (motion_x, motion_y) in [(10.0, 0.0), (0.0, -7.0)] {
+            let mut parent = spawn(
+                3,
+                &behaviors,
+                vec![
+                    appearance.clone(),
+                    StandardSpawnData::MotionX(motion_x),
+                    StandardSpawnData::MotionY(motion_y),
+                ],
+            );
+            let mut next = spawn(
+                100,
+                &behaviors,
+                vec![
+                    appearance.clone(),
+                    StandardSpawnData::PosX(Pos::from(1.0_f32)),
+                ],
+            );
+            next.anchor = Some(Vector3::new(20.0, 5.0, 0.0));
+            // Nothing added from the parent, so the next stages stand still
+            parent.next_stage_add_data = EnumSet::empty();
+            parent.next_stage.push(next);
+            handler.spawn_one(parent).unwrap();
+        }
+
+        for _ in 0..6 {
+            handler.tick();
+        }
+
+        let rendered = handler.render_data(1.0);
+        assert_eq!(rendered.len(), 2);
+        for data in rendered {
+            assert_eq!(data.model_mat.column(3).xyz(), Vector3::new(21.0, 5.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn split_fans_children_around_the_up_of_the_danmaku() {
+        let spread = 0.3;
+        let mut handler = standard_handler();
+        handler.register_behavior(split_behavior(5, 3, spread));
+
+        let mut parent = spawn(
+            100,
+            &[SPLIT_BEHAVIOR_ID],
+            vec![
+                StandardSpawnData::Forward(Vector3::x_axis()),
+                // Up is now z, so the fan is in the xy plane
+                StandardSpawnData::Orientation(UnitQuaternion::from_axis_angle(
+                    &Vector3::x_axis(),
+                    std::f32::consts::FRAC_PI_2,
+                )),
+            ],
+        );
+        parent.next_stage.push(spawn(
+            100,
+            &[
+                ACCELERATION3_BEHAVIOR_ID,
+                MOTION3_BEHAVIOR_ID,
+                APPEARANCE_BEHAVIOR_ID,
+            ],
+            vec![
+                StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                },
+                StandardSpawnData::SpeedAccel(1.0),
+            ],
+        ));
+        let parent_id = handler.spawn_one(parent).unwrap();
+
+        for _ in 0..5 {
+            handler.tick();
+        }
+        // Splits on the tick it starts 5 ticks old
+        assert_eq!(handler.get_danmaku(parent_id).unwrap().ticks_existed, 5);
+        handler.tick();
+        assert!(handler.get_danmaku(parent_id).is_none());
+        assert_eq!(handler.live_count(), 3);
+
+        // Accelerating once along their forward makes the motion the forward
+        handler.tick();
+        let ids: Vec<i128> = handler
+            .render_data_with_ids(1.0)
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        let mut forwards: Vec<Vector3<f32>> = ids
+            .iter()
+            .map(|id| handler.get_danmaku(*id).unwrap().motion)
+            .collect();
+        forwards.sort_by(|a, b| a.y.total_cmp(&b.y));
+
+        let expected =
+            [-spread, 0.0, spread].map(|angle| Vector3::new(angle.cos(), angle.sin(), 0.0));
+        for (forward, expected) in forwards.iter().zip(expected) {
+            assert!(
+                (forward - expected).norm() < 1e-5,
+                "{} != {}",
+                forward,
+                expected
+            );
+        }
+        assert!((forwards[0].angle(&forwards[1]) - spread).abs() < 1e-5);
+        assert!((forwards[1].angle(&forwards[2]) - spread).abs() < 1e-5);
+    }
+
+    #[test]
+    fn planar_danmaku_render_with_a_unit_z_scale() {
+        let mut handler = standard_handler();
+        let behaviors = [MOTION2_BEHAVIOR_ID, APPEARANCE2_BEHAVIOR_ID];
+        handler
+            .spawn_one(spawn(
+                100,
+                &behaviors,
+                vec![
+                    StandardSpawnData::Appearance {
+                        form: &Form::SPHERE,
+                    },
+                    StandardSpawnData::PosX(Pos::from(1.0_f32)),
+                    StandardSpawnData::MotionY(1.0),
+                    StandardSpawnData::SizeX(2.0),
+                    StandardSpawnData::SizeY(3.0),
+                ],
+            ))
+            .unwrap();
+        handler.tick();
+
+        let columns = handler.required_columns_for(&behaviors).unwrap();
+        assert!(!columns.contains(StandardDataColumns::PosZ));
+        assert!(!columns.contains(StandardDataColumns::ScaleZ));
+
+        let model_mat = handler.render_data(1.0)[0].model_mat;
+        assert_eq!(model_mat.column(0).xyz(), Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(model_mat.column(1).xyz(), Vector3::new(0.0, 3.0, 0.0));
+        assert_eq!(model_mat.column(2).xyz(), Vector3::new(0.0, 0.0, 1.0));
+        assert_eq!(model_mat.column(3).xyz(), Vector3::new(1.0, 1.0, 0.0));
+        assert_ne!(model_mat.determinant(), 0.0);
+    }
+
+    #[test]
+    fn next_stages_carry_the_velocity_of_the_parent() {
+        let behaviors = [MOTION3_BEHAVIOR_ID, APPEARANCE_BEHAVIOR_ID];
+        let appearance = StandardSpawnData::Appearance {
+            form: &Form::SPHERE,
+        };
+        let mut handler = standard_handler();
+        let mut parent = spawn(
+            2,
+            &behaviors,
+            vec![
+                appearance.clone(),
+                StandardSpawnData::MotionX(1.0),
+                StandardSpawnData::MotionY(2.0),
+                StandardSpawnData::MotionZ(-3.0),
+            ],
+        );
+        // No motion or position of its own, so all of it comes from the parent
+        parent
+            .next_stage
+            .push(spawn(100, &behaviors, vec![appearance]));
+        let parent_id = handler.spawn_one(parent).unwrap();
+
+        let mut died_at = None;
+        while let Some(parent) = handler.get_danmaku(parent_id) {
+            died_at = Some(parent.position);
+            handler.tick();
+        }
+        let rendered = handler.render_data_with_ids(1.0);
+        assert_eq!(rendered.len(), 1);
+        let next_id = rendered[0].0;
+        let next = handler.get_danmaku(next_id).unwrap();
+
+        assert_eq!(next.motion, Vector3::new(1.0, 2.0, -3.0));
+        // Picks up where the parent moved to on the tick it died, instead of at the origin
+        assert_eq!(next.position, died_at.unwrap() + next.motion);
+        assert_eq!(next.ticks_existed, 0);
+    }
+
+    #[test]
+    fn conditional_motion_only_moves_flagged_danmaku() {
+        let mut handler = standard_handler();
+        let conditional = conditional_behavior(
+            &handler,
+            "held_motion3",
+            StandardDataColumns::Damage,
+            MOTION3_BEHAVIOR_ID,
+        )
+        .unwrap();
+        handler.register_behavior(conditional);
+
+        // Not a multiple of N, so the last chunk is only partly used
+        let ids: Vec<i128> = (0..2 * N + 1)
+            .map(|i| {
+                let flag = if i % 2 == 0 { 1.0 } else { 0.0 };
+                handler
+                    .spawn_one(spawn(
+                        100,
+                        &["held_motion3"],
+                        vec![
+                            StandardSpawnData::PosX(Pos::from(i as f32)),
+                            StandardSpawnData::MotionX(1.0),
+                            StandardSpawnData::Damage(flag),
+                        ],
+                    ))
+                    .unwrap()
+            })
+            .collect();
+
+        for _ in 0..3 {
+            handler.tick();
+        }
+
+        for (i, id) in ids.iter().enumerate() {
+            let moved = if i % 2 == 0 { 3.0 } else { 0.0 };
+            assert_eq!(
+                handler.get_danmaku(*id).unwrap().position.x,
+                i as f32 + moved
+            );
+        }
+    }
+
+    #[test]
+    fn conditional_split_leaves_unflagged_danmaku_whole() {
+        let mut handler = standard_handler();
+        handler.register_behavior(split_behavior(2, 3, 0.3));
+        let conditional = conditional_behavior(
+            &handler,
+            "flagged_split",
+            StandardDataColumns::Damage,
+            SPLIT_BEHAVIOR_ID,
+        )
+        .unwrap();
+        handler.register_behavior(conditional);
+
+        let ids: Vec<i128> = (0..2 * N + 1)
+            .map(|i| {
+                let flag = if i % 2 == 0 { 1.0 } else { 0.0 };
+                let mut parent = spawn(
+                    100,
+                    &["flagged_split"],
+                    vec![
+                        StandardSpawnData::Forward(Vector3::x_axis()),
+                        StandardSpawnData::Damage(flag),
+                    ],
+                );
+                parent.next_stage.push(spawn(100, &[], vec![]));
+                handler.spawn_one(parent).unwrap()
+            })
+            .collect();
+
+        for _ in 0..5 {
+            handler.tick();
+        }
+
+        let flagged = ids.len().div_ceil(2);
+        for (i, id) in ids.iter().enumerate() {
+            assert_eq!(handler.get_danmaku(*id).is_some(), i % 2 == 1);
+        }
+        assert_eq!(handler.live_count(), ids.len() - flagged + 3 * flagged);
+    }
+
+    #[test]
+    fn conditional_needs_a_registered_lane_masked_inner() {
+        let handler = standard_handler();
+
+        let unknown = conditional_behavior(
+            &handler,
+            "conditional",
+            StandardDataColumns::Damage,
+            SPLIT_BEHAVIOR_ID,
+        );
+        assert_eq!(
+            unknown.err(),
+            Some(DanCoreError::UnknownBehavior(SPLIT_BEHAVIOR_ID.to_string()))
+        );
+
+        let unmasked = conditional_behavior(
+            &handler,
+            "conditional",
+            StandardDataColumns::Damage,
+            COLOR_GRADIENT_BEHAVIOR_ID,
+        );
+        assert_eq!(
+            unmasked.err(),
+            Some(DanCoreError::InvalidParameter("inner"))
+        );
+
+        let predicate = conditional_behavior(
+            &handler,
+            "conditional",
+            StandardDataColumns::Forward,
+            MOTION3_BEHAVIOR_ID,
+        );
+        assert_eq!(
+            predicate.err(),
+            Some(DanCoreError::InvalidParameter("predicate"))
+        );
     }
 }
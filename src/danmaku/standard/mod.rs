@@ -1,29 +1,60 @@
 use std::collections::HashMap;
-use std::simd::{Simd, SimdElement};
+use std::simd::{Mask, Select, Simd, SimdElement};
 
 use enumset::{EnumSet, EnumSetType};
 use nalgebra::{Matrix4, UnitQuaternion, UnitVector3, Vector3};
 
-use crate::color::ColorHex;
+use crate::color::{ColorHex, ColorLerpMode};
 use crate::danmaku::{
-    data::{DanmakuSpawnData, RenderData},
+    data::{DanmakuSpawnData, DanmakuView, HandlerContext, InheritOp, PendingSpawn, RenderData},
     DanmakuData, N,
 };
 use crate::form::Form;
+use crate::simd_math::{simd_len3, simd_lerp_rgb};
 
 pub mod behaviors;
+pub mod patterns;
+pub mod sim;
+#[cfg(test)]
+pub(crate) mod test_util;
+
+// Positions can be stored as f64 for big play fields, where f32 positions visibly snap to a grid.
+// Everything else, motion included, stays f32, and only the positions themselves are f64
+#[cfg(not(feature = "f64_positions"))]
+pub type Pos = f32;
+#[cfg(feature = "f64_positions")]
+pub type Pos = f64;
+
+// Without f64_positions this converts f32 to f32, which clippy would complain about if cast inline
+#[allow(clippy::unnecessary_cast)]
+pub fn pos_to_f32(pos: Pos) -> f32 {
+    pos as f32
+}
+
+// Anything outside of 0 to 1 would extrapolate past the last or current tick, and a NaN would end
+// up in every matrix. A NaN is treated as the current tick
+fn sanitize_partial_ticks(partial_ticks: f32) -> f32 {
+    if partial_ticks.is_nan() {
+        1.0
+    } else {
+        partial_ticks.clamp(0.0, 1.0)
+    }
+}
 
+#[derive(Clone)]
 pub struct StandardColumns {
     pub required_columns: EnumSet<StandardDataColumns>,
     pub id: Vec<i128>,
+    // Opaque to the handler, for game code to tag danmaku with things like who fired them
+    pub user_data: Vec<u64>,
 
-    pub pos_x: Vec<Simd<f32, N>>,
-    pub pos_y: Vec<Simd<f32, N>>,
-    pub pos_z: Vec<Simd<f32, N>>,
+    pub pos_x: Vec<Simd<Pos, N>>,
+    pub pos_y: Vec<Simd<Pos, N>>,
+    pub pos_z: Vec<Simd<Pos, N>>,
 
-    pub old_pos_x: Vec<Simd<f32, N>>,
-    pub old_pos_y: Vec<Simd<f32, N>>,
-    pub old_pos_z: Vec<Simd<f32, N>>,
+    pub old_pos_x: Vec<Simd<Pos, N>>,
+    pub old_pos_y: Vec<Simd<Pos, N>>,
+    pub old_pos_z: Vec<Simd<Pos, N>>,
 
     pub scale_x: Vec<Simd<f32, N>>,
     pub scale_y: Vec<Simd<f32, N>>,
@@ -42,26 +73,46 @@ pub struct StandardColumns {
     pub old_main_color: Vec<Simd<i32, N>>,
     pub old_secondary_color: Vec<Simd<i32, N>>,
 
+    // The main color danmaku spawned with, for behaviors changing it over their lifetime
+    pub spawn_main_color: Vec<Simd<i32, N>>,
+    pub end_color: Vec<Simd<i32, N>>,
+
     pub damage: Vec<Simd<f32, N>>,
     pub form: Vec<&'static Form>,
     pub render_properties: Vec<HashMap<&'static str, f32>>,
 
     pub ticks_existed: Vec<Simd<i16, N>>,
     pub end_time: Vec<Simd<i16, N>>,
+    pub activation_tick: Vec<Simd<i16, N>>,
+    // The age spawners last spawned something at
+    pub last_spawn_tick: Vec<Simd<i16, N>>,
     pub dead: Vec<bool>,
+    // If the transform of a danmaku came out as NaN or infinite, in which case it's left out when
+    // rendering
+    non_finite_transform: Vec<bool>,
+    // If any danmaku in a SIMD chunk is not dead, so fully dead chunks can be skipped
+    pub alive_chunks: Vec<bool>,
     pub next_stage: Vec<Vec<DanmakuSpawnData<StandardSpawnData, StandardDataColumns>>>,
     pub next_stage_add_data: Vec<EnumSet<StandardDataColumns>>,
+    pub next_stage_inherit: Vec<HashMap<StandardDataColumns, InheritOp>>,
 
     pub parent: Vec<i128>,
 
     pub transform_mats: Vec<Matrix4<f32>>,
+    // The partial ticks transform_mats was last computed for, if nothing has changed since then
+    transform_mats_partial_ticks: Option<f32>,
     pub family_depth: Vec<i16>,
 
+    // Slots of dead danmaku, which can be reused until they are compacted away
     pub current_dead: Vec<usize>,
-    pub add_spawns: Vec<(
-        DanmakuSpawnData<StandardSpawnData, StandardDataColumns>,
-        Option<usize>,
-    )>,
+    // Danmaku that died during the current tick, to run death callbacks for. Unlike current_dead,
+    // a slot is removed from this when reused, so new danmaku aren't reported for the old ones
+    pub expired_this_tick: Vec<usize>,
+
+    // Shared by all handlers, and updated before each tick
+    pub handler_context: HandlerContext,
+
+    pub add_spawns: Vec<PendingSpawn<StandardSpawnData, StandardDataColumns>>,
 
     // Behavior specific data
     pub motion_x: Vec<Simd<f32, N>>,
@@ -74,11 +125,444 @@ pub struct StandardColumns {
 
     pub speed_accel: Vec<Simd<f32, N>>,
 
+    pub max_accel: Vec<Simd<f32, N>>,
+    // The motion before any forces were applied this tick
+    pub accel_base_x: Vec<Simd<f32, N>>,
+    pub accel_base_y: Vec<Simd<f32, N>>,
+    pub accel_base_z: Vec<Simd<f32, N>>,
+
+    pub gravity_source_x: Vec<Simd<f32, N>>,
+    pub gravity_source_y: Vec<Simd<f32, N>>,
+    pub gravity_source_z: Vec<Simd<f32, N>>,
+    pub gravity_strength: Vec<Simd<f32, N>>,
+
+    pub orbit_strength: Vec<Simd<f32, N>>,
+    // Radians per tick
+    pub turn_rate: Vec<Simd<f32, N>>,
+
     pub forward_x: Vec<Simd<f32, N>>,
     pub forward_y: Vec<Simd<f32, N>>,
     pub forward_z: Vec<Simd<f32, N>>,
 
+    // The motion and forward delayed danmaku spawned with, which they keep until they activate
+    pub held_motion_x: Vec<Simd<f32, N>>,
+    pub held_motion_y: Vec<Simd<f32, N>>,
+    pub held_motion_z: Vec<Simd<f32, N>>,
+    pub held_forward_x: Vec<Simd<f32, N>>,
+    pub held_forward_y: Vec<Simd<f32, N>>,
+    pub held_forward_z: Vec<Simd<f32, N>>,
+
     pub rotation: Vec<UnitQuaternion<f32>>,
+
+    // Positions from the last ticks, oldest first, holding at most trail_length of them
+    pub trail: Vec<Vec<Vector3<f32>>>,
+    pub trail_length: Vec<u16>,
+}
+
+const DEFAULT_TRAIL_LENGTH: u16 = 16;
+
+impl StandardColumns {
+    fn rebuild_alive_chunks(&mut self) {
+        self.alive_chunks = self
+            .dead
+            .chunks(N)
+            .map(|chunk| chunk.iter().any(|d| !d))
+            .collect();
+    }
+
+    pub fn refresh_alive_chunk(&mut self, chunk: usize) {
+        let end = ((chunk + 1) * N).min(self.dead.len());
+        self.alive_chunks[chunk] = self.dead[chunk * N..end].iter().any(|d| !d);
+    }
+
+    // The current state of a danmaku as spawn data, for the columns this handler has. Spawning
+    // with it again gives a danmaku in the same state, besides what only behaviors keep track of
+    pub fn spawn_data(&self, i: usize) -> Vec<StandardSpawnData> {
+        let has = |column| self.required_columns.contains(column);
+        let simd = |column: &Vec<Simd<f32, N>>| column[i / N][i % N];
+        let mut data = vec![];
+
+        if has(StandardDataColumns::PosX) {
+            data.push(StandardSpawnData::PosX(self.pos_x[i / N][i % N]));
+        }
+        if has(StandardDataColumns::PosY) {
+            data.push(StandardSpawnData::PosY(self.pos_y[i / N][i % N]));
+        }
+        if has(StandardDataColumns::PosZ) {
+            data.push(StandardSpawnData::PosZ(self.pos_z[i / N][i % N]));
+        }
+        if has(StandardDataColumns::Orientation) {
+            data.push(StandardSpawnData::Orientation(self.orientation[i]));
+        }
+        if has(StandardDataColumns::Appearance) {
+            data.push(StandardSpawnData::Appearance { form: self.form[i] });
+        }
+        if has(StandardDataColumns::MainColor) {
+            data.push(StandardSpawnData::MainColor(self.main_color[i / N][i % N]));
+        }
+        if has(StandardDataColumns::SecondaryColor) {
+            data.push(StandardSpawnData::SecondaryColor(
+                self.secondary_color[i / N][i % N],
+            ));
+        }
+        if has(StandardDataColumns::EndColor) {
+            data.push(StandardSpawnData::EndColor(self.end_color[i / N][i % N]));
+        }
+        if has(StandardDataColumns::Damage) {
+            data.push(StandardSpawnData::Damage(simd(&self.damage)));
+        }
+        if has(StandardDataColumns::ScaleX) {
+            data.push(StandardSpawnData::SizeX(simd(&self.scale_x)));
+        }
+        if has(StandardDataColumns::ScaleY) {
+            data.push(StandardSpawnData::SizeY(simd(&self.scale_y)));
+        }
+        if has(StandardDataColumns::ScaleZ) {
+            data.push(StandardSpawnData::SizeZ(simd(&self.scale_z)));
+        }
+        if has(StandardDataColumns::MotionX) {
+            data.push(StandardSpawnData::MotionX(simd(&self.motion_x)));
+        }
+        if has(StandardDataColumns::MotionY) {
+            data.push(StandardSpawnData::MotionY(simd(&self.motion_y)));
+        }
+        if has(StandardDataColumns::MotionZ) {
+            data.push(StandardSpawnData::MotionZ(simd(&self.motion_z)));
+        }
+        if has(StandardDataColumns::GravityX) {
+            data.push(StandardSpawnData::GravityX(simd(&self.gravity_x)));
+        }
+        if has(StandardDataColumns::GravityY) {
+            data.push(StandardSpawnData::GravityY(simd(&self.gravity_y)));
+        }
+        if has(StandardDataColumns::GravityZ) {
+            data.push(StandardSpawnData::GravityZ(simd(&self.gravity_z)));
+        }
+        if has(StandardDataColumns::SpeedAccel) {
+            data.push(StandardSpawnData::SpeedAccel(simd(&self.speed_accel)));
+        }
+        if has(StandardDataColumns::MaxAccel) {
+            data.push(StandardSpawnData::MaxAccel(simd(&self.max_accel)));
+        }
+        if has(StandardDataColumns::GravitySource) {
+            data.push(StandardSpawnData::GravitySource(Vector3::new(
+                simd(&self.gravity_source_x),
+                simd(&self.gravity_source_y),
+                simd(&self.gravity_source_z),
+            )));
+        }
+        if has(StandardDataColumns::GravityStrength) {
+            data.push(StandardSpawnData::GravityStrength(simd(
+                &self.gravity_strength,
+            )));
+        }
+        if has(StandardDataColumns::OrbitStrength) {
+            data.push(StandardSpawnData::OrbitStrength(simd(&self.orbit_strength)));
+        }
+        if has(StandardDataColumns::TurnRate) {
+            data.push(StandardSpawnData::TurnRate(simd(&self.turn_rate)));
+        }
+        if has(StandardDataColumns::ActivationTick) {
+            data.push(StandardSpawnData::ActivationTick(
+                self.activation_tick[i / N][i % N],
+            ));
+        }
+        if has(StandardDataColumns::Forward) {
+            // Behaviors only ever rotate forward, so it is still normalized
+            data.push(StandardSpawnData::Forward(UnitVector3::new_unchecked(
+                Vector3::new(
+                    simd(&self.forward_x),
+                    simd(&self.forward_y),
+                    simd(&self.forward_z),
+                ),
+            )));
+        }
+        if has(StandardDataColumns::Rotation) {
+            data.push(StandardSpawnData::Rotation(self.rotation[i]));
+        }
+        if has(StandardDataColumns::Trail) {
+            data.push(StandardSpawnData::TrailLength(self.trail_length[i]));
+        }
+        data.push(StandardSpawnData::UserData(self.user_data[i]));
+
+        data
+    }
+
+    // Puts a reused slot back to how a new slot starts out, as spawn data usually only sets some
+    // columns, and the rest would otherwise keep what the previous danmaku left there
+    fn reset_slot(&mut self, i: usize) {
+        fn reset<A: SimdElement>(vec: &mut [Simd<A, N>], i: usize, value: A) {
+            if let Some(chunk) = vec.get_mut(i / N) {
+                chunk[i % N] = value;
+            }
+        }
+
+        for vec in [
+            &mut self.pos_x,
+            &mut self.pos_y,
+            &mut self.pos_z,
+            &mut self.old_pos_x,
+            &mut self.old_pos_y,
+            &mut self.old_pos_z,
+        ] {
+            reset(vec, i, 0.0);
+        }
+        for vec in [
+            &mut self.scale_x,
+            &mut self.scale_y,
+            &mut self.scale_z,
+            &mut self.old_scale_x,
+            &mut self.old_scale_y,
+            &mut self.old_scale_z,
+            &mut self.damage,
+            &mut self.motion_x,
+            &mut self.motion_y,
+            &mut self.motion_z,
+            &mut self.gravity_x,
+            &mut self.gravity_y,
+            &mut self.gravity_z,
+            &mut self.speed_accel,
+            &mut self.max_accel,
+            &mut self.accel_base_x,
+            &mut self.accel_base_y,
+            &mut self.accel_base_z,
+            &mut self.gravity_source_x,
+            &mut self.gravity_source_y,
+            &mut self.gravity_source_z,
+            &mut self.gravity_strength,
+            &mut self.orbit_strength,
+            &mut self.turn_rate,
+            &mut self.forward_y,
+            &mut self.forward_z,
+            &mut self.held_motion_x,
+            &mut self.held_motion_y,
+            &mut self.held_motion_z,
+            &mut self.held_forward_x,
+            &mut self.held_forward_y,
+            &mut self.held_forward_z,
+        ] {
+            reset(vec, i, 0.0);
+        }
+        reset(&mut self.forward_x, i, 1.0);
+        for vec in [
+            &mut self.main_color,
+            &mut self.old_main_color,
+            &mut self.secondary_color,
+            &mut self.old_secondary_color,
+            &mut self.spawn_main_color,
+            &mut self.end_color,
+        ] {
+            reset(vec, i, 0);
+        }
+        reset(&mut self.activation_tick, i, 0);
+
+        for vec in [
+            &mut self.orientation,
+            &mut self.old_orientation,
+            &mut self.rotation,
+        ] {
+            if let Some(v) = vec.get_mut(i) {
+                *v = UnitQuaternion::identity();
+            }
+        }
+        if let Some(form) = self.form.get_mut(i) {
+            *form = &Form::SPHERE;
+        }
+        if let Some(render_properties) = self.render_properties.get_mut(i) {
+            render_properties.clear();
+        }
+    }
+
+    // Keeps the motion and forward a danmaku spawned with, for delay to put back while it waits.
+    // Those not allocated are held at their default, to line up with how they read elsewhere
+    fn hold_for_delay(&mut self, i: usize) {
+        for (held, column, default) in [
+            (&mut self.held_motion_x, &self.motion_x, 0.0),
+            (&mut self.held_motion_y, &self.motion_y, 0.0),
+            (&mut self.held_motion_z, &self.motion_z, 0.0),
+            (&mut self.held_forward_x, &self.forward_x, 1.0),
+            (&mut self.held_forward_y, &self.forward_y, 0.0),
+            (&mut self.held_forward_z, &self.forward_z, 0.0),
+        ] {
+            held[i / N][i % N] = column.get(i / N).map_or(default, |chunk| chunk[i % N]);
+        }
+    }
+
+    // Flags the danmaku whose value in column isn't zero, for conditional behaviors. Columns which
+    // aren't a single number for each danmaku flag nothing
+    pub(crate) fn flagged_by(&self, column: StandardDataColumns, size: usize) -> Vec<bool> {
+        fn nonzero<A: SimdElement + Default + PartialEq>(vec: &[Simd<A, N>], idx: usize) -> bool {
+            vec.get(idx / N)
+                .is_some_and(|chunk| chunk[idx % N] != A::default())
+        }
+
+        (0..size)
+            .map(|idx| match column {
+                StandardDataColumns::PosX => nonzero(&self.pos_x, idx),
+                StandardDataColumns::PosY => nonzero(&self.pos_y, idx),
+                StandardDataColumns::PosZ => nonzero(&self.pos_z, idx),
+                StandardDataColumns::ScaleX => nonzero(&self.scale_x, idx),
+                StandardDataColumns::ScaleY => nonzero(&self.scale_y, idx),
+                StandardDataColumns::ScaleZ => nonzero(&self.scale_z, idx),
+                StandardDataColumns::MainColor => nonzero(&self.main_color, idx),
+                StandardDataColumns::SecondaryColor => nonzero(&self.secondary_color, idx),
+                StandardDataColumns::EndColor => nonzero(&self.end_color, idx),
+                StandardDataColumns::Damage => nonzero(&self.damage, idx),
+                StandardDataColumns::MotionX => nonzero(&self.motion_x, idx),
+                StandardDataColumns::MotionY => nonzero(&self.motion_y, idx),
+                StandardDataColumns::MotionZ => nonzero(&self.motion_z, idx),
+                StandardDataColumns::GravityX => nonzero(&self.gravity_x, idx),
+                StandardDataColumns::GravityY => nonzero(&self.gravity_y, idx),
+                StandardDataColumns::GravityZ => nonzero(&self.gravity_z, idx),
+                StandardDataColumns::SpeedAccel => nonzero(&self.speed_accel, idx),
+                StandardDataColumns::MaxAccel => nonzero(&self.max_accel, idx),
+                StandardDataColumns::GravityStrength => nonzero(&self.gravity_strength, idx),
+                StandardDataColumns::OrbitStrength => nonzero(&self.orbit_strength, idx),
+                StandardDataColumns::TurnRate => nonzero(&self.turn_rate, idx),
+                StandardDataColumns::ActivationTick => nonzero(&self.activation_tick, idx),
+                StandardDataColumns::SpawnInterval
+                | StandardDataColumns::Orientation
+                | StandardDataColumns::Appearance
+                | StandardDataColumns::GravitySource
+                | StandardDataColumns::Rotation
+                | StandardDataColumns::Forward
+                | StandardDataColumns::Trail => false,
+            })
+            .collect()
+    }
+
+    // Spawns coming from a danmaku outside the lane mask are dropped, like its kills are
+    pub(crate) fn queue_spawns(
+        &mut self,
+        from: usize,
+        spawns: impl IntoIterator<Item = PendingSpawn<StandardSpawnData, StandardDataColumns>>,
+    ) {
+        if self.handler_context.lane_flagged(from) {
+            self.add_spawns.extend(spawns);
+        }
+    }
+
+    fn live_mask(&self, chunk: usize, size: usize) -> Mask<i32, N> {
+        Mask::from_array(std::array::from_fn(|j| {
+            let idx = chunk * N + j;
+            idx < size && !self.dead[idx]
+        }))
+    }
+
+    // Sums a lane-wise value over the live danmaku. Columns that aren't allocated read as zero. The
+    // values are added up one danmaku at a time in order, instead of a chunk at a time, so the sum
+    // comes out the same whatever the SIMD width of the host is
+    fn sum_live(&self, size: usize, value: impl Fn(usize) -> Simd<f32, N>) -> f32 {
+        (0..size.div_ceil(N))
+            .filter(|chunk| self.alive_chunks[*chunk])
+            .flat_map(|chunk| {
+                self.live_mask(chunk, size)
+                    .select(value(chunk), Simd::splat(0.0))
+                    .to_array()
+            })
+            .fold(0.0, |sum, v| sum + v)
+    }
+
+    // Totals over the live danmaku, like to check that a physics behavior keeps the energy of
+    // danmaku the same
+    pub fn sum_motion_magnitude(&self, size: usize) -> f32 {
+        let zero = Simd::splat(0.0);
+        self.sum_live(size, |chunk| {
+            simd_len3(
+                *self.motion_x.get(chunk).unwrap_or(&zero),
+                *self.motion_y.get(chunk).unwrap_or(&zero),
+                *self.motion_z.get(chunk).unwrap_or(&zero),
+            )
+        })
+    }
+
+    pub fn sum_motion(&self, size: usize) -> Vector3<f32> {
+        let zero = Simd::splat(0.0);
+        Vector3::new(
+            self.sum_live(size, |chunk| *self.motion_x.get(chunk).unwrap_or(&zero)),
+            self.sum_live(size, |chunk| *self.motion_y.get(chunk).unwrap_or(&zero)),
+            self.sum_live(size, |chunk| *self.motion_z.get(chunk).unwrap_or(&zero)),
+        )
+    }
+
+    pub fn sum_damage(&self, size: usize) -> f32 {
+        let zero = Simd::splat(0.0);
+        self.sum_live(size, |chunk| *self.damage.get(chunk).unwrap_or(&zero))
+    }
+
+    // The transform of a single danmaku, without its parents
+    fn local_transform(&self, i: usize, partial_ticks: f32) -> Matrix4<f32> {
+        let requires = |column| self.required_columns.contains(column);
+        let default_scale = self.handler_context.default_scale;
+
+        // Axes without a scale, like z for planar danmaku, use the default instead of flattening
+        // the danmaku
+        let lerp_or_default = |column, old: &[Simd<f32, N>], new: &[Simd<f32, N>], default| {
+            if requires(column) {
+                nalgebra_glm::lerp_scalar(old[i / N][i % N], new[i / N][i % N], partial_ticks)
+            } else {
+                default
+            }
+        };
+        // Positions are lerped at their full precision, and only then brought down to f32
+        let lerp_pos_if_used = |column, old: &[Simd<Pos, N>], new: &[Simd<Pos, N>]| {
+            if requires(column) {
+                let old = old[i / N][i % N];
+                let new = new[i / N][i % N];
+                pos_to_f32(old + (new - old) * partial_ticks as Pos)
+            } else {
+                0.0
+            }
+        };
+
+        use StandardDataColumns as C;
+        let mut transform = Matrix4::new_nonuniform_scaling(&Vector3::new(
+            lerp_or_default(C::ScaleX, &self.old_scale_x, &self.scale_x, default_scale.x),
+            lerp_or_default(C::ScaleY, &self.old_scale_y, &self.scale_y, default_scale.y),
+            lerp_or_default(C::ScaleZ, &self.old_scale_z, &self.scale_z, default_scale.z),
+        ));
+
+        if requires(C::PosX) || requires(C::PosY) || requires(C::PosZ) {
+            transform.append_translation_mut(&Vector3::new(
+                lerp_pos_if_used(C::PosX, &self.old_pos_x, &self.pos_x),
+                lerp_pos_if_used(C::PosY, &self.old_pos_y, &self.pos_y),
+                lerp_pos_if_used(C::PosZ, &self.old_pos_z, &self.pos_z),
+            ));
+        }
+
+        let orientation = *self
+            .orientation
+            .get(i)
+            .unwrap_or(&UnitQuaternion::identity());
+        let orientation_mat = if requires(C::Orientation) {
+            self.old_orientation
+                .get(i)
+                .unwrap_or(&UnitQuaternion::identity())
+                .slerp(&orientation, partial_ticks)
+                .to_homogeneous()
+        } else {
+            orientation.to_homogeneous()
+        };
+
+        orientation_mat * transform
+    }
+}
+
+// Writes spawn data to the fields of a column, if that column is allocated. Each use names the
+// column together with its fields, so data can't end up written to the fields of another column
+macro_rules! transfer {
+    ($columns:ident, $i:ident, simd $column:ident => $($field:ident),+ = $v:expr) => {
+        if $columns.required_columns.contains(StandardDataColumns::$column) {
+            let v = $v;
+            $($columns.$field[$i / N][$i % N] = v;)+
+        }
+    };
+    ($columns:ident, $i:ident, $column:ident => $($field:ident),+ = $v:expr) => {
+        if $columns.required_columns.contains(StandardDataColumns::$column) {
+            let v = $v;
+            $($columns.$field[$i] = v;)+
+        }
+    };
 }
 
 impl DanmakuData for StandardColumns {
@@ -124,6 +608,7 @@ impl DanmakuData for StandardColumns {
             required_columns: required,
 
             id: vec![0; max_column_size],
+            user_data: vec![0; max_column_size],
             pos_x: sized_simd(0.0, required, max_column_size, StandardDataColumns::PosX),
             pos_y: sized_simd(0.0, required, max_column_size, StandardDataColumns::PosY),
             pos_z: sized_simd(0.0, required, max_column_size, StandardDataColumns::PosZ),
@@ -131,8 +616,8 @@ impl DanmakuData for StandardColumns {
             old_pos_y: sized_simd(0.0, required, max_column_size, StandardDataColumns::PosY),
             old_pos_z: sized_simd(0.0, required, max_column_size, StandardDataColumns::PosZ),
             scale_x: sized_simd(0.0, required, max_column_size, StandardDataColumns::ScaleX),
-            scale_y: sized_simd(0.0, required, max_column_size, StandardDataColumns::ScaleX),
-            scale_z: sized_simd(0.0, required, max_column_size, StandardDataColumns::ScaleX),
+            scale_y: sized_simd(0.0, required, max_column_size, StandardDataColumns::ScaleY),
+            scale_z: sized_simd(0.0, required, max_column_size, StandardDataColumns::ScaleZ),
             old_scale_x: sized_simd(0.0, required, max_column_size, StandardDataColumns::ScaleX),
             old_scale_y: sized_simd(0.0, required, max_column_size, StandardDataColumns::ScaleY),
             old_scale_z: sized_simd(0.0, required, max_column_size, StandardDataColumns::ScaleZ),
@@ -167,6 +652,13 @@ impl DanmakuData for StandardColumns {
                 max_column_size,
                 StandardDataColumns::SecondaryColor,
             ),
+            spawn_main_color: sized_simd(
+                0,
+                required,
+                max_column_size,
+                StandardDataColumns::MainColor,
+            ),
+            end_color: sized_simd(0, required, max_column_size, StandardDataColumns::EndColor),
             damage: sized_simd(0.0, required, max_column_size, StandardDataColumns::Damage),
             form: sized_vec(
                 &Form::SPHERE,
@@ -182,13 +674,31 @@ impl DanmakuData for StandardColumns {
             ),
             ticks_existed: sized_simd_always(0, max_column_size),
             end_time: sized_simd_always(0, max_column_size),
+            activation_tick: sized_simd(
+                0,
+                required,
+                max_column_size,
+                StandardDataColumns::ActivationTick,
+            ),
+            last_spawn_tick: sized_simd(
+                0,
+                required,
+                max_column_size,
+                StandardDataColumns::SpawnInterval,
+            ),
             dead: vec![false; max_column_size],
+            alive_chunks: vec![true; max_column_size.div_ceil(N)],
             next_stage: vec![Vec::new(); max_column_size],
-            next_stage_add_data: vec![EnumSet::EMPTY; max_column_size],
+            next_stage_add_data: vec![EnumSet::empty(); max_column_size],
+            next_stage_inherit: vec![HashMap::new(); max_column_size],
             parent: vec![-1; max_column_size],
             transform_mats: vec![Matrix4::identity(); max_column_size],
+            transform_mats_partial_ticks: None,
+            non_finite_transform: vec![false; max_column_size],
             family_depth: vec![0; max_column_size],
             current_dead: Vec::new(),
+            expired_this_tick: Vec::new(),
+            handler_context: HandlerContext::default(),
             add_spawns: Vec::new(),
 
             // Behavior specific data
@@ -219,16 +729,126 @@ impl DanmakuData for StandardColumns {
                 max_column_size,
                 StandardDataColumns::SpeedAccel,
             ),
+            max_accel: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::MaxAccel,
+            ),
+            accel_base_x: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::MaxAccel,
+            ),
+            accel_base_y: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::MaxAccel,
+            ),
+            accel_base_z: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::MaxAccel,
+            ),
+            gravity_source_x: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::GravitySource,
+            ),
+            gravity_source_y: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::GravitySource,
+            ),
+            gravity_source_z: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::GravitySource,
+            ),
+            gravity_strength: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::GravityStrength,
+            ),
+            orbit_strength: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::OrbitStrength,
+            ),
+            turn_rate: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::TurnRate,
+            ),
 
+            // Danmaku spawned without a forward face along +X, wherever a slot comes from
             forward_x: sized_simd(1.0, required, max_column_size, StandardDataColumns::Forward),
-            forward_y: sized_simd(1.0, required, max_column_size, StandardDataColumns::Forward),
-            forward_z: sized_simd(1.0, required, max_column_size, StandardDataColumns::Forward),
+            forward_y: sized_simd(0.0, required, max_column_size, StandardDataColumns::Forward),
+            forward_z: sized_simd(0.0, required, max_column_size, StandardDataColumns::Forward),
+            held_motion_x: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::ActivationTick,
+            ),
+            held_motion_y: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::ActivationTick,
+            ),
+            held_motion_z: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::ActivationTick,
+            ),
+            held_forward_x: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::ActivationTick,
+            ),
+            held_forward_y: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::ActivationTick,
+            ),
+            held_forward_z: sized_simd(
+                0.0,
+                required,
+                max_column_size,
+                StandardDataColumns::ActivationTick,
+            ),
             rotation: sized_vec(
                 UnitQuaternion::identity(),
                 required,
                 max_column_size,
                 StandardDataColumns::Rotation,
             ),
+
+            trail: sized_vec(
+                Vec::new(),
+                required,
+                max_column_size,
+                StandardDataColumns::Trail,
+            ),
+            trail_length: sized_vec(
+                DEFAULT_TRAIL_LENGTH,
+                required,
+                max_column_size,
+                StandardDataColumns::Trail,
+            ),
         }
     }
 
@@ -236,17 +856,14 @@ impl DanmakuData for StandardColumns {
         self.required_columns
     }
 
-    fn grab_new_spawns(
-        &mut self,
-    ) -> Vec<(
-        DanmakuSpawnData<StandardSpawnData, StandardDataColumns>,
-        Option<usize>,
-    )> {
+    fn grab_new_spawns(&mut self) -> Vec<PendingSpawn<StandardSpawnData, StandardDataColumns>> {
         std::mem::take(&mut self.add_spawns)
     }
 
     fn resize(&mut self, new_max_size: usize) {
+        self.invalidate_transform_mats();
         self.id.resize(new_max_size, 0);
+        self.user_data.resize(new_max_size, 0);
 
         fn resize_if_required<A: Clone>(
             required_columns: EnumSet<StandardDataColumns>,
@@ -351,21 +968,21 @@ impl DanmakuData for StandardColumns {
             self.required_columns,
             new_max_size,
             StandardDataColumns::ScaleX,
-            &mut self.scale_x,
+            &mut self.old_scale_x,
             0.0,
         );
         resize_simd_if_required(
             self.required_columns,
             new_max_size,
             StandardDataColumns::ScaleY,
-            &mut self.scale_y,
+            &mut self.old_scale_y,
             0.0,
         );
         resize_simd_if_required(
             self.required_columns,
             new_max_size,
             StandardDataColumns::ScaleZ,
-            &mut self.scale_z,
+            &mut self.old_scale_z,
             0.0,
         );
 
@@ -414,6 +1031,21 @@ impl DanmakuData for StandardColumns {
             0,
         );
 
+        resize_simd_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::MainColor,
+            &mut self.spawn_main_color,
+            0,
+        );
+        resize_simd_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::EndColor,
+            &mut self.end_color,
+            0,
+        );
+
         resize_simd_if_required(
             self.required_columns,
             new_max_size,
@@ -460,21 +1092,21 @@ impl DanmakuData for StandardColumns {
         resize_simd_if_required(
             self.required_columns,
             new_max_size,
-            StandardDataColumns::MotionX,
+            StandardDataColumns::GravityX,
             &mut self.gravity_x,
             0.0,
         );
         resize_simd_if_required(
             self.required_columns,
             new_max_size,
-            StandardDataColumns::MotionY,
+            StandardDataColumns::GravityY,
             &mut self.gravity_y,
             0.0,
         );
         resize_simd_if_required(
             self.required_columns,
             new_max_size,
-            StandardDataColumns::MotionZ,
+            StandardDataColumns::GravityZ,
             &mut self.gravity_z,
             0.0,
         );
@@ -485,41 +1117,144 @@ impl DanmakuData for StandardColumns {
             &mut self.speed_accel,
             0.0,
         );
+        for vec in [
+            &mut self.max_accel,
+            &mut self.accel_base_x,
+            &mut self.accel_base_y,
+            &mut self.accel_base_z,
+        ] {
+            resize_simd_if_required(
+                self.required_columns,
+                new_max_size,
+                StandardDataColumns::MaxAccel,
+                vec,
+                0.0,
+            );
+        }
         resize_simd_if_required(
             self.required_columns,
             new_max_size,
-            StandardDataColumns::Forward,
-            &mut self.forward_x,
-            1.0,
+            StandardDataColumns::GravitySource,
+            &mut self.gravity_source_x,
+            0.0,
         );
         resize_simd_if_required(
             self.required_columns,
             new_max_size,
-            StandardDataColumns::Forward,
-            &mut self.forward_y,
+            StandardDataColumns::GravitySource,
+            &mut self.gravity_source_y,
             0.0,
         );
         resize_simd_if_required(
             self.required_columns,
             new_max_size,
-            StandardDataColumns::Forward,
-            &mut self.forward_z,
+            StandardDataColumns::GravitySource,
+            &mut self.gravity_source_z,
             0.0,
         );
-        resize_if_required(
+        resize_simd_if_required(
             self.required_columns,
             new_max_size,
-            StandardDataColumns::Rotation,
-            &mut self.rotation,
-            UnitQuaternion::identity(),
+            StandardDataColumns::GravityStrength,
+            &mut self.gravity_strength,
+            0.0,
         );
-
-        resize_simd(new_max_size, &mut self.ticks_existed, 0);
-        resize_simd(new_max_size, &mut self.end_time, 0);
-        self.dead.resize(new_max_size, false);
+        resize_simd_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::OrbitStrength,
+            &mut self.orbit_strength,
+            0.0,
+        );
+        resize_simd_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::TurnRate,
+            &mut self.turn_rate,
+            0.0,
+        );
+        resize_simd_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::Forward,
+            &mut self.forward_x,
+            1.0,
+        );
+        resize_simd_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::Forward,
+            &mut self.forward_y,
+            0.0,
+        );
+        resize_simd_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::Forward,
+            &mut self.forward_z,
+            0.0,
+        );
+        resize_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::Rotation,
+            &mut self.rotation,
+            UnitQuaternion::identity(),
+        );
+        resize_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::Trail,
+            &mut self.trail,
+            Vec::new(),
+        );
+        resize_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::Trail,
+            &mut self.trail_length,
+            DEFAULT_TRAIL_LENGTH,
+        );
+
+        resize_simd(new_max_size, &mut self.ticks_existed, 0);
+        resize_simd(new_max_size, &mut self.end_time, 0);
+        resize_simd_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::ActivationTick,
+            &mut self.activation_tick,
+            0,
+        );
+        for vec in [
+            &mut self.held_motion_x,
+            &mut self.held_motion_y,
+            &mut self.held_motion_z,
+            &mut self.held_forward_x,
+            &mut self.held_forward_y,
+            &mut self.held_forward_z,
+        ] {
+            resize_simd_if_required(
+                self.required_columns,
+                new_max_size,
+                StandardDataColumns::ActivationTick,
+                vec,
+                0.0,
+            );
+        }
+        resize_simd_if_required(
+            self.required_columns,
+            new_max_size,
+            StandardDataColumns::SpawnInterval,
+            &mut self.last_spawn_tick,
+            0,
+        );
+        self.dead.resize(new_max_size, false);
+        self.non_finite_transform.resize(new_max_size, false);
+        self.rebuild_alive_chunks();
         self.next_stage.resize(new_max_size, Vec::new());
         self.next_stage_add_data
-            .resize(new_max_size, EnumSet::EMPTY);
+            .resize(new_max_size, EnumSet::empty());
+        self.next_stage_inherit.resize(new_max_size, HashMap::new());
 
         self.parent.resize(new_max_size, -1);
         self.transform_mats
@@ -529,6 +1264,8 @@ impl DanmakuData for StandardColumns {
     }
 
     fn compact(&mut self, new_max_size: usize) {
+        self.invalidate_transform_mats();
+
         fn compact_vec<A: Clone>(vec: &mut Vec<A>, remove: &[bool], new_max_size: usize, value: A) {
             let mut j = 0;
             vec.retain(|_| {
@@ -537,91 +1274,202 @@ impl DanmakuData for StandardColumns {
                 !to_remove
             });
             vec.resize(new_max_size, value);
+            // Compacting to a smaller size is how handlers scale down, so the memory is given back
+            vec.shrink_to_fit();
         }
 
-        fn compact_simd<A: SimdElement + Clone>(
+        fn compact_simd<A: SimdElement>(
             vec: &mut Vec<Simd<A, N>>,
             remove: &[bool],
             new_max_size: usize,
             value: A,
         ) {
-            let mut new_vec = vec![value; new_max_size];
-            let mut stored_so_far = 0;
-            vec.iter().enumerate().for_each(|(idx, v)| {
-                let from = idx / N;
-                let slice = &remove[from..from + N];
-                let mut arr = [false; N];
-                let len = arr.len();
-                arr.copy_from_slice(&slice[..len]);
-
-                let mask = !std::simd::Mask::from_array(arr);
-                v.store_select(&mut new_vec[stored_so_far..stored_so_far + N], mask);
-                stored_so_far += slice.iter().filter(|v| !*v).count();
-            });
+            // Moves every kept element down to the next free slot, one element at a time, as the
+            // kept elements of a chunk can end up spread over two chunks. remove may be shorter than
+            // the column when the last chunk is only partly used, and what it doesn't cover is kept
+            let mut kept = 0;
+            for idx in 0..vec.len() * N {
+                if *remove.get(idx).unwrap_or(&false) {
+                    continue;
+                }
+
+                let v = vec[idx / N][idx % N];
+                vec[kept / N][kept % N] = v;
+                kept += 1;
+            }
 
             vec.resize(new_max_size.div_ceil(N), Simd::splat(value));
-            for i in 0..new_max_size.div_ceil(N) {
-                vec[i] = Simd::load_or(&new_vec[i * N..(i + 1) * N], Simd::splat(value));
+            vec.shrink_to_fit();
+            for idx in kept..vec.len() * N {
+                vec[idx / N][idx % N] = value;
+            }
+        }
+
+        // Like when resizing, columns that were never allocated stay that way
+        fn compact_vec_if_required<A: Clone>(
+            required_columns: EnumSet<StandardDataColumns>,
+            required_column: StandardDataColumns,
+            vec: &mut Vec<A>,
+            remove: &[bool],
+            new_max_size: usize,
+            value: A,
+        ) {
+            if required_columns.contains(required_column) {
+                compact_vec(vec, remove, new_max_size, value);
+            }
+        }
+
+        fn compact_simd_if_required<A: SimdElement>(
+            required_columns: EnumSet<StandardDataColumns>,
+            required_column: StandardDataColumns,
+            vec: &mut Vec<Simd<A, N>>,
+            remove: &[bool],
+            new_max_size: usize,
+            value: A,
+        ) {
+            if required_columns.contains(required_column) {
+                compact_simd(vec, remove, new_max_size, value);
             }
         }
 
+        let required = self.required_columns;
         let dead = &self.dead;
 
         [&mut self.id, &mut self.parent]
             .iter_mut()
             .for_each(|d| compact_vec(d, dead, new_max_size, -1));
-        [
-            &mut self.pos_x,
-            &mut self.pos_y,
-            &mut self.pos_z,
-            &mut self.old_pos_x,
-            &mut self.old_pos_y,
-            &mut self.old_pos_z,
-            &mut self.scale_x,
-            &mut self.scale_y,
-            &mut self.scale_z,
-            &mut self.old_scale_x,
-            &mut self.old_scale_y,
-            &mut self.old_scale_z,
-            &mut self.damage,
-            &mut self.motion_x,
-            &mut self.motion_y,
-            &mut self.motion_z,
-            &mut self.gravity_x,
-            &mut self.gravity_y,
-            &mut self.gravity_z,
-            &mut self.speed_accel,
-        ]
-        .iter_mut()
-        .for_each(|d| compact_simd(d, dead, new_max_size, 0.0));
+        compact_vec(&mut self.user_data, dead, new_max_size, 0);
+
+        for (column, vec, value) in [
+            (StandardDataColumns::PosX, &mut self.pos_x, 0.0),
+            (StandardDataColumns::PosY, &mut self.pos_y, 0.0),
+            (StandardDataColumns::PosZ, &mut self.pos_z, 0.0),
+            (StandardDataColumns::PosX, &mut self.old_pos_x, 0.0),
+            (StandardDataColumns::PosY, &mut self.old_pos_y, 0.0),
+            (StandardDataColumns::PosZ, &mut self.old_pos_z, 0.0),
+        ] {
+            compact_simd_if_required(required, column, vec, dead, new_max_size, value);
+        }
 
-        [
-            &mut self.forward_x,
-            &mut self.forward_y,
-            &mut self.forward_z,
-        ]
-        .iter_mut()
-        .for_each(|d| compact_simd(d, dead, new_max_size, 1.0));
+        for (column, vec, value) in [
+            (StandardDataColumns::ScaleX, &mut self.scale_x, 0.0),
+            (StandardDataColumns::ScaleY, &mut self.scale_y, 0.0),
+            (StandardDataColumns::ScaleZ, &mut self.scale_z, 0.0),
+            (StandardDataColumns::ScaleX, &mut self.old_scale_x, 0.0),
+            (StandardDataColumns::ScaleY, &mut self.old_scale_y, 0.0),
+            (StandardDataColumns::ScaleZ, &mut self.old_scale_z, 0.0),
+            (StandardDataColumns::Damage, &mut self.damage, 0.0),
+            (StandardDataColumns::MotionX, &mut self.motion_x, 0.0),
+            (StandardDataColumns::MotionY, &mut self.motion_y, 0.0),
+            (StandardDataColumns::MotionZ, &mut self.motion_z, 0.0),
+            (StandardDataColumns::GravityX, &mut self.gravity_x, 0.0),
+            (StandardDataColumns::GravityY, &mut self.gravity_y, 0.0),
+            (StandardDataColumns::GravityZ, &mut self.gravity_z, 0.0),
+            (StandardDataColumns::SpeedAccel, &mut self.speed_accel, 0.0),
+            (StandardDataColumns::MaxAccel, &mut self.max_accel, 0.0),
+            (StandardDataColumns::MaxAccel, &mut self.accel_base_x, 0.0),
+            (StandardDataColumns::MaxAccel, &mut self.accel_base_y, 0.0),
+            (StandardDataColumns::MaxAccel, &mut self.accel_base_z, 0.0),
+            (
+                StandardDataColumns::GravitySource,
+                &mut self.gravity_source_x,
+                0.0,
+            ),
+            (
+                StandardDataColumns::GravitySource,
+                &mut self.gravity_source_y,
+                0.0,
+            ),
+            (
+                StandardDataColumns::GravitySource,
+                &mut self.gravity_source_z,
+                0.0,
+            ),
+            (
+                StandardDataColumns::GravityStrength,
+                &mut self.gravity_strength,
+                0.0,
+            ),
+            (
+                StandardDataColumns::OrbitStrength,
+                &mut self.orbit_strength,
+                0.0,
+            ),
+            (StandardDataColumns::TurnRate, &mut self.turn_rate, 0.0),
+            (StandardDataColumns::Forward, &mut self.forward_x, 1.0),
+            (StandardDataColumns::Forward, &mut self.forward_y, 0.0),
+            (StandardDataColumns::Forward, &mut self.forward_z, 0.0),
+            (
+                StandardDataColumns::ActivationTick,
+                &mut self.held_motion_x,
+                0.0,
+            ),
+            (
+                StandardDataColumns::ActivationTick,
+                &mut self.held_motion_y,
+                0.0,
+            ),
+            (
+                StandardDataColumns::ActivationTick,
+                &mut self.held_motion_z,
+                0.0,
+            ),
+            (
+                StandardDataColumns::ActivationTick,
+                &mut self.held_forward_x,
+                0.0,
+            ),
+            (
+                StandardDataColumns::ActivationTick,
+                &mut self.held_forward_y,
+                0.0,
+            ),
+            (
+                StandardDataColumns::ActivationTick,
+                &mut self.held_forward_z,
+                0.0,
+            ),
+        ] {
+            compact_simd_if_required(required, column, vec, dead, new_max_size, value);
+        }
 
-        [
-            &mut self.orientation,
-            &mut self.old_orientation,
-            &mut self.rotation,
-        ]
-        .iter_mut()
-        .for_each(|d| compact_vec(d, dead, new_max_size, UnitQuaternion::identity()));
+        for (column, vec) in [
+            (StandardDataColumns::Orientation, &mut self.orientation),
+            (StandardDataColumns::Orientation, &mut self.old_orientation),
+            (StandardDataColumns::Rotation, &mut self.rotation),
+        ] {
+            let identity = UnitQuaternion::identity();
+            compact_vec_if_required(required, column, vec, dead, new_max_size, identity);
+        }
 
-        [
-            &mut self.main_color,
-            &mut self.old_main_color,
-            &mut self.secondary_color,
-            &mut self.old_secondary_color,
-        ]
-        .iter_mut()
-        .for_each(|d| compact_simd(d, dead, new_max_size, 0));
+        for (column, vec) in [
+            (StandardDataColumns::MainColor, &mut self.main_color),
+            (StandardDataColumns::MainColor, &mut self.old_main_color),
+            (
+                StandardDataColumns::SecondaryColor,
+                &mut self.secondary_color,
+            ),
+            (
+                StandardDataColumns::SecondaryColor,
+                &mut self.old_secondary_color,
+            ),
+            (StandardDataColumns::MainColor, &mut self.spawn_main_color),
+            (StandardDataColumns::EndColor, &mut self.end_color),
+        ] {
+            compact_simd_if_required(required, column, vec, dead, new_max_size, 0);
+        }
 
-        compact_vec(&mut self.form, dead, new_max_size, &Form::SPHERE);
-        compact_vec(
+        compact_vec_if_required(
+            required,
+            StandardDataColumns::Appearance,
+            &mut self.form,
+            dead,
+            new_max_size,
+            &Form::SPHERE,
+        );
+        compact_vec_if_required(
+            required,
+            StandardDataColumns::Appearance,
             &mut self.render_properties,
             dead,
             new_max_size,
@@ -631,9 +1479,42 @@ impl DanmakuData for StandardColumns {
         [&mut self.ticks_existed, &mut self.end_time]
             .iter_mut()
             .for_each(|d| compact_simd(d, dead, new_max_size, 0));
+        compact_simd_if_required(
+            required,
+            StandardDataColumns::ActivationTick,
+            &mut self.activation_tick,
+            dead,
+            new_max_size,
+            0,
+        );
+        compact_simd_if_required(
+            required,
+            StandardDataColumns::SpawnInterval,
+            &mut self.last_spawn_tick,
+            dead,
+            new_max_size,
+            0,
+        );
 
         compact_vec(&mut self.family_depth, dead, new_max_size, 0);
 
+        compact_vec_if_required(
+            required,
+            StandardDataColumns::Trail,
+            &mut self.trail,
+            dead,
+            new_max_size,
+            Vec::new(),
+        );
+        compact_vec_if_required(
+            required,
+            StandardDataColumns::Trail,
+            &mut self.trail_length,
+            dead,
+            new_max_size,
+            DEFAULT_TRAIL_LENGTH,
+        );
+
         compact_vec(&mut self.next_stage, dead, new_max_size, Vec::new());
         compact_vec(
             &mut self.next_stage_add_data,
@@ -641,16 +1522,34 @@ impl DanmakuData for StandardColumns {
             new_max_size,
             EnumSet::new(),
         );
+        compact_vec(
+            &mut self.next_stage_inherit,
+            dead,
+            new_max_size,
+            HashMap::new(),
+        );
         compact_vec(
             &mut self.transform_mats,
             dead,
             new_max_size,
             Matrix4::identity(),
         );
+        compact_vec(&mut self.non_finite_transform, dead, new_max_size, false);
 
-        let _ = &mut self.dead.retain(|d| *d);
+        let _ = &mut self.dead.retain(|d| !*d);
         self.dead.resize(new_max_size, false);
+        self.dead.shrink_to_fit();
+        self.rebuild_alive_chunks();
         let _ = &mut self.current_dead.clear();
+        self.expired_this_tick.clear();
+    }
+
+    fn invalidate_transform_mats(&mut self) {
+        self.transform_mats_partial_ticks = None;
+    }
+
+    fn set_context(&mut self, context: &HandlerContext) {
+        self.handler_context.clone_from(context);
     }
 
     fn id(&mut self) -> &mut Vec<i128> {
@@ -661,302 +1560,368 @@ impl DanmakuData for StandardColumns {
         &mut self.dead
     }
 
+    fn ids(&self) -> &[i128] {
+        &self.id
+    }
+
+    fn is_dead(&self, idx: usize) -> bool {
+        self.dead[idx]
+    }
+
     fn current_dead_len(&self) -> usize {
         self.current_dead.len()
     }
 
+    fn memory_usage(&self) -> Vec<(&'static str, usize)> {
+        // Needs the Vec itself to know how much it has allocated, and not just how much is in use
+        #[allow(clippy::ptr_arg)]
+        fn vec_bytes<T>(v: &Vec<T>) -> usize {
+            v.capacity() * std::mem::size_of::<T>()
+        }
+
+        macro_rules! columns_bytes {
+            ($($field:ident),+ $(,)?) => {
+                vec![$((stringify!($field), vec_bytes(&self.$field))),+]
+            };
+        }
+
+        let mut usage = columns_bytes![
+            id,
+            user_data,
+            pos_x,
+            pos_y,
+            pos_z,
+            old_pos_x,
+            old_pos_y,
+            old_pos_z,
+            scale_x,
+            scale_y,
+            scale_z,
+            old_scale_x,
+            old_scale_y,
+            old_scale_z,
+            orientation,
+            old_orientation,
+            main_color,
+            secondary_color,
+            old_main_color,
+            old_secondary_color,
+            spawn_main_color,
+            end_color,
+            damage,
+            form,
+            render_properties,
+            ticks_existed,
+            end_time,
+            activation_tick,
+            last_spawn_tick,
+            dead,
+            alive_chunks,
+            next_stage,
+            next_stage_add_data,
+            next_stage_inherit,
+            parent,
+            transform_mats,
+            family_depth,
+            motion_x,
+            motion_y,
+            motion_z,
+            gravity_x,
+            gravity_y,
+            gravity_z,
+            speed_accel,
+            max_accel,
+            accel_base_x,
+            accel_base_y,
+            accel_base_z,
+            gravity_source_x,
+            gravity_source_y,
+            gravity_source_z,
+            gravity_strength,
+            orbit_strength,
+            turn_rate,
+            forward_x,
+            forward_y,
+            forward_z,
+            held_motion_x,
+            held_motion_y,
+            held_motion_z,
+            held_forward_x,
+            held_forward_y,
+            held_forward_z,
+            rotation,
+            trail,
+            trail_length,
+        ];
+
+        // Trails are the only column with a sizable allocation per danmaku
+        let trail_points: usize = self.trail.iter().map(vec_bytes).sum();
+        if let Some((_, bytes)) = usage.iter_mut().find(|(name, _)| *name == "trail") {
+            *bytes += trail_points;
+        }
+
+        usage.retain(|(_, bytes)| *bytes > 0);
+        usage
+    }
+
+    fn expired_this_tick(&self) -> &[usize] {
+        &self.expired_this_tick
+    }
+
+    fn clear_expired(&mut self) {
+        self.expired_this_tick.clear();
+    }
+
+    fn kill(&mut self, idx: usize) {
+        if !self.dead[idx] && self.handler_context.lane_flagged(idx) {
+            self.dead[idx] = true;
+            self.refresh_alive_chunk(idx / N);
+            self.current_dead.push(idx);
+            self.expired_this_tick.push(idx);
+        }
+    }
+
+    fn view(&self, idx: usize) -> DanmakuView {
+        // Columns that aren't allocated are empty, and just read as the default value
+        fn lane<A: SimdElement + Default>(vec: &[Simd<A, N>], idx: usize) -> A {
+            vec.get(idx / N)
+                .map(|chunk| chunk[idx % N])
+                .unwrap_or_default()
+        }
+
+        fn lane_or<A: SimdElement>(vec: &[Simd<A, N>], idx: usize, default: A) -> A {
+            vec.get(idx / N).map_or(default, |chunk| chunk[idx % N])
+        }
+
+        DanmakuView {
+            id: self.id[idx],
+            user_data: self.user_data[idx],
+            form: self.form.get(idx).copied(),
+            position: Vector3::new(
+                pos_to_f32(lane(&self.pos_x, idx)),
+                pos_to_f32(lane(&self.pos_y, idx)),
+                pos_to_f32(lane(&self.pos_z, idx)),
+            ),
+            scale: Vector3::new(
+                lane_or(&self.scale_x, idx, 1.0),
+                lane_or(&self.scale_y, idx, 1.0),
+                lane_or(&self.scale_z, idx, 1.0),
+            ),
+            orientation: self
+                .orientation
+                .get(idx)
+                .copied()
+                .unwrap_or(UnitQuaternion::identity()),
+            motion: Vector3::new(
+                lane(&self.motion_x, idx),
+                lane(&self.motion_y, idx),
+                lane(&self.motion_z, idx),
+            ),
+            main_color: lane(&self.main_color, idx),
+            secondary_color: lane(&self.secondary_color, idx),
+            damage: lane(&self.damage, idx),
+            ticks_existed: lane(&self.ticks_existed, idx),
+            end_time: lane(&self.end_time, idx),
+        }
+    }
+
     fn add_danmaku_at_idx(
         &mut self,
         i: usize,
         danmaku: DanmakuSpawnData<StandardSpawnData, StandardDataColumns>,
         id: i128,
     ) -> Vec<DanmakuSpawnData<StandardSpawnData, StandardDataColumns>> {
-        fn transfer_data_simd<A: SimdElement>(
-            required_columns: EnumSet<StandardDataColumns>,
-            i: usize,
-            required: StandardDataColumns,
-            vec: &mut [Simd<A, N>],
-            data: A,
-        ) {
-            if required_columns.contains(required) {
-                vec[i.div_ceil(N)][i % N] = data;
-            }
-        }
-
-        fn transfer_data<A>(
-            required_columns: EnumSet<StandardDataColumns>,
-            i: usize,
-            required: StandardDataColumns,
-            vec: &mut [A],
-            data: A,
-        ) {
-            if required_columns.contains(required) {
-                vec[i] = data;
-            }
+        self.invalidate_transform_mats();
+        if self.dead[i] {
+            // Reusing a dead slot, which should no longer count as dead
+            self.current_dead.retain(|d| *d != i);
+            self.expired_this_tick.retain(|d| *d != i);
+            self.reset_slot(i);
         }
-
         self.id[i] = id;
+        self.user_data[i] = 0;
 
         let render_properties = danmaku.render_properties;
 
+        if self.required_columns.contains(StandardDataColumns::Trail) {
+            self.trail[i].clear();
+            self.trail_length[i] = DEFAULT_TRAIL_LENGTH;
+        }
+        if self
+            .required_columns
+            .contains(StandardDataColumns::SpawnInterval)
+        {
+            self.last_spawn_tick[i / N][i % N] = 0;
+        }
+
         for d in danmaku.behavior_data {
             match d {
-                StandardSpawnData::PosX(v) => {
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::PosX,
-                        &mut self.pos_x,
-                        v,
-                    );
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::PosX,
-                        &mut self.old_pos_x,
-                        v,
-                    );
-                }
-                StandardSpawnData::PosY(v) => {
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::PosY,
-                        &mut self.pos_y,
-                        v,
-                    );
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::PosY,
-                        &mut self.old_pos_y,
-                        v,
-                    );
-                }
-                StandardSpawnData::PosZ(v) => {
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::PosZ,
-                        &mut self.pos_z,
-                        v,
-                    );
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::PosZ,
-                        &mut self.old_pos_z,
-                        v,
-                    );
-                }
+                StandardSpawnData::PosX(v) => transfer!(self, i, simd PosX => pos_x, old_pos_x = v),
+                StandardSpawnData::PosY(v) => transfer!(self, i, simd PosY => pos_y, old_pos_y = v),
+                StandardSpawnData::PosZ(v) => transfer!(self, i, simd PosZ => pos_z, old_pos_z = v),
                 StandardSpawnData::Orientation(v) => {
-                    transfer_data(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::Orientation,
-                        &mut self.orientation,
-                        v,
-                    );
-                    transfer_data(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::Orientation,
-                        &mut self.old_orientation,
-                        v,
-                    );
+                    transfer!(self, i, Orientation => orientation, old_orientation = v)
                 }
                 StandardSpawnData::Appearance { form } => {
-                    transfer_data(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::Appearance,
-                        &mut self.form,
-                        form,
-                    );
-                    transfer_data(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::Appearance,
-                        &mut self.render_properties,
-                        render_properties.clone(),
-                    );
+                    transfer!(self, i, Appearance => form = form);
+                    transfer!(self, i, Appearance => render_properties = render_properties.clone());
                 }
                 StandardSpawnData::MainColor(v) => {
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::MainColor,
-                        &mut self.main_color,
-                        v,
-                    );
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::MainColor,
-                        &mut self.old_main_color,
-                        v,
-                    );
+                    transfer!(self, i, simd MainColor =>
+                        main_color, old_main_color, spawn_main_color = v)
                 }
                 StandardSpawnData::SecondaryColor(v) => {
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::SecondaryColor,
-                        &mut self.secondary_color,
-                        v,
-                    );
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::SecondaryColor,
-                        &mut self.old_secondary_color,
-                        v,
-                    );
+                    transfer!(self, i, simd SecondaryColor =>
+                        secondary_color, old_secondary_color = v)
                 }
-                StandardSpawnData::Damage(v) => {
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::Damage,
-                        &mut self.damage,
-                        v,
-                    );
+                StandardSpawnData::EndColor(v) => {
+                    transfer!(self, i, simd EndColor => end_color = v)
                 }
+                StandardSpawnData::Damage(v) => transfer!(self, i, simd Damage => damage = v),
                 StandardSpawnData::SizeX(v) => {
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::ScaleX,
-                        &mut self.scale_x,
-                        v,
-                    );
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::ScaleX,
-                        &mut self.old_scale_x,
-                        v,
-                    );
+                    transfer!(self, i, simd ScaleX => scale_x, old_scale_x = v)
                 }
                 StandardSpawnData::SizeY(v) => {
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::ScaleY,
-                        &mut self.scale_y,
-                        v,
-                    );
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::ScaleY,
-                        &mut self.old_scale_y,
-                        v,
-                    );
+                    transfer!(self, i, simd ScaleY => scale_y, old_scale_y = v)
                 }
                 StandardSpawnData::SizeZ(v) => {
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::ScaleZ,
-                        &mut self.scale_z,
-                        v,
-                    );
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::ScaleZ,
-                        &mut self.old_scale_z,
-                        v,
-                    );
+                    transfer!(self, i, simd ScaleZ => scale_z, old_scale_z = v)
+                }
+                StandardSpawnData::MotionX(v) => transfer!(self, i, simd MotionX => motion_x = v),
+                StandardSpawnData::MotionY(v) => transfer!(self, i, simd MotionY => motion_y = v),
+                StandardSpawnData::MotionZ(v) => transfer!(self, i, simd MotionZ => motion_z = v),
+                StandardSpawnData::GravityX(v) => {
+                    transfer!(self, i, simd GravityX => gravity_x = v)
+                }
+                StandardSpawnData::GravityY(v) => {
+                    transfer!(self, i, simd GravityY => gravity_y = v)
+                }
+                StandardSpawnData::GravityZ(v) => {
+                    transfer!(self, i, simd GravityZ => gravity_z = v)
+                }
+                StandardSpawnData::SpeedAccel(v) => {
+                    transfer!(self, i, simd SpeedAccel => speed_accel = v)
+                }
+                StandardSpawnData::MaxAccel(v) => {
+                    transfer!(self, i, simd MaxAccel => max_accel = v)
+                }
+                StandardSpawnData::GravitySource(v) => {
+                    transfer!(self, i, simd GravitySource => gravity_source_x = v.x);
+                    transfer!(self, i, simd GravitySource => gravity_source_y = v.y);
+                    transfer!(self, i, simd GravitySource => gravity_source_z = v.z);
+                }
+                StandardSpawnData::GravityStrength(v) => {
+                    transfer!(self, i, simd GravityStrength => gravity_strength = v)
+                }
+                StandardSpawnData::OrbitStrength(v) => {
+                    transfer!(self, i, simd OrbitStrength => orbit_strength = v)
+                }
+                StandardSpawnData::TurnRate(v) => {
+                    transfer!(self, i, simd TurnRate => turn_rate = v)
+                }
+                StandardSpawnData::ActivationTick(v) => {
+                    transfer!(self, i, simd ActivationTick => activation_tick = v)
                 }
-                StandardSpawnData::MotionX(v) => transfer_data_simd(
-                    self.required_columns,
-                    i,
-                    StandardDataColumns::MotionX,
-                    &mut self.motion_x,
-                    v,
-                ),
-                StandardSpawnData::MotionY(v) => transfer_data_simd(
-                    self.required_columns,
-                    i,
-                    StandardDataColumns::MotionY,
-                    &mut self.motion_x,
-                    v,
-                ),
-                StandardSpawnData::MotionZ(v) => transfer_data_simd(
-                    self.required_columns,
-                    i,
-                    StandardDataColumns::MotionZ,
-                    &mut self.motion_x,
-                    v,
-                ),
-                StandardSpawnData::GravityX(v) => transfer_data_simd(
-                    self.required_columns,
-                    i,
-                    StandardDataColumns::GravityX,
-                    &mut self.motion_x,
-                    v,
-                ),
-                StandardSpawnData::GravityY(v) => transfer_data_simd(
-                    self.required_columns,
-                    i,
-                    StandardDataColumns::GravityY,
-                    &mut self.motion_x,
-                    v,
-                ),
-                StandardSpawnData::GravityZ(v) => transfer_data_simd(
-                    self.required_columns,
-                    i,
-                    StandardDataColumns::GravityZ,
-                    &mut self.motion_x,
-                    v,
-                ),
-                StandardSpawnData::SpeedAccel(v) => transfer_data_simd(
-                    self.required_columns,
-                    i,
-                    StandardDataColumns::SpeedAccel,
-                    &mut self.speed_accel,
-                    v,
-                ),
                 StandardSpawnData::Forward(v) => {
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::Forward,
-                        &mut self.forward_x,
-                        v.x,
-                    );
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::Forward,
-                        &mut self.forward_z,
-                        v.y,
-                    );
-                    transfer_data_simd(
-                        self.required_columns,
-                        i,
-                        StandardDataColumns::Forward,
-                        &mut self.forward_z,
-                        v.z,
-                    );
+                    transfer!(self, i, simd Forward => forward_x = v.x);
+                    transfer!(self, i, simd Forward => forward_y = v.y);
+                    transfer!(self, i, simd Forward => forward_z = v.z);
                 }
-                StandardSpawnData::Rotation(v) => transfer_data(
-                    self.required_columns,
-                    i,
-                    StandardDataColumns::Rotation,
-                    &mut self.rotation,
-                    v,
-                ),
+                StandardSpawnData::Rotation(v) => transfer!(self, i, Rotation => rotation = v),
+                StandardSpawnData::TrailLength(v) => transfer!(self, i, Trail => trail_length = v),
+                StandardSpawnData::UserData(v) => self.user_data[i] = v,
             }
         }
 
-        self.ticks_existed[i.div_ceil(N)][i % N] = 0;
-        self.end_time[i.div_ceil(N)][i % N] = danmaku.end_time;
+        if self
+            .required_columns
+            .contains(StandardDataColumns::ActivationTick)
+        {
+            self.hold_for_delay(i);
+        }
+
+        // A reused slot still holds the previous state of the danmaku that was there before, which
+        // the first frame would otherwise interpolate from
+        fn reset_old_simd<A: SimdElement>(
+            required_columns: EnumSet<StandardDataColumns>,
+            i: usize,
+            required: StandardDataColumns,
+            old: &mut [Simd<A, N>],
+            new: &[Simd<A, N>],
+        ) {
+            if required_columns.contains(required) {
+                old[i / N][i % N] = new[i / N][i % N];
+            }
+        }
+
+        let required = self.required_columns;
+        reset_old_simd(
+            required,
+            i,
+            StandardDataColumns::PosX,
+            &mut self.old_pos_x,
+            &self.pos_x,
+        );
+        reset_old_simd(
+            required,
+            i,
+            StandardDataColumns::PosY,
+            &mut self.old_pos_y,
+            &self.pos_y,
+        );
+        reset_old_simd(
+            required,
+            i,
+            StandardDataColumns::PosZ,
+            &mut self.old_pos_z,
+            &self.pos_z,
+        );
+        reset_old_simd(
+            required,
+            i,
+            StandardDataColumns::ScaleX,
+            &mut self.old_scale_x,
+            &self.scale_x,
+        );
+        reset_old_simd(
+            required,
+            i,
+            StandardDataColumns::ScaleY,
+            &mut self.old_scale_y,
+            &self.scale_y,
+        );
+        reset_old_simd(
+            required,
+            i,
+            StandardDataColumns::ScaleZ,
+            &mut self.old_scale_z,
+            &self.scale_z,
+        );
+        reset_old_simd(
+            required,
+            i,
+            StandardDataColumns::MainColor,
+            &mut self.old_main_color,
+            &self.main_color,
+        );
+        reset_old_simd(
+            required,
+            i,
+            StandardDataColumns::SecondaryColor,
+            &mut self.old_secondary_color,
+            &self.secondary_color,
+        );
+        if required.contains(StandardDataColumns::Orientation) {
+            self.old_orientation[i] = self.orientation[i];
+        }
+
+        self.ticks_existed[i / N][i % N] = 0;
+        self.end_time[i / N][i % N] = danmaku.end_time;
         self.dead[i] = false;
+        self.alive_chunks[i / N] = true;
         self.next_stage[i] = danmaku.next_stage;
         self.next_stage_add_data[i] = danmaku.next_stage_add_data;
+        self.next_stage_inherit[i] = danmaku.next_stage_inherit;
         self.parent[i] = danmaku.parent.unwrap_or(-1);
         self.family_depth[i] = danmaku.family_depth;
 
@@ -966,107 +1931,98 @@ impl DanmakuData for StandardColumns {
     }
 
     fn compute_transform_mats(&mut self, current_size: usize, partial_ticks: f32) {
-        let required_main_columns = self.required_columns;
+        let partial_ticks = sanitize_partial_ticks(partial_ticks);
+        if self.transform_mats_partial_ticks == Some(partial_ticks) {
+            return;
+        }
+        self.transform_mats_partial_ticks = Some(partial_ticks);
+        self.non_finite_transform.fill(false);
 
-        #[inline]
-        fn lerp_if_used(
-            partial_ticks: f32,
-            used: bool,
-            i: usize,
-            old: &[Simd<f32, N>],
-            new: &[Simd<f32, N>],
-        ) -> f32 {
-            if used {
-                nalgebra_glm::lerp_scalar(
-                    old[i.div_ceil(N)][i % N],
-                    new[i.div_ceil(N)][i % N],
-                    partial_ticks,
-                )
-            } else {
-                0.0
+        for chunk in 0..current_size.div_ceil(N) {
+            if !self.alive_chunks[chunk] {
+                continue;
             }
-        }
 
-        if required_main_columns.contains(StandardDataColumns::Appearance) {
-            let requires_scale_x = required_main_columns.contains(StandardDataColumns::ScaleX);
-            let requires_scale_y = required_main_columns.contains(StandardDataColumns::ScaleY);
-            let requires_scale_z = required_main_columns.contains(StandardDataColumns::ScaleZ);
-            let requires_pos_x = required_main_columns.contains(StandardDataColumns::PosX);
-            let requires_pos_y = required_main_columns.contains(StandardDataColumns::PosY);
-            let requires_pos_z = required_main_columns.contains(StandardDataColumns::PosZ);
-            let requires_orientation =
-                required_main_columns.contains(StandardDataColumns::Orientation);
-
-            let mut temp = Matrix4::identity();
-
-            let pos_x = &self.pos_x;
-            let pos_y = &self.pos_y;
-            let pos_z = &self.pos_z;
-            let old_pos_x = &self.old_pos_x;
-            let old_pos_y = &self.old_pos_y;
-            let old_pos_z = &self.old_pos_z;
-
-            let scale_x = &self.scale_x;
-            let scale_y = &self.scale_y;
-            let scale_z = &self.scale_z;
-            let old_scale_x = &self.old_scale_x;
-            let old_scale_y = &self.old_scale_y;
-            let old_scale_z = &self.old_scale_z;
-
-            let orientation = &self.orientation;
-            let old_orientation = &self.old_orientation;
-
-            let dead = &self.dead;
-
-            for i in 0..current_size {
-                if !dead[i] {
-                    temp.fill_with_identity();
-
-                    temp.append_nonuniform_scaling_mut(&Vector3::new(
-                        lerp_if_used(partial_ticks, requires_scale_x, i, old_scale_x, scale_x),
-                        lerp_if_used(partial_ticks, requires_scale_y, i, old_scale_y, scale_y),
-                        lerp_if_used(partial_ticks, requires_scale_z, i, old_scale_z, scale_z),
-                    ));
-
-                    if requires_pos_x || requires_pos_y || requires_pos_z {
-                        temp.append_translation_mut(&Vector3::new(
-                            lerp_if_used(partial_ticks, requires_pos_x, i, old_pos_x, pos_x),
-                            lerp_if_used(partial_ticks, requires_pos_y, i, old_pos_y, pos_y),
-                            lerp_if_used(partial_ticks, requires_pos_z, i, old_pos_z, pos_z),
-                        ));
-                    }
-
-                    let orientation_mat = if requires_orientation {
-                        old_orientation
-                            .get(i)
-                            .unwrap_or(&UnitQuaternion::identity())
-                            .slerp(
-                                orientation.get(i).unwrap_or(&UnitQuaternion::identity()),
-                                partial_ticks,
-                            )
-                            .to_homogeneous()
-                    } else {
-                        orientation
-                            .get(i)
-                            .unwrap_or(&UnitQuaternion::identity())
-                            .to_homogeneous()
-                    };
+            let chunk_end = ((chunk + 1) * N).min(current_size);
+            for i in chunk * N..chunk_end {
+                if self.dead[i] {
+                    continue;
+                }
 
-                    self.transform_mats[i] = orientation_mat * temp;
+                let transform = self.local_transform(i, partial_ticks);
+                let finite = transform.iter().all(|v| v.is_finite());
+                // Most likely a bug in a behavior, so debug builds stop here. Tests put in
+                // non-finite values on purpose, to check they are skipped
+                debug_assert!(
+                    finite || cfg!(test),
+                    "Danmaku {} has a non-finite transform",
+                    self.id[i]
+                );
+
+                if finite {
+                    self.transform_mats[i] = transform;
+                } else {
+                    // Something like normalizing a zero vector. Kept out of the transforms so it
+                    // can't spread to the children of the danmaku
+                    log::warn!(
+                        "Danmaku {} has a non-finite transform, skipping it",
+                        self.id[i]
+                    );
+                    self.transform_mats[i] = Matrix4::identity();
+                    self.non_finite_transform[i] = true;
                 }
             }
         }
     }
 
-    fn compute_and_get_render_data(
+    fn transform_mat_of(&mut self, idx: usize, partial_ticks: f32) -> Option<Matrix4<f32>> {
+        if *self.dead.get(idx)? {
+            return None;
+        }
+
+        // Reuses this frame's transforms if they are already there
+        let partial_ticks = sanitize_partial_ticks(partial_ticks);
+        let transform = if self.transform_mats_partial_ticks == Some(partial_ticks) {
+            if self.non_finite_transform[idx] {
+                return None;
+            }
+            self.transform_mats[idx]
+        } else {
+            self.local_transform(idx, partial_ticks)
+        };
+
+        transform.iter().all(|v| v.is_finite()).then_some(transform)
+    }
+
+    fn compute_and_get_transform_mats(
         &mut self,
         current_size: usize,
         partial_ticks: f32,
-    ) -> Vec<(i128, RenderData)> {
+    ) -> Vec<(i128, Matrix4<f32>)> {
         self.compute_transform_mats(current_size, partial_ticks);
 
+        (0..current_size)
+            .filter(|i| !self.dead[*i] && !self.non_finite_transform[*i])
+            .map(|i| (self.id[i], self.transform_mats[i]))
+            .collect()
+    }
+
+    fn compute_and_get_render_data(
+        &mut self,
+        current_size: usize,
+        partial_ticks: f32,
+    ) -> Vec<(i128, RenderData<'_>)> {
+        let partial_ticks = sanitize_partial_ticks(partial_ticks);
+        if self
+            .required_columns
+            .contains(StandardDataColumns::Appearance)
+        {
+            self.compute_transform_mats(current_size, partial_ticks);
+        }
+
         let form = &self.form;
         let render_properties = &self.render_properties;
+        let trail = &self.trail;
         let transform_mats = &self.transform_mats;
         let main_color = &self.main_color;
         let old_main_color = &self.old_main_color;
@@ -1074,7 +2030,9 @@ impl DanmakuData for StandardColumns {
         let old_secondary_color = &self.old_secondary_color;
         let ticks_existed = &self.ticks_existed;
         let end_time = &self.end_time;
+        let user_data = &self.user_data;
         let dead = &self.dead;
+        let non_finite_transform = &self.non_finite_transform;
         let id = &self.id;
 
         let has_main_color = self
@@ -1084,42 +2042,76 @@ impl DanmakuData for StandardColumns {
             .required_columns
             .contains(StandardDataColumns::SecondaryColor);
 
+        // Rgb is lerped a chunk at a time up front, while the other modes are done per danmaku
+        let color_lerp_mode = self.handler_context.color_lerp_mode;
+        let lerp_rgb_chunks = |has_color: bool,
+                               new: &Vec<Simd<i32, N>>,
+                               old: &Vec<Simd<i32, N>>|
+         -> Vec<Simd<i32, N>> {
+            if has_color && color_lerp_mode == ColorLerpMode::Rgb {
+                old.iter()
+                    .zip(new)
+                    .take(current_size.div_ceil(N))
+                    .map(|(old, new)| simd_lerp_rgb(*old, *new, partial_ticks))
+                    .collect()
+            } else {
+                vec![]
+            }
+        };
+        let main_color_rgb = lerp_rgb_chunks(has_main_color, main_color, old_main_color);
+        let secondary_color_rgb =
+            lerp_rgb_chunks(has_secondary_color, secondary_color, old_secondary_color);
+
         if self
             .required_columns
             .contains(StandardDataColumns::Appearance)
         {
             (0..current_size)
-                .filter(|i| !dead.get(*i).unwrap_or(&false))
+                .filter(|i| {
+                    !dead.get(*i).unwrap_or(&false)
+                        && !non_finite_transform.get(*i).unwrap_or(&false)
+                })
                 .map(|i| (id.get(i).unwrap_or(&0), i))
                 .map(|(id, i)| {
                     let lerp_color = |has_color: bool,
                                       new: &Vec<Simd<i32, N>>,
-                                      old: &Vec<Simd<i32, N>>|
+                                      old: &Vec<Simd<i32, N>>,
+                                      rgb: &Vec<Simd<i32, N>>|
                      -> ColorHex {
-                        if has_color {
-                            ColorHex(new[i.div_ceil(N)][i % N]).lerp_through_hsv(
-                                ColorHex(old[i.div_ceil(N)][i % N]),
+                        if !has_color {
+                            ColorHex(0)
+                        } else if color_lerp_mode == ColorLerpMode::Rgb {
+                            ColorHex(rgb[i / N][i % N])
+                        } else {
+                            ColorHex(old[i / N][i % N]).lerp(
+                                ColorHex(new[i / N][i % N]),
                                 partial_ticks,
+                                color_lerp_mode,
                             )
-                        } else {
-                            ColorHex(0)
                         }
                     };
 
-                    let main_color = lerp_color(has_main_color, main_color, old_main_color);
-                    let secondary_color =
-                        lerp_color(has_secondary_color, secondary_color, old_secondary_color);
+                    let main_color =
+                        lerp_color(has_main_color, main_color, old_main_color, &main_color_rgb);
+                    let secondary_color = lerp_color(
+                        has_secondary_color,
+                        secondary_color,
+                        old_secondary_color,
+                        &secondary_color_rgb,
+                    );
 
                     (
                         *id,
                         RenderData {
                             form: form.get(i).unwrap(),
                             render_properties: render_properties.get(i).unwrap(),
+                            trail: trail.get(i).map_or(&[], |t| t.as_slice()),
                             model_mat: *transform_mats.get(i).unwrap_or(&Matrix4::identity()),
-                            main_color: main_color.0,
-                            secondary_color: secondary_color.0,
-                            ticks_existed: ticks_existed[i.div_ceil(N)][i & N],
-                            end_time: end_time[i.div_ceil(N)][i & N],
+                            main_color,
+                            secondary_color,
+                            ticks_existed: ticks_existed[i / N][i % N],
+                            end_time: end_time[i / N][i % N],
+                            user_data: user_data[i],
                         },
                     )
                 })
@@ -1128,17 +2120,90 @@ impl DanmakuData for StandardColumns {
             vec![]
         }
     }
+
+    fn collect_damage(
+        &mut self,
+        current_size: usize,
+        world_transforms: &HashMap<i128, Matrix4<f32>>,
+        hitbox: (Vector3<f32>, f32),
+    ) -> f32 {
+        if !self.required_columns.contains(StandardDataColumns::Damage) {
+            return 0.0;
+        }
+
+        let (hitbox_center, hitbox_radius) = hitbox;
+        let mut total_damage = 0.0;
+
+        for i in 0..current_size {
+            if self.dead[i] {
+                continue;
+            }
+
+            // Danmaku without a world transform have a parent that is gone
+            let Some(transform) = world_transforms.get(&self.id[i]) else {
+                continue;
+            };
+
+            let center = transform.column(3).xyz();
+            let scale = (0..3)
+                .map(|c| transform.column(c).xyz().norm())
+                .fold(0.0, f32::max);
+            let form_radius = self.form.get(i).map_or(1.0, |f| f.bounding_radius());
+
+            if (center - hitbox_center).norm() <= scale * form_radius + hitbox_radius {
+                total_damage += self.damage[i / N][i % N];
+                self.kill(i);
+            }
+        }
+
+        total_damage
+    }
+
+    fn translate(&mut self, current_size: usize, delta: Vector3<f32>) {
+        self.invalidate_transform_mats();
+
+        let chunks = current_size.div_ceil(N);
+        for (required, pos, old_pos, delta) in [
+            (
+                StandardDataColumns::PosX,
+                &mut self.pos_x,
+                &mut self.old_pos_x,
+                delta.x,
+            ),
+            (
+                StandardDataColumns::PosY,
+                &mut self.pos_y,
+                &mut self.old_pos_y,
+                delta.y,
+            ),
+            (
+                StandardDataColumns::PosZ,
+                &mut self.pos_z,
+                &mut self.old_pos_z,
+                delta.z,
+            ),
+        ] {
+            if !self.required_columns.contains(required) {
+                continue;
+            }
+
+            let delta = Simd::splat(Pos::from(delta));
+            pos[0..chunks].iter_mut().for_each(|p| *p += delta);
+            old_pos[0..chunks].iter_mut().for_each(|p| *p += delta);
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
 pub enum StandardSpawnData {
-    PosX(f32),
-    PosY(f32),
-    PosZ(f32),
+    PosX(Pos),
+    PosY(Pos),
+    PosZ(Pos),
     Orientation(UnitQuaternion<f32>),
     Appearance { form: &'static Form },
     MainColor(i32),
     SecondaryColor(i32),
+    EndColor(i32),
     Damage(f32),
     SizeX(f32),
     SizeY(f32),
@@ -1153,8 +2218,54 @@ pub enum StandardSpawnData {
     GravityZ(f32),
 
     SpeedAccel(f32),
+    MaxAccel(f32),
+    GravitySource(Vector3<f32>),
+    GravityStrength(f32),
+    OrbitStrength(f32),
+    TurnRate(f32),
+    ActivationTick(i16),
     Forward(UnitVector3<f32>),
     Rotation(UnitQuaternion<f32>),
+    TrailLength(u16),
+    UserData(u64),
+}
+
+impl StandardSpawnData {
+    // The column the data is stored in, if it isn't one that is always allocated
+    pub fn column(&self) -> Option<StandardDataColumns> {
+        match self {
+            StandardSpawnData::PosX(_) => Some(StandardDataColumns::PosX),
+            StandardSpawnData::PosY(_) => Some(StandardDataColumns::PosY),
+            StandardSpawnData::PosZ(_) => Some(StandardDataColumns::PosZ),
+            StandardSpawnData::Orientation(_) => Some(StandardDataColumns::Orientation),
+            StandardSpawnData::Appearance { .. } => Some(StandardDataColumns::Appearance),
+            StandardSpawnData::MainColor(_) => Some(StandardDataColumns::MainColor),
+            StandardSpawnData::SecondaryColor(_) => Some(StandardDataColumns::SecondaryColor),
+            StandardSpawnData::EndColor(_) => Some(StandardDataColumns::EndColor),
+            StandardSpawnData::Damage(_) => Some(StandardDataColumns::Damage),
+            StandardSpawnData::SizeX(_) => Some(StandardDataColumns::ScaleX),
+            StandardSpawnData::SizeY(_) => Some(StandardDataColumns::ScaleY),
+            StandardSpawnData::SizeZ(_) => Some(StandardDataColumns::ScaleZ),
+            StandardSpawnData::MotionX(_) => Some(StandardDataColumns::MotionX),
+            StandardSpawnData::MotionY(_) => Some(StandardDataColumns::MotionY),
+            StandardSpawnData::MotionZ(_) => Some(StandardDataColumns::MotionZ),
+            StandardSpawnData::GravityX(_) => Some(StandardDataColumns::GravityX),
+            StandardSpawnData::GravityY(_) => Some(StandardDataColumns::GravityY),
+            StandardSpawnData::GravityZ(_) => Some(StandardDataColumns::GravityZ),
+            StandardSpawnData::SpeedAccel(_) => Some(StandardDataColumns::SpeedAccel),
+            StandardSpawnData::MaxAccel(_) => Some(StandardDataColumns::MaxAccel),
+            StandardSpawnData::GravitySource(_) => Some(StandardDataColumns::GravitySource),
+            StandardSpawnData::GravityStrength(_) => Some(StandardDataColumns::GravityStrength),
+            StandardSpawnData::OrbitStrength(_) => Some(StandardDataColumns::OrbitStrength),
+            StandardSpawnData::TurnRate(_) => Some(StandardDataColumns::TurnRate),
+            StandardSpawnData::ActivationTick(_) => Some(StandardDataColumns::ActivationTick),
+            StandardSpawnData::Forward(_) => Some(StandardDataColumns::Forward),
+            StandardSpawnData::Rotation(_) => Some(StandardDataColumns::Rotation),
+            StandardSpawnData::TrailLength(_) => Some(StandardDataColumns::Trail),
+            // Always allocated
+            StandardSpawnData::UserData(_) => None,
+        }
+    }
 }
 
 #[derive(Debug, Hash, EnumSetType)]
@@ -1168,6 +2279,7 @@ pub enum StandardDataColumns {
     Orientation,
     MainColor,
     SecondaryColor,
+    EndColor,
     Damage,
     Appearance,
 
@@ -1178,7 +2290,293 @@ pub enum StandardDataColumns {
     GravityY,
     GravityZ,
     SpeedAccel,
+    MaxAccel,
+    GravitySource,
+    GravityStrength,
+    OrbitStrength,
+    TurnRate,
+    ActivationTick,
+    SpawnInterval,
 
     Rotation,
     Forward,
+    Trail,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::danmaku::standard::{behaviors::motion3_behavior, test_util::spawn};
+
+    #[test]
+    fn small_motion_far_from_the_origin() {
+        let motion3 = motion3_behavior();
+        let mut columns = StandardColumns::new(N, motion3.required_columns);
+        columns.add_danmaku_at_idx(
+            0,
+            spawn(
+                100,
+                &[],
+                vec![
+                    StandardSpawnData::PosX(Pos::from(1e6_f32)),
+                    StandardSpawnData::MotionX(0.01),
+                ],
+            ),
+            0,
+        );
+
+        for _ in 0..10 {
+            (motion3.act)(&mut columns, 1);
+        }
+
+        let moved = columns.pos_x[0][0] - Pos::from(1e6_f32);
+        if cfg!(feature = "f64_positions") {
+            assert!((moved - 0.1).abs() < 1e-6);
+        } else {
+            // A step of 0.01 is below the precision of f32 this far out, so it is lost entirely
+            assert_eq!(moved, 0.0);
+        }
+    }
+
+    #[test]
+    fn compacting_a_partly_used_last_chunk() {
+        // Not a multiple of N, with dead danmaku in the first and the partly used last chunk
+        let size = 2 * N + 3;
+        let mut columns = StandardColumns::new(
+            size,
+            StandardDataColumns::PosX | StandardDataColumns::MotionX,
+        );
+        for i in 0..size {
+            columns.add_danmaku_at_idx(
+                i,
+                spawn(
+                    100,
+                    &[],
+                    vec![
+                        StandardSpawnData::PosX(Pos::from(i as f32)),
+                        StandardSpawnData::MotionX(i as f32),
+                    ],
+                ),
+                i as i128,
+            );
+        }
+        let dead = [0, 2 * N + 1, 2 * N + 2];
+        for i in dead {
+            columns.kill(i);
+        }
+
+        let kept: Vec<usize> = (0..size).filter(|i| !dead.contains(i)).collect();
+        columns.compact(kept.len());
+
+        for (new_idx, old_idx) in kept.iter().enumerate() {
+            assert_eq!(columns.id[new_idx], *old_idx as i128);
+            assert_eq!(
+                columns.pos_x[new_idx / N][new_idx % N],
+                Pos::from(*old_idx as f32)
+            );
+            assert_eq!(columns.motion_x[new_idx / N][new_idx % N], *old_idx as f32);
+            assert!(!columns.dead[new_idx]);
+        }
+        assert_eq!(columns.motion_x.len(), kept.len().div_ceil(N));
+        // Lanes past the end are reset, rather than holding what was moved out of them
+        for idx in kept.len()..columns.motion_x.len() * N {
+            assert_eq!(columns.motion_x[idx / N][idx % N], 0.0);
+        }
+    }
+
+    #[test]
+    fn summed_motion_only_covers_live_danmaku() {
+        use StandardDataColumns as C;
+
+        let mut columns = StandardColumns::new(3 * N, C::MotionX | C::MotionY | C::MotionZ);
+        for i in 0..3 * N {
+            columns.add_danmaku_at_idx(
+                i,
+                spawn(
+                    100,
+                    &[],
+                    vec![
+                        StandardSpawnData::MotionX(3.0),
+                        StandardSpawnData::MotionY(4.0),
+                    ],
+                ),
+                i as i128,
+            );
+        }
+        columns.kill(1);
+
+        // Lanes from size on are left out, even if they hold something
+        let size = 2 * N + 1;
+        let live = (size - 1) as f32;
+        assert_eq!(columns.sum_motion_magnitude(size), 5.0 * live);
+        assert_eq!(
+            columns.sum_motion(size),
+            Vector3::new(3.0 * live, 4.0 * live, 0.0)
+        );
+        // Not allocated, so there is nothing to sum
+        assert_eq!(columns.sum_damage(size), 0.0);
+    }
+
+    #[test]
+    fn sums_add_up_danmaku_in_order() {
+        // Far apart in magnitude, so adding them up in another order rounds differently
+        let motions: Vec<f32> = (0..4 * N + 3)
+            .map(|i| match i % 4 {
+                0 => 1e8,
+                1 => 0.75,
+                2 => -1e8,
+                _ => 3.3,
+            })
+            .collect();
+        let size = motions.len();
+        let mut columns = StandardColumns::new(size, EnumSet::only(StandardDataColumns::MotionX));
+        for (i, motion) in motions.iter().enumerate() {
+            columns.add_danmaku_at_idx(
+                i,
+                spawn(100, &[], vec![StandardSpawnData::MotionX(*motion)]),
+                i as i128,
+            );
+        }
+        columns.kill(5);
+
+        let expected = motions
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 5)
+            .fold(0.0_f32, |sum, (_, v)| sum + v);
+        assert_eq!(columns.sum_motion(size).x.to_bits(), expected.to_bits());
+    }
+
+    // One of every variant, with values no column starts out with
+    fn every_spawn_data() -> Vec<StandardSpawnData> {
+        let orientation = UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3);
+        let rotation = UnitQuaternion::from_euler_angles(0.4, 0.5, 0.6);
+        let forward = UnitVector3::new_normalize(Vector3::new(1.0, 2.0, 3.0));
+        vec![
+            StandardSpawnData::PosX(Pos::from(1.0_f32)),
+            StandardSpawnData::PosY(Pos::from(2.0_f32)),
+            StandardSpawnData::PosZ(Pos::from(3.0_f32)),
+            StandardSpawnData::Orientation(orientation),
+            StandardSpawnData::Appearance { form: &Form::BOX },
+            StandardSpawnData::MainColor(0x111111),
+            StandardSpawnData::SecondaryColor(0x222222),
+            StandardSpawnData::EndColor(0x333333),
+            StandardSpawnData::Damage(4.0),
+            StandardSpawnData::SizeX(5.0),
+            StandardSpawnData::SizeY(6.0),
+            StandardSpawnData::SizeZ(7.0),
+            StandardSpawnData::MotionX(8.0),
+            StandardSpawnData::MotionY(9.0),
+            StandardSpawnData::MotionZ(10.0),
+            StandardSpawnData::GravityX(11.0),
+            StandardSpawnData::GravityY(12.0),
+            StandardSpawnData::GravityZ(13.0),
+            StandardSpawnData::SpeedAccel(14.0),
+            StandardSpawnData::MaxAccel(15.0),
+            StandardSpawnData::GravitySource(Vector3::new(16.0, 17.0, 18.0)),
+            StandardSpawnData::GravityStrength(19.0),
+            StandardSpawnData::OrbitStrength(20.0),
+            StandardSpawnData::TurnRate(21.0),
+            StandardSpawnData::ActivationTick(22),
+            StandardSpawnData::Forward(forward),
+            StandardSpawnData::Rotation(rotation),
+            StandardSpawnData::TrailLength(23),
+            StandardSpawnData::UserData(24),
+        ]
+    }
+
+    #[test]
+    fn every_spawn_data_goes_to_its_own_column() {
+        let orientation = UnitQuaternion::from_euler_angles(0.1, 0.2, 0.3);
+        let rotation = UnitQuaternion::from_euler_angles(0.4, 0.5, 0.6);
+        let forward = UnitVector3::new_normalize(Vector3::new(1.0, 2.0, 3.0));
+        let data = every_spawn_data();
+        let columns_used: EnumSet<StandardDataColumns> =
+            data.iter().filter_map(StandardSpawnData::column).collect();
+
+        // The last lane of the second chunk, to catch mixed up chunk and lane indices
+        let i = 2 * N - 1;
+        let mut columns = StandardColumns::new(2 * N, EnumSet::all());
+        columns.add_danmaku_at_idx(i, spawn(100, &[], data), 1);
+        let simd = |column: &Vec<Simd<f32, N>>| column[i / N][i % N];
+
+        assert_eq!(columns.pos_x[i / N][i % N], Pos::from(1.0_f32));
+        assert_eq!(columns.pos_y[i / N][i % N], Pos::from(2.0_f32));
+        assert_eq!(columns.pos_z[i / N][i % N], Pos::from(3.0_f32));
+        assert_eq!(columns.orientation[i], orientation);
+        assert_eq!(columns.form[i].id(), "box");
+        assert_eq!(columns.main_color[i / N][i % N], 0x111111);
+        assert_eq!(columns.secondary_color[i / N][i % N], 0x222222);
+        assert_eq!(columns.end_color[i / N][i % N], 0x333333);
+        assert_eq!(simd(&columns.damage), 4.0);
+        assert_eq!(simd(&columns.scale_x), 5.0);
+        assert_eq!(simd(&columns.scale_y), 6.0);
+        assert_eq!(simd(&columns.scale_z), 7.0);
+        assert_eq!(simd(&columns.motion_x), 8.0);
+        assert_eq!(simd(&columns.motion_y), 9.0);
+        assert_eq!(simd(&columns.motion_z), 10.0);
+        assert_eq!(simd(&columns.gravity_x), 11.0);
+        assert_eq!(simd(&columns.gravity_y), 12.0);
+        assert_eq!(simd(&columns.gravity_z), 13.0);
+        assert_eq!(simd(&columns.speed_accel), 14.0);
+        assert_eq!(simd(&columns.max_accel), 15.0);
+        assert_eq!(simd(&columns.gravity_source_x), 16.0);
+        assert_eq!(simd(&columns.gravity_source_y), 17.0);
+        assert_eq!(simd(&columns.gravity_source_z), 18.0);
+        assert_eq!(simd(&columns.gravity_strength), 19.0);
+        assert_eq!(simd(&columns.orbit_strength), 20.0);
+        assert_eq!(simd(&columns.turn_rate), 21.0);
+        assert_eq!(columns.activation_tick[i / N][i % N], 22);
+        assert_eq!(simd(&columns.forward_x), forward.x);
+        assert_eq!(simd(&columns.forward_y), forward.y);
+        assert_eq!(simd(&columns.forward_z), forward.z);
+        assert_eq!(columns.rotation[i], rotation);
+        assert_eq!(columns.trail_length[i], 23);
+        assert_eq!(columns.user_data[i], 24);
+
+        // Nothing was written to the other danmaku
+        let other = StandardColumns::new(2 * N, EnumSet::all());
+        assert_eq!(columns.motion_x[0], other.motion_x[0]);
+        assert_eq!(columns.pos_x[0], other.pos_x[0]);
+
+        // Spawners only need their column for the state they keep, so there is nothing to spawn with
+        assert_eq!(
+            EnumSet::all() - columns_used,
+            EnumSet::only(StandardDataColumns::SpawnInterval)
+        );
+    }
+
+    #[test]
+    fn spawn_data_round_trips_through_the_columns() {
+        let data = every_spawn_data();
+
+        let mut columns = StandardColumns::new(2 * N, EnumSet::all());
+        columns.add_danmaku_at_idx(0, spawn(100, &[], data.clone()), 1);
+        let read = columns.spawn_data(0);
+        assert_eq!(format!("{:?}", read), format!("{:?}", data));
+
+        columns.add_danmaku_at_idx(2 * N - 1, spawn(100, &[], read), 2);
+        assert_eq!(
+            format!("{:?}", columns.spawn_data(2 * N - 1)),
+            format!("{:?}", data)
+        );
+    }
+
+    #[test]
+    fn spawn_data_only_covers_allocated_columns() {
+        let mut columns = StandardColumns::new(N, StandardDataColumns::PosX.into());
+        columns.add_danmaku_at_idx(0, spawn(100, &[], vec![StandardSpawnData::MotionX(1.0)]), 1);
+
+        let read = columns.spawn_data(0);
+        assert_eq!(
+            format!("{:?}", read),
+            format!(
+                "{:?}",
+                [
+                    StandardSpawnData::PosX(Pos::from(0.0_f32)),
+                    StandardSpawnData::UserData(0)
+                ]
+            )
+        );
+    }
 }
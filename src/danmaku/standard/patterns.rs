@@ -0,0 +1,93 @@
+use nalgebra::{UnitVector3, Vector3};
+
+use crate::danmaku::{
+    data::DanmakuSpawnData,
+    standard::{StandardDataColumns, StandardSpawnData},
+};
+
+pub fn ring_spawn(
+    count: usize,
+    template: DanmakuSpawnData<StandardSpawnData, StandardDataColumns>,
+    speed: f32,
+    plane_normal: UnitVector3<f32>,
+) -> Vec<DanmakuSpawnData<StandardSpawnData, StandardDataColumns>> {
+    // Any axis not parallel to the normal can be used to find a vector in the plane
+    let helper_axis = if plane_normal.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+    let u = plane_normal.cross(&helper_axis).normalize();
+    let v = plane_normal.cross(&u);
+
+    (0..count)
+        .map(|k| {
+            let angle = std::f32::consts::TAU * k as f32 / count as f32;
+            let forward = UnitVector3::new_normalize(u * angle.cos() + v * angle.sin());
+
+            let mut danmaku = template.clone();
+            danmaku.behavior_data.retain(|d| {
+                !matches!(
+                    d,
+                    StandardSpawnData::Forward(_)
+                        | StandardSpawnData::MotionX(_)
+                        | StandardSpawnData::MotionY(_)
+                        | StandardSpawnData::MotionZ(_)
+                )
+            });
+            danmaku.behavior_data.extend([
+                StandardSpawnData::Forward(forward),
+                StandardSpawnData::MotionX(forward.x * speed),
+                StandardSpawnData::MotionY(forward.y * speed),
+                StandardSpawnData::MotionZ(forward.z * speed),
+            ]);
+            danmaku
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra::{UnitVector3, Vector3};
+
+    use super::ring_spawn;
+    use crate::danmaku::standard::{
+        test_util::{spawn, Spawn},
+        StandardSpawnData,
+    };
+
+    fn forward_of(danmaku: &Spawn) -> Vector3<f32> {
+        danmaku
+            .behavior_data
+            .iter()
+            .find_map(|d| match d {
+                StandardSpawnData::Forward(f) => Some(f.into_inner()),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn ring_of_eight_spreads_forwards_evenly() {
+        let template = spawn(100, &[], vec![StandardSpawnData::MotionX(5.0)]);
+        let ring = ring_spawn(8, template, 2.0, UnitVector3::new_normalize(Vector3::z()));
+        assert_eq!(ring.len(), 8);
+
+        let forwards: Vec<_> = ring.iter().map(forward_of).collect();
+        for (a, b) in forwards.iter().zip(forwards.iter().cycle().skip(1)) {
+            assert!((a.angle(b) - std::f32::consts::FRAC_PI_4).abs() < 1e-4);
+            assert!(a.z.abs() < 1e-6);
+        }
+        assert!(forwards.iter().sum::<Vector3<f32>>().norm() < 1e-5);
+
+        // The template's motion is replaced, not added to
+        for danmaku in &ring {
+            let motion_x = danmaku
+                .behavior_data
+                .iter()
+                .filter(|d| matches!(d, StandardSpawnData::MotionX(_)))
+                .count();
+            assert_eq!(motion_x, 1);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::danmaku::{
+    data::{DanmakuSpawnData, RenderData},
+    handlers::TopDanmakuBehaviorsHandler,
+    standard::{
+        behaviors::StandardTopHandlerExt, StandardColumns, StandardDataColumns, StandardSpawnData,
+    },
+};
+
+// Runs a scene without any rendering, for tests and benchmarks
+pub struct SimRunner {
+    handler: TopDanmakuBehaviorsHandler<StandardColumns>,
+}
+
+impl Default for SimRunner {
+    fn default() -> Self {
+        let mut handler = TopDanmakuBehaviorsHandler::new();
+        handler.register_standard_behaviors();
+        SimRunner { handler }
+    }
+}
+
+impl SimRunner {
+    pub fn new() -> SimRunner {
+        SimRunner::default()
+    }
+
+    // For registering extra behaviors, or inspecting the state between runs
+    pub fn handler(&mut self) -> &mut TopDanmakuBehaviorsHandler<StandardColumns> {
+        &mut self.handler
+    }
+
+    pub fn spawn(
+        &mut self,
+        danmaku: Vec<DanmakuSpawnData<StandardSpawnData, StandardDataColumns>>,
+    ) {
+        self.handler.add_danmaku(danmaku);
+    }
+
+    // Sorted by id, so runs with the same spawns can be compared directly
+    pub fn run(&mut self, ticks: usize) -> Vec<(i128, RenderData<'_>)> {
+        for _ in 0..ticks {
+            self.handler.tick();
+        }
+
+        // No interpolation back towards the previous tick, this is the state after the last tick
+        let mut render_data = self.handler.render_data_with_ids(1.0);
+        render_data.sort_by_key(|(id, _)| *id);
+        render_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::danmaku::standard::{behaviors::*, test_util::spawn};
+    use crate::form::Form;
+
+    fn gravity_ball() -> Vec<DanmakuSpawnData<StandardSpawnData, StandardDataColumns>> {
+        vec![spawn(
+            200,
+            &[
+                MOTION3_BEHAVIOR_ID,
+                GRAVITY3_BEHAVIOR_ID,
+                APPEARANCE_BEHAVIOR_ID,
+            ],
+            vec![
+                StandardSpawnData::Appearance {
+                    form: &Form::SPHERE,
+                },
+                StandardSpawnData::MotionX(0.5),
+                StandardSpawnData::MotionY(1.0),
+                StandardSpawnData::GravityY(-0.1),
+            ],
+        )]
+    }
+
+    #[test]
+    fn gravity_ball_runs_the_same_every_time() {
+        let mut runner = SimRunner::new();
+        runner.spawn(gravity_ball());
+
+        let render_data = runner.run(100);
+        assert_eq!(render_data.len(), 1);
+
+        // Gravity is scaled by the age, and applied before moving, so the motion of tick k is
+        // 1 - 0.05k(k - 1), adding up to 100 - 0.05 * 333300 over 100 ticks
+        let position = render_data[0].1.model_mat.column(3).xyz();
+        assert!((position.x - 50.0).abs() < 1e-2);
+        assert!((position.y - (100.0 - 0.05 * 333300.0)).abs() < 0.1);
+        let model_mat = render_data[0].1.model_mat;
+
+        let mut again = SimRunner::new();
+        again.spawn(gravity_ball());
+        assert_eq!(again.run(100)[0].1.model_mat, model_mat);
+    }
+}
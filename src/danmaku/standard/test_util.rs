@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use enumset::EnumSet;
+
+use crate::danmaku::{
+    data::DanmakuSpawnData,
+    handlers::TopDanmakuBehaviorsHandler,
+    standard::{
+        behaviors::StandardTopHandlerExt, StandardColumns, StandardDataColumns, StandardSpawnData,
+    },
+};
+
+pub(crate) type Spawn = DanmakuSpawnData<StandardSpawnData, StandardDataColumns>;
+
+pub(crate) fn spawn(
+    end_time: i16,
+    behaviors: &[&'static str],
+    data: Vec<StandardSpawnData>,
+) -> Spawn {
+    DanmakuSpawnData {
+        end_time,
+        behavior_data: data,
+        render_properties: HashMap::new(),
+        behaviors: behaviors.to_vec(),
+        next_stage_add_data: EnumSet::all(),
+        next_stage_inherit: HashMap::new(),
+        anchor: None,
+        next_stage: vec![],
+        parent: None,
+        children: vec![],
+        family_depth: -1,
+    }
+}
+
+pub(crate) fn standard_handler() -> TopDanmakuBehaviorsHandler<StandardColumns> {
+    let mut handler = TopDanmakuBehaviorsHandler::new();
+    handler.register_standard_behaviors();
+    handler
+}
@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DanCoreError {
+    UnknownBehavior(String),
+    // The behavior which needs columns that aren't allocated
+    ColumnNotAllocated(&'static str),
+    // The behavior, and the behavior it needs which is not part of the same handler
+    MissingBehavior(&'static str, &'static str),
+    DepthLimitExceeded,
+    ParseColor,
+    // The parameter of a behavior which was given a value it can't work with
+    InvalidParameter(&'static str),
+}
+
+impl Display for DanCoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DanCoreError::UnknownBehavior(id) => write!(f, "Unknown behavior {}", id),
+            DanCoreError::ColumnNotAllocated(behavior) => write!(
+                f,
+                "Behavior {} requires columns which are not allocated",
+                behavior
+            ),
+            DanCoreError::MissingBehavior(behavior, required) => write!(
+                f,
+                "Behavior {} requires the behavior {}, which is not used with it",
+                behavior, required
+            ),
+            DanCoreError::DepthLimitExceeded => write!(f, "Family depth limit exceeded"),
+            DanCoreError::ParseColor => write!(f, "Invalid color"),
+            DanCoreError::InvalidParameter(parameter) => {
+                write!(f, "Invalid value for parameter {}", parameter)
+            }
+        }
+    }
+}
+
+impl Error for DanCoreError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_variant_names_its_cause() {
+        assert_eq!(
+            DanCoreError::UnknownBehavior("spin".to_string()).to_string(),
+            "Unknown behavior spin"
+        );
+        assert_eq!(
+            DanCoreError::ColumnNotAllocated("motion3").to_string(),
+            "Behavior motion3 requires columns which are not allocated"
+        );
+        assert_eq!(
+            DanCoreError::MissingBehavior("clamp_accel", "accel_base").to_string(),
+            "Behavior clamp_accel requires the behavior accel_base, which is not used with it"
+        );
+        assert_eq!(
+            DanCoreError::DepthLimitExceeded.to_string(),
+            "Family depth limit exceeded"
+        );
+        assert_eq!(DanCoreError::ParseColor.to_string(), "Invalid color");
+        assert_eq!(
+            DanCoreError::InvalidParameter("period_ticks").to_string(),
+            "Invalid value for parameter period_ticks"
+        );
+    }
+
+    #[test]
+    fn is_a_std_error() {
+        let err: Box<dyn Error> = Box::new(DanCoreError::ParseColor);
+        assert!(err.source().is_none());
+    }
+}
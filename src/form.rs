@@ -1,17 +1,111 @@
+use std::collections::HashMap;
 use std::fmt::{Debug, Formatter};
 
+use nalgebra::Vector3;
+
 pub struct Form {
     id: &'static str,
+    bounding_radius: f32,
+    half_extents: [f32; 3],
     //client_form:
 }
 impl Form {
+    pub const POINT: Form = Form {
+        id: "point",
+        bounding_radius: 0.0,
+        half_extents: [0.0; 3],
+    };
+
     pub const SPHERE: Form = Form {
         id: "sphere",
+        bounding_radius: 1.0,
+        half_extents: [1.0; 3],
+    };
+
+    // Flat, for planar games where danmaku have no depth
+    pub const CIRCLE: Form = Form {
+        id: "circle",
+        bounding_radius: 1.0,
+        half_extents: [1.0, 1.0, 0.0],
+    };
+
+    pub const BOX: Form = Form {
+        id: "box",
+        // The distance to a corner of the box
+        bounding_radius: 1.732_050_8,
+        half_extents: [1.0; 3],
     };
+
+    pub fn id(&self) -> &'static str {
+        self.id
+    }
+
+    // The radius of a sphere containing the form at a scale of 1
+    pub fn bounding_radius(&self) -> f32 {
+        self.bounding_radius
+    }
+
+    // Half the size of the axis aligned box containing the form at a scale of 1
+    pub fn half_extents(&self) -> Vector3<f32> {
+        Vector3::from(self.half_extents)
+    }
 }
 
 impl Debug for Form {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "Form({})", self.id)
     }
-}
\ No newline at end of file
+}
+
+// Looks up forms by their id, for example when loading patterns
+pub struct FormRegistry {
+    forms: HashMap<&'static str, &'static Form>,
+}
+
+impl Default for FormRegistry {
+    fn default() -> Self {
+        let mut registry = FormRegistry {
+            forms: HashMap::new(),
+        };
+        registry.register(&Form::POINT);
+        registry.register(&Form::SPHERE);
+        registry.register(&Form::CIRCLE);
+        registry.register(&Form::BOX);
+        registry
+    }
+}
+
+impl FormRegistry {
+    pub fn new() -> FormRegistry {
+        FormRegistry::default()
+    }
+
+    pub fn register(&mut self, form: &'static Form) {
+        self.forms.insert(form.id, form);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&'static Form> {
+        self.forms.get(id).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_registry_has_the_built_in_forms() {
+        let registry = FormRegistry::new();
+        for form in [&Form::POINT, &Form::SPHERE, &Form::CIRCLE, &Form::BOX] {
+            assert_eq!(registry.get(form.id()).unwrap().id(), form.id());
+        }
+        assert!(registry.get("teapot").is_none());
+    }
+
+    #[test]
+    fn box_corners_are_within_the_bounding_radius() {
+        let corner = Form::BOX.half_extents().norm();
+        assert!((Form::BOX.bounding_radius() - corner).abs() < 1e-6);
+        assert!(Form::SPHERE.bounding_radius() < Form::BOX.bounding_radius());
+    }
+}
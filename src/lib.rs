@@ -1,5 +1,9 @@
 #![feature(portable_simd)]
+// Lane indexing is written for any width, which includes 1 when forcing scalar code
+#![cfg_attr(feature = "force_scalar", allow(clippy::modulo_one))]
 
-mod color;
+pub mod color;
 pub mod danmaku;
+pub mod error;
 pub mod form;
+pub mod simd_math;
@@ -0,0 +1,117 @@
+use std::simd::{cmp::SimdPartialOrd, num::SimdFloat, num::SimdInt, Select, Simd, StdFloat};
+
+use crate::danmaku::N;
+
+// Lane-wise math on vectors split over three SIMD columns, as the columns of danmaku are stored.
+// Inlined, so they are compiled for the same target features as the behaviors using them
+
+#[inline]
+pub fn simd_len3(x: Simd<f32, N>, y: Simd<f32, N>, z: Simd<f32, N>) -> Simd<f32, N> {
+    (x * x + y * y + z * z).sqrt()
+}
+
+// Vectors of length zero have no direction, and are left as zero instead of turning into NaN
+#[inline]
+pub fn simd_normalize3(
+    x: Simd<f32, N>,
+    y: Simd<f32, N>,
+    z: Simd<f32, N>,
+) -> (Simd<f32, N>, Simd<f32, N>, Simd<f32, N>) {
+    let len = simd_len3(x, y, z);
+    let inv_len = len
+        .simd_gt(Simd::splat(f32::EPSILON))
+        .select(Simd::splat(1.0) / len, Simd::splat(0.0));
+
+    (x * inv_len, y * inv_len, z * inv_len)
+}
+
+// Lerps packed 0xRRGGBB colors channel by channel, giving the same colors as
+// ColorHex::lerp_through_rgb for each lane
+#[inline]
+pub fn simd_lerp_rgb(a: Simd<i32, N>, b: Simd<i32, N>, t: f32) -> Simd<i32, N> {
+    let t = Simd::splat(t);
+    let channel = |color: Simd<i32, N>, shift: i32| {
+        ((color >> Simd::splat(shift)) & Simd::splat(0xFF)).cast::<f32>()
+    };
+    let lerp = |shift: i32| {
+        let from = channel(a, shift);
+        let to = channel(b, shift);
+        (from + (to - from) * t).round().cast::<i32>() << Simd::splat(shift)
+    };
+
+    lerp(16) | lerp(8) | lerp(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{ColorHex, ColorLerpMode};
+    use nalgebra::Vector3;
+
+    // Lane i holds vectors[i % len], so every lane is checked for any N
+    fn lanes(vectors: &[Vector3<f32>]) -> (Simd<f32, N>, Simd<f32, N>, Simd<f32, N>) {
+        let lane = |axis: usize| {
+            Simd::from_array(std::array::from_fn(|i| vectors[i % vectors.len()][axis]))
+        };
+        (lane(0), lane(1), lane(2))
+    }
+
+    fn vectors() -> Vec<Vector3<f32>> {
+        vec![
+            Vector3::new(3.0, 4.0, 0.0),
+            Vector3::zeros(),
+            Vector3::new(-1.0, 2.0, -2.0),
+            Vector3::new(1e-3, 0.0, 0.0),
+        ]
+    }
+
+    #[test]
+    fn len3_matches_scalar_norms() {
+        let vectors = vectors();
+        let (x, y, z) = lanes(&vectors);
+        let len = simd_len3(x, y, z);
+
+        for i in 0..N {
+            assert!((len[i] - vectors[i % vectors.len()].norm()).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn normalize3_matches_scalar_and_keeps_zero() {
+        let vectors = vectors();
+        let (x, y, z) = lanes(&vectors);
+        let (nx, ny, nz) = simd_normalize3(x, y, z);
+
+        for i in 0..N {
+            let normalized = Vector3::new(nx[i], ny[i], nz[i]);
+            let expected = vectors[i % vectors.len()]
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(Vector3::zeros);
+            assert!((normalized - expected).norm() < 1e-6, "lane {}", i);
+        }
+        // Zero stays zero instead of turning into NaN, even with a single lane
+        let zero = Simd::splat(0.0);
+        assert_eq!(simd_normalize3(zero, zero, zero), (zero, zero, zero));
+    }
+
+    #[test]
+    fn lerp_rgb_matches_the_scalar_lerp_in_every_lane() {
+        let from = [0xFF0000, 0x000000, 0x123456, 0xFFFFFF, 0x00FF7F];
+        let to = [0x0000FF, 0xFFFFFF, 0xFEDCBA, 0x000000, 0x7F00FF];
+        let chunk = |colors: &[i32], offset: usize| {
+            Simd::from_array(std::array::from_fn(|i| colors[(i + offset) % colors.len()]))
+        };
+
+        // Shifting the lanes between chunks pairs up different colors in each lane
+        for offset in 0..from.len() {
+            let (a, b) = (chunk(&from, offset), chunk(&to, offset));
+            for t in [0.0, 0.25, 0.5, 0.7, 1.0] {
+                let lerped = simd_lerp_rgb(a, b, t);
+                for i in 0..N {
+                    let expected = ColorHex(a[i]).lerp(ColorHex(b[i]), t, ColorLerpMode::Rgb);
+                    assert_eq!(ColorHex(lerped[i]), expected, "lane {} at {}", i, t);
+                }
+            }
+        }
+    }
+}